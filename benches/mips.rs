@@ -0,0 +1,125 @@
+// how fast the emulator runs, in millions of instructions a second (the Melem/s criterion reports).
+// the main workload is 8080EXM, which exercises every instruction and runs for billions of them, so a slice of it is
+// timed. it isn't part of the repository (see tests/roms/README.md); without it, only a loop of common instructions
+// is timed.
+//
+//     cargo bench --bench mips
+//
+// with the jit feature, the loop is timed through the JIT as well:
+//
+//     cargo bench --bench mips --features jit
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use criterion::Throughput;
+
+use emulator_8080::assembler;
+use emulator_8080::cpm;
+use emulator_8080::cpu;
+use emulator_8080::cpu::State8080;
+use emulator_8080::machine;
+use emulator_8080::machine::Bare;
+
+// the instructions run in each timed iteration
+const INSTRUCTIONS: u64 = 1_000_000;
+
+// moves through a buffer doing arithmetic, rotates, stack operations and calls, forever
+const LOOP: &str = "
+        ORG 100h
+        LXI SP,0f000h
+        LXI D,1234h
+start:  LXI H,buffer
+        MVI B,0
+inner:  MOV A,M
+        ADD B
+        XRA C
+        RLC
+        MOV M,A
+        INX H
+        DCR B
+        JNZ inner
+        DAD D
+        PUSH H
+        POP D
+        CALL next
+        JMP start
+next:   INR C
+        RET
+buffer: DS 256
+";
+
+fn exerciser() -> Option<Vec<u8>> {
+    let dir = match env::var("EMULATOR_8080_ROMS") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("roms"),
+    };
+    fs::read(dir.join("8080EXM.COM")).ok()
+}
+
+// runs a CP/M program for the number of instructions, throwing its output away
+fn run_com(state: &mut State8080, instructions: u64) {
+    let mut out = io::sink();
+    for _ in 0..instructions {
+        if state.pc == cpm::BDOS {
+            cpm::bdos(state, &mut out).unwrap();
+        }
+        cpu::emulate(state);
+    }
+}
+
+fn mips(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mips");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    group.sample_size(20);
+
+    let program = assembler::assemble(LOOP).unwrap().program;
+    let mut state = State8080::new();
+    cpm::load_com(&mut state, &program.bytes).unwrap();
+    group.bench_function("loop", |b| b.iter(|| run_com(&mut state, INSTRUCTIONS)));
+
+    // the same, through a machine, which is how the debugger and the runner step
+    let mut state = State8080::new();
+    cpm::load_com(&mut state, &program.bytes).unwrap();
+    group.bench_function("loop in a machine", |b| {
+        b.iter(|| {
+            for _ in 0..INSTRUCTIONS {
+                machine::step(&mut state, &mut Bare);
+            }
+        })
+    });
+
+    // the same, through the JIT. it runs a few thousand cycles at a time, as a host running frames would
+    #[cfg(all(feature = "jit", target_arch = "x86_64"))]
+    {
+        let mut state = State8080::new();
+        cpm::load_com(&mut state, &program.bytes).unwrap();
+        let mut jit = emulator_8080::jit::Jit::new().unwrap();
+        group.bench_function("loop with the jit", |b| {
+            b.iter(|| {
+                let end = jit.translated + jit.interpreted + INSTRUCTIONS;
+                while jit.translated + jit.interpreted < end {
+                    jit.run(&mut state, &mut Bare, 4_000);
+                }
+            })
+        });
+    }
+
+    match exerciser() {
+        Some(program) => {
+            // each iteration carries on where the last one stopped, so the whole bench moves through the exerciser
+            let mut state = State8080::new();
+            cpm::load_com(&mut state, &program).unwrap();
+            group.bench_function("8080EXM", |b| b.iter(|| run_com(&mut state, INSTRUCTIONS)));
+        },
+        None => println!("skipping 8080EXM: it isn't in tests/roms (see tests/roms/README.md)"),
+    }
+    group.finish();
+}
+
+criterion_group!(benches, mips);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+// runs arbitrary programs from arbitrary starting states, checking after each instruction that the state is still sound.
+// the first 12 bytes are the registers (a b c d e h l, the flags byte, sp and pc), and the rest is memory from address 0.
+#![no_main]
+
+use emulator_8080::cpu::emulate;
+use emulator_8080::cpu::State8080;
+use libfuzzer_sys::fuzz_target;
+
+const STEPS: usize = 1000;
+
+// every instruction takes one of these numbers of cycles
+const CYCLE_COUNTS: [u8; 9] = [4, 5, 7, 10, 11, 13, 16, 17, 18];
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 12 {
+        return;
+    }
+    let (registers, image) = data.split_at(12);
+    let image = &image[..image.len().min(0x10000)];
+
+    let mut state = State8080::new();
+    state.a = registers[0];
+    state.b = registers[1];
+    state.c = registers[2];
+    state.d = registers[3];
+    state.e = registers[4];
+    state.h = registers[5];
+    state.l = registers[6];
+    state.set_psw(registers[7]);
+    state.sp = ((registers[9] as u16) << 8) | (registers[8] as u16);
+    state.pc = ((registers[11] as u16) << 8) | (registers[10] as u16);
+    state.memory[..image.len()].copy_from_slice(image);
+
+    for _ in 0..STEPS {
+        let halted = state.halted;
+        let pc = state.pc;
+        let cycles = emulate(&mut state);
+
+        assert!(CYCLE_COUNTS.contains(&cycles), "{} cycles", cycles);
+        assert_eq!(state.memory.len(), 0x10000);
+        // the flags byte always has bit 1 set, and bits 3 and 5 clear
+        assert_eq!(state.get_psw() & 0x2a, 0x02);
+        if halted {
+            // only an interrupt ends HLT
+            assert!(state.halted);
+            assert_eq!(state.pc, pc);
+            assert_eq!(cycles, 4);
+            break;
+        }
+    }
+});
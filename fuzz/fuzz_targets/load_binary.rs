@@ -0,0 +1,17 @@
+// feeds arbitrary bytes to the binary loader, at the origin given by the first two
+#![no_main]
+
+use emulator_8080::cpu::State8080;
+use emulator_8080::loader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let origin = ((data[1] as u16) << 8) | (data[0] as u16);
+    match loader::binary(&data[2..], origin) {
+        Ok(program) => program.load(&mut State8080::new()),
+        Err(_) => assert!(origin as usize + data.len() - 2 > 0x10000),
+    }
+});
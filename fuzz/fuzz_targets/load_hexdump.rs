@@ -0,0 +1,14 @@
+// feeds arbitrary text to the hexdump loader, and loads whatever it accepts
+#![no_main]
+
+use emulator_8080::cpu::State8080;
+use emulator_8080::loader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    if let Ok(program) = loader::hexdump(&text) {
+        assert!(program.origin as usize + program.bytes.len() <= 0x10000);
+        program.load(&mut State8080::new());
+    }
+});
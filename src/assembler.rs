@@ -0,0 +1,658 @@
+// a two-pass assembler for Intel 8080 assembly.
+// the first pass works out the address of every label, and the second writes the bytes now that every label is known.
+//
+// it understands labels (name: or a name in the first column), local labels that start with a . and belong to the
+// label before them, the directives ORG, DB, DW, DS, EQU and END, and expressions with + - * / MOD, AND OR XOR NOT,
+// SHL SHR, HIGH and LOW, where $ is the address of the current line.
+// numbers are decimal unless they end in h (hex), b (binary) or o/q (octal), or start with 0x.
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::disassembler;
+use crate::loader::Program;
+
+pub struct Assembly {
+    pub program: Program,
+    pub listing: String,
+    pub symbols: BTreeMap<String, u16>,
+}
+
+impl Assembly {
+    // the symbol file: each symbol's value and name, in order of value
+    pub fn symbol_file(&self) -> String {
+        let mut symbols: Vec<(&String, &u16)> = self.symbols.iter().collect();
+        symbols.sort_by_key(|(name, value)| (**value, (*name).clone()));
+        symbols.iter().map(|(name, value)| format!("{:04x} {}\n", value, name)).collect()
+    }
+}
+
+// assembles the source, returning every error found (one per line) if there are any
+pub fn assemble(source: &str) -> Result<Assembly, String> {
+    let mut assembler = Assembler::new();
+    let lines: Vec<Line> = source.lines().enumerate().map(|(n, text)| Line::parse(n + 1, text)).collect();
+
+    assembler.pass(&lines, 1);
+    assembler.resolve_equates();
+    assembler.pass(&lines, 2);
+    if !assembler.errors.is_empty() {
+        let errors: Vec<String> = assembler.errors.iter().map(|(number, error)| format!("line {}: {}", number, error)).collect();
+        return Err(errors.join("\n"));
+    }
+
+    let program = match (assembler.output.keys().next(), assembler.output.keys().next_back()) {
+        (Some(first), Some(last)) => {
+            let mut bytes = vec![0; (*last - *first) as usize + 1];
+            for (addr, byte) in assembler.output.iter() {
+                bytes[(*addr - *first) as usize] = *byte;
+            }
+            Program { origin: *first, bytes }
+        },
+        _ => Program { origin: 0, bytes: Vec::new() },
+    };
+    let symbols = assembler.symbols.iter().map(|(name, value)| (name.clone(), *value as u16)).collect();
+    Ok(Assembly { program, listing: assembler.listing.join("\n") + "\n", symbols })
+}
+
+// one line of source, split into its parts
+struct Line {
+    number: usize,
+    text: String,
+    label: Option<String>,
+    op: Option<String>, // upper case
+    operands: Vec<String>,
+    error: Option<String>,
+}
+
+const DIRECTIVES: [&str; 6] = ["ORG", "DB", "DW", "DS", "EQU", "END"];
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '?' || c == '@'
+}
+
+fn is_mnemonic(word: &str) -> bool {
+    let word = word.to_uppercase();
+    DIRECTIVES.contains(&word.as_str()) || (0..=255u8).any(|opcode| disassembler::template(opcode).split(' ').next() == Some(&word))
+}
+
+// removes the comment from a line, leaving any ; inside quotes alone
+fn strip_comment(text: &str) -> &str {
+    let mut quote: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {},
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == ';' => return &text[..i],
+            None => {},
+        }
+    }
+    text
+}
+
+// splits operands at the commas that aren't inside quotes
+fn split_operands(text: &str) -> Vec<String> {
+    let mut operands: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in text.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {},
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == ',' => {
+                operands.push(current.trim().to_string());
+                current = String::new();
+                continue;
+            },
+            None => {},
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() || !operands.is_empty() {
+        operands.push(current.trim().to_string());
+    }
+    operands
+}
+
+impl Line {
+    fn parse(number: usize, text: &str) -> Line {
+        let mut line = Line { number, text: text.to_string(), label: None, op: None, operands: Vec::new(), error: None };
+        let code = strip_comment(text);
+        let mut rest = code.trim_start();
+        let first_column = !code.starts_with(char::is_whitespace);
+
+        let word_end = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+        let word = &rest[..word_end];
+        if !word.is_empty() {
+            let after = &rest[word_end..];
+            let next_word = after.trim_start().split(|c: char| !is_name_char(c)).next().unwrap_or("");
+            if let Some(after_colon) = after.strip_prefix(':') {
+                line.label = Some(word.to_string());
+                rest = after_colon.trim_start();
+            } else if next_word.eq_ignore_ascii_case("EQU") || (first_column && !is_mnemonic(word)) {
+                line.label = Some(word.to_string());
+                rest = after.trim_start();
+            }
+        }
+        if let Some(label) = &line.label {
+            if label.starts_with(|c: char| c.is_ascii_digit()) {
+                line.error = Some(format!("'{}' isn't a valid label", label));
+            }
+        }
+
+        let op_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let op = &rest[..op_end];
+        if !op.is_empty() {
+            line.op = Some(op.to_uppercase());
+            line.operands = split_operands(&rest[op_end..]);
+        }
+        line
+    }
+}
+
+enum Error {
+    Undefined(String),
+    Invalid(String),
+}
+
+impl Error {
+    fn message(self) -> String {
+        match self {
+            Error::Undefined(name) => format!("'{}' isn't defined", name),
+            Error::Invalid(message) => message,
+        }
+    }
+}
+
+struct Assembler {
+    pass: u8,
+    pc: u32,
+    scope: String, // the last label not starting with . which local labels belong to
+    symbols: BTreeMap<String, i64>,
+    equates: Vec<(String, String, u16, usize)>, // EQUs whose values weren't known in the first pass: name, expression, $, line
+    output: BTreeMap<u16, u8>,
+    listing: Vec<String>,
+    errors: BTreeMap<usize, String>, // the first error on each line
+    opcodes: HashMap<&'static str, Vec<(u8, Vec<&'static str>)>>, // each mnemonic's opcodes, with their operands
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        let mut opcodes: HashMap<&'static str, Vec<(u8, Vec<&'static str>)>> = HashMap::new();
+        for opcode in 0..=255u8 {
+            let template = disassembler::template(opcode);
+            if template.starts_with('*') {
+                continue;
+            }
+            let (mnemonic, operands) = template.split_once(' ').unwrap_or((template, ""));
+            let operands = if operands.is_empty() { Vec::new() } else { operands.split(',').collect() };
+            opcodes.entry(mnemonic).or_default().push((opcode, operands));
+        }
+        Assembler {
+            pass: 1,
+            pc: 0,
+            scope: String::new(),
+            symbols: BTreeMap::new(),
+            equates: Vec::new(),
+            output: BTreeMap::new(),
+            listing: Vec::new(),
+            errors: BTreeMap::new(),
+            opcodes,
+        }
+    }
+
+    // records an error, unless the line already has one
+    fn error(&mut self, number: usize, error: Error) {
+        self.errors.entry(number).or_insert(error.message());
+    }
+
+    // the full name of a symbol: local labels are prefixed with the label they belong to
+    fn qualify(&self, name: &str) -> String {
+        if name.starts_with('.') {
+            format!("{}{}", self.scope, name).to_uppercase()
+        } else {
+            name.to_uppercase()
+        }
+    }
+
+    fn define(&mut self, name: &str, value: i64) -> Result<(), Error> {
+        let name = self.qualify(name);
+        if self.symbols.contains_key(&name) {
+            return Err(Error::Invalid(format!("'{}' is defined more than once", name)));
+        }
+        self.symbols.insert(name, value);
+        Ok(())
+    }
+
+    fn eval(&self, text: &str, here: u16) -> Result<i64, Error> {
+        let tokens = tokenize(text)?;
+        let mut parser = Expression { tokens: &tokens, pos: 0, assembler: self, here };
+        let value = parser.or()?;
+        if parser.pos != tokens.len() {
+            return Err(Error::Invalid(format!("can't understand '{}'", text)));
+        }
+        Ok(value)
+    }
+
+    // evaluates an expression, which in the first pass may use labels that come later
+    fn value(&self, text: &str, here: u16) -> Result<i64, Error> {
+        match self.eval(text, here) {
+            Err(Error::Undefined(_)) if self.pass == 1 => Ok(0),
+            result => result,
+        }
+    }
+
+    fn byte(&self, text: &str, here: u16) -> Result<u8, Error> {
+        let value = self.value(text, here)?;
+        if !(-128..=255).contains(&value) {
+            return Err(Error::Invalid(format!("{} doesn't fit in a byte", value)));
+        }
+        Ok(value as u8)
+    }
+
+    fn word(&self, text: &str, here: u16) -> Result<u16, Error> {
+        let value = self.value(text, here)?;
+        if !(-32768..=65535).contains(&value) {
+            return Err(Error::Invalid(format!("{} doesn't fit in a word", value)));
+        }
+        Ok(value as u16)
+    }
+
+    fn pass(&mut self, lines: &[Line], pass: u8) {
+        self.pass = pass;
+        self.pc = 0;
+        self.scope = String::new();
+        for line in lines.iter() {
+            let start = self.pc;
+            let result = match &line.error {
+                Some(error) => Err(Error::Invalid(error.clone())),
+                None => self.line(line),
+            };
+            match result {
+                Ok(bytes) => {
+                    if pass == 2 {
+                        self.list(line, start as u16, &bytes);
+                    }
+                },
+                Err(error) => self.error(line.number, error),
+            }
+            if line.op.as_deref() == Some("END") {
+                break;
+            }
+        }
+    }
+
+    // assembles one line, and returns the bytes it produced
+    fn line(&mut self, line: &Line) -> Result<Vec<u8>, Error> {
+        let here = self.pc as u16;
+        let op = line.op.as_deref().unwrap_or("");
+
+        if let Some(label) = &line.label {
+            if op != "EQU" {
+                if !label.starts_with('.') {
+                    self.scope = label.to_uppercase();
+                }
+                if self.pass == 1 {
+                    self.define(label, here as i64)?;
+                }
+            }
+        }
+
+        let operands: Vec<&str> = line.operands.iter().map(|operand| operand.as_str()).collect();
+        let one = || match operands.as_slice() {
+            [operand] => Ok(*operand),
+            _ => Err(Error::Invalid(format!("{} takes one operand", op))),
+        };
+        let mut bytes: Vec<u8> = Vec::new();
+        match op {
+            "" => {},
+            "END" => {},
+            "ORG" => {
+                self.pc = self.eval(one()?, here)? as u16 as u32;
+            },
+            "DS" => {
+                let size = self.eval(one()?, here)?;
+                if size < 0 {
+                    return Err(Error::Invalid("DS can't reserve a negative size".to_string()));
+                }
+                self.pc += size as u32;
+            },
+            "EQU" => {
+                let Some(label) = &line.label else {
+                    return Err(Error::Invalid("EQU needs a name".to_string()));
+                };
+                let expression = one()?;
+                if self.pass == 1 {
+                    match self.eval(expression, here) {
+                        Ok(value) => self.define(label, value)?,
+                        Err(Error::Undefined(_)) => {
+                            let name = self.qualify(label);
+                            self.equates.push((name, expression.to_string(), here, line.number));
+                        },
+                        Err(error) => return Err(error),
+                    }
+                }
+            },
+            "DB" => {
+                for operand in operands.iter() {
+                    match string(operand) {
+                        Some(s) => bytes.extend(s.bytes()),
+                        None => bytes.push(self.byte(operand, here)?),
+                    }
+                }
+            },
+            "DW" => {
+                for operand in operands.iter() {
+                    let word = self.word(operand, here)?;
+                    bytes.push(word as u8);
+                    bytes.push((word >> 8) as u8);
+                }
+            },
+            "RST" => {
+                let n = self.value(one()?, here)?;
+                if !(0..8).contains(&n) {
+                    return Err(Error::Invalid(format!("RST {} doesn't exist", n)));
+                }
+                bytes.push(0xc7 | (n as u8) << 3);
+            },
+            _ => bytes = self.instruction(op, &operands, here)?,
+        }
+
+        if self.pass == 2 {
+            for (i, byte) in bytes.iter().enumerate() {
+                let addr = self.pc as usize + i;
+                if addr > 0xffff {
+                    return Err(Error::Invalid("the program runs past the end of memory".to_string()));
+                }
+                if self.output.insert(addr as u16, *byte).is_some() {
+                    return Err(Error::Invalid(format!("{:04x}h is written more than once", addr)));
+                }
+            }
+        }
+        self.pc += bytes.len() as u32;
+        Ok(bytes)
+    }
+
+    fn instruction(&self, op: &str, operands: &[&str], here: u16) -> Result<Vec<u8>, Error> {
+        let Some(forms) = self.opcodes.get(op) else {
+            return Err(Error::Invalid(format!("unknown instruction '{}'", op)));
+        };
+        // registers have to match the form exactly, anything else is an expression
+        let matches = |template: &[&str]| {
+            template.len() == operands.len()
+                && template.iter().zip(operands.iter()).all(|(t, o)| matches!(*t, "D8" | "D16" | "adr") || t.eq_ignore_ascii_case(o))
+        };
+        let Some((opcode, template)) = forms.iter().find(|(_, template)| matches(template)) else {
+            return Err(Error::Invalid(format!("{} can't take '{}'", op, operands.join(","))));
+        };
+
+        let mut bytes = vec![*opcode];
+        for (t, operand) in template.iter().zip(operands.iter()) {
+            match *t {
+                "D8" => bytes.push(self.byte(operand, here)?),
+                "D16" | "adr" => {
+                    let word = self.word(operand, here)?;
+                    bytes.push(word as u8);
+                    bytes.push((word >> 8) as u8);
+                },
+                _ => {},
+            }
+        }
+        Ok(bytes)
+    }
+
+    // works out the EQUs that used symbols defined after them, repeating while that defines more of them
+    fn resolve_equates(&mut self) {
+        loop {
+            let pending = std::mem::take(&mut self.equates);
+            let count = pending.len();
+            for (name, expression, here, number) in pending {
+                match self.eval(&expression, here) {
+                    Ok(value) => {
+                        self.symbols.insert(name, value);
+                    },
+                    Err(Error::Undefined(_)) => self.equates.push((name, expression, here, number)),
+                    Err(error) => self.error(number, error),
+                }
+            }
+            if self.equates.is_empty() || self.equates.len() == count {
+                break;
+            }
+        }
+        for (_, expression, here, number) in std::mem::take(&mut self.equates) {
+            if let Err(error) = self.eval(&expression, here) {
+                self.error(number, error);
+            }
+        }
+    }
+
+    // adds a line to the listing: the address, up to 4 of the bytes, and the source.
+    // the rest of the bytes go on lines of their own underneath.
+    fn list(&mut self, line: &Line, addr: u16, bytes: &[u8]) {
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" ");
+        let prefix = match line.op.as_deref() {
+            Some("EQU") => {
+                let value = line.label.as_ref().and_then(|label| self.symbols.get(&self.qualify(label))).copied().unwrap_or(0);
+                format!("      = {:04x}", value as u16)
+            },
+            // the address that ORG moved to
+            Some("ORG") => format!("{:04x}", self.pc),
+            Some("END") => String::new(),
+            _ if bytes.is_empty() && line.op.is_none() && line.label.is_none() => String::new(),
+            _ => format!("{:04x}  {}", addr, hex(&bytes[..bytes.len().min(4)])),
+        };
+        self.listing.push(format!("{:<18}{:>5}  {}", prefix, line.number, line.text).trim_end().to_string());
+        for (i, chunk) in bytes.chunks(4).enumerate().skip(1) {
+            self.listing.push(format!("{:04x}  {}", addr as usize + i * 4, hex(chunk)));
+        }
+    }
+}
+
+// the text of an operand that is a quoted string, other than a single character, which is a number
+fn string(operand: &str) -> Option<&str> {
+    let quote = operand.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let inner = operand[1..].strip_suffix(quote)?;
+    if inner.contains(quote) || inner.chars().count() == 1 {
+        return None;
+    }
+    Some(inner)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String), // upper case, including the operator words like MOD and HIGH
+    Here, // $
+    Symbol(&'static str),
+}
+
+fn number(text: &str) -> Result<i64, Error> {
+    let invalid = || Error::Invalid(format!("'{}' isn't a number", text));
+    let lower = text.to_lowercase();
+    let (digits, radix) = if let Some(hex) = lower.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(hex) = lower.strip_suffix('h') {
+        (hex, 16)
+    } else if let Some(binary) = lower.strip_suffix('b') {
+        (binary, 2)
+    } else if let Some(octal) = lower.strip_suffix('o').or(lower.strip_suffix('q')) {
+        (octal, 8)
+    } else if let Some(decimal) = lower.strip_suffix('d') {
+        (decimal, 10)
+    } else {
+        (lower.as_str(), 10)
+    };
+    i64::from_str_radix(digits, radix).map_err(|_| invalid())
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            tokens.push(Token::Number(number(&chars[start..i].iter().collect::<String>())?));
+        } else if c == '\'' || c == '"' {
+            // a character constant, or two characters as a word
+            let end = chars[i + 1..].iter().position(|d| *d == c).ok_or(Error::Invalid("unterminated quote".to_string()))?;
+            let inner = &chars[i + 1..i + 1 + end];
+            let value = match inner {
+                [a] => *a as i64,
+                [a, b] => ((*a as i64) << 8) | (*b as i64),
+                _ => return Err(Error::Invalid("a character constant has one or two characters".to_string())),
+            };
+            tokens.push(Token::Number(value));
+            i += end + 2;
+        } else if c == '$' && !chars.get(i + 1).is_some_and(|d| is_name_char(*d)) {
+            tokens.push(Token::Here);
+            i += 1;
+        } else if is_name_char(c) || c == '$' {
+            let start = i;
+            while i < chars.len() && (is_name_char(chars[i]) || chars[i] == '$') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect::<String>().to_uppercase()));
+        } else {
+            let two: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = match two.as_str() {
+                "<<" => "<<",
+                ">>" => ">>",
+                _ => match c {
+                    '+' => "+",
+                    '-' => "-",
+                    '*' => "*",
+                    '/' => "/",
+                    '%' => "%",
+                    '&' => "&",
+                    '|' => "|",
+                    '^' => "^",
+                    '~' => "~",
+                    '(' => "(",
+                    ')' => ")",
+                    _ => return Err(Error::Invalid(format!("unexpected '{}'", c))),
+                },
+            };
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+// evaluates an expression by recursive descent, one function per level of precedence
+struct Expression<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    assembler: &'a Assembler,
+    here: u16,
+}
+
+impl Expression<'_> {
+    // takes the next token if it is one of the operators given, as a symbol or a word
+    fn operator(&mut self, operators: &[&str]) -> Option<String> {
+        let found = match self.tokens.get(self.pos)? {
+            Token::Symbol(symbol) if operators.contains(symbol) => symbol.to_string(),
+            Token::Name(name) if operators.contains(&name.as_str()) => name.clone(),
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(found)
+    }
+
+    fn or(&mut self) -> Result<i64, Error> {
+        let mut value = self.and()?;
+        while let Some(op) = self.operator(&["|", "^", "OR", "XOR"]) {
+            let rhs = self.and()?;
+            value = if op == "|" || op == "OR" { value | rhs } else { value ^ rhs };
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<i64, Error> {
+        let mut value = self.not()?;
+        while self.operator(&["&", "AND"]).is_some() {
+            value &= self.not()?;
+        }
+        Ok(value)
+    }
+
+    fn not(&mut self) -> Result<i64, Error> {
+        if self.operator(&["NOT"]).is_some() {
+            return Ok(!self.not()?);
+        }
+        self.shift()
+    }
+
+    fn shift(&mut self) -> Result<i64, Error> {
+        let mut value = self.sum()?;
+        while let Some(op) = self.operator(&["<<", ">>", "SHL", "SHR"]) {
+            let rhs = self.sum()?.clamp(0, 63);
+            value = if op == "<<" || op == "SHL" { value << rhs } else { value >> rhs };
+        }
+        Ok(value)
+    }
+
+    fn sum(&mut self) -> Result<i64, Error> {
+        let mut value = self.product()?;
+        while let Some(op) = self.operator(&["+", "-"]) {
+            let rhs = self.product()?;
+            value = if op == "+" { value.wrapping_add(rhs) } else { value.wrapping_sub(rhs) };
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<i64, Error> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.operator(&["*", "/", "%", "MOD"]) {
+            let rhs = self.unary()?;
+            if op != "*" && rhs == 0 {
+                return Err(Error::Invalid("division by zero".to_string()));
+            }
+            value = match op.as_str() {
+                "*" => value.wrapping_mul(rhs),
+                "/" => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<i64, Error> {
+        match self.operator(&["-", "+", "~", "HIGH", "LOW"]).as_deref() {
+            Some("-") => Ok(self.unary()?.wrapping_neg()),
+            Some("+") => self.unary(),
+            Some("~") => Ok(!self.unary()?),
+            Some("HIGH") => Ok((self.unary()? >> 8) & 0xff),
+            Some("LOW") => Ok(self.unary()? & 0xff),
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<i64, Error> {
+        let token = self.tokens.get(self.pos).cloned().ok_or(Error::Invalid("missing value".to_string()))?;
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(value),
+            Token::Here => Ok(self.here as i64),
+            Token::Name(name) => {
+                let name = self.assembler.qualify(&name);
+                self.assembler.symbols.get(&name).copied().ok_or(Error::Undefined(name))
+            },
+            Token::Symbol("(") => {
+                let value = self.or()?;
+                if self.operator(&[")"]).is_none() {
+                    return Err(Error::Invalid("missing )".to_string()));
+                }
+                Ok(value)
+            },
+            Token::Symbol(symbol) => Err(Error::Invalid(format!("unexpected '{}'", symbol))),
+        }
+    }
+}
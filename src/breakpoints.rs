@@ -0,0 +1,390 @@
+// the breakpoints the debugger stops at: addresses the pc reaches, ranges of memory that are read or written,
+// and ports that are read or written by IN and OUT. each one can have a condition over the registers, flags and memory,
+// like a == 10 && cy, and counts the times it has been hit.
+// numbers in conditions are hex, like everywhere else in the debugger, but have to start with a digit (0ah, 0x0a)
+// so they can't be mistaken for registers.
+use std::fmt;
+
+use crate::cpu::Access;
+use crate::cpu::State8080;
+use crate::debugger::parse_hex;
+
+// what makes a breakpoint stop
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kind {
+    Exec(u16), // the pc reaching an address
+    Watch { start: u16, end: u16, read: bool, write: bool }, // an instruction reading or writing memory in start..=end
+    Port { input: bool, port: u8 }, // an IN or OUT on a port
+}
+
+pub struct Breakpoint {
+    pub id: usize,
+    pub kind: Kind,
+    pub condition: Option<Condition>,
+    pub hits: u64, // the times it has been reached with its condition true
+    pub ignore: u64, // the number of hits to let pass before stopping
+    pub enabled: bool,
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Kind::Exec(addr) => write!(f, "break {:04x}", addr)?,
+            Kind::Watch { start, end, read, write } => {
+                let mode = match (read, write) {
+                    (true, true) => "access",
+                    (true, false) => "read",
+                    _ => "write",
+                };
+                if start == end {
+                    write!(f, "watch {:04x} {}", start, mode)?
+                } else {
+                    write!(f, "watch {:04x}-{:04x} {}", start, end, mode)?
+                }
+            },
+            Kind::Port { input, port } => write!(f, "port {} {:02x}", if input { "in" } else { "out" }, port)?,
+        }
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition.text)?;
+        }
+        Ok(())
+    }
+}
+
+// why a breakpoint stopped the program
+pub struct Stop {
+    pub id: usize,
+    pub kind: Kind,
+    pub hits: u64,
+    pub event: String, // what happened, like "2002 written with 02"
+    pub access: Option<Access>, // the memory access that hit a watchpoint
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let times = if self.hits == 1 { "once".to_string() } else { format!("{} times", self.hits) };
+        write!(f, "breakpoint {}: {}, hit {}", self.id, self.event, times)
+    }
+}
+
+pub struct Breakpoints {
+    pub list: Vec<Breakpoint>,
+    next_id: usize,
+}
+
+impl Default for Breakpoints {
+    fn default() -> Breakpoints {
+        Breakpoints::new()
+    }
+}
+
+impl Breakpoints {
+    pub fn new() -> Breakpoints {
+        Breakpoints { list: Vec::new(), next_id: 1 }
+    }
+
+    // adds a breakpoint, and returns its id
+    pub fn add(&mut self, kind: Kind, condition: Option<Condition>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.list.push(Breakpoint { id, kind, condition, hits: 0, ignore: 0, enabled: true });
+        id
+    }
+
+    pub fn get(&mut self, id: usize) -> Result<&mut Breakpoint, String> {
+        self.list.iter_mut().find(|b| b.id == id).ok_or(format!("there is no breakpoint {}", id))
+    }
+
+    pub fn delete(&mut self, id: usize) -> Result<(), String> {
+        self.get(id)?;
+        self.list.retain(|b| b.id != id);
+        Ok(())
+    }
+
+    // the id of a breakpoint that stops at the same thing
+    pub fn find(&self, kind: &Kind) -> Option<usize> {
+        self.list.iter().find(|b| b.kind == *kind).map(|b| b.id)
+    }
+
+    // whether any watchpoint is enabled, which is when memory accesses need to be recorded
+    pub fn watching(&self) -> bool {
+        self.list.iter().any(|b| b.enabled && matches!(b.kind, Kind::Watch { .. }))
+    }
+
+    // checks the breakpoints on the instruction about to run: its address, and the port if it is an IN or OUT
+    pub fn before(&mut self, state: &State8080) -> Option<Stop> {
+        if state.halted {
+            return None;
+        }
+        let opcode = state.memory[state.pc as usize];
+        let port = state.memory[state.pc.wrapping_add(1) as usize];
+        for breakpoint in self.list.iter_mut() {
+            let event = match breakpoint.kind {
+                Kind::Exec(addr) if addr == state.pc => format!("at {:04x}", addr),
+                Kind::Port { input: true, port: p } if opcode == 0xdb && port == p => format!("IN from port {:02x}", p),
+                Kind::Port { input: false, port: p } if opcode == 0xd3 && port == p => {
+                    format!("OUT {:02x} to port {:02x}", state.a, p)
+                },
+                _ => continue,
+            };
+            if let Some(stop) = breakpoint.hit(state, event, None) {
+                return Some(stop);
+            }
+        }
+        None
+    }
+
+    // checks the watchpoints against the memory the last instruction read and wrote
+    pub fn after(&mut self, state: &State8080, accesses: &[Access]) -> Option<Stop> {
+        for breakpoint in self.list.iter_mut() {
+            let Kind::Watch { start, end, read, write } = breakpoint.kind else {
+                continue;
+            };
+            let access = accesses.iter().find(|a| (start..=end).contains(&a.addr) && if a.write { write } else { read });
+            let Some(access) = access else {
+                continue;
+            };
+            let event = if access.write {
+                format!("{:04x} written with {:02x}", access.addr, access.value)
+            } else {
+                format!("{:04x} read as {:02x}", access.addr, access.value)
+            };
+            if let Some(stop) = breakpoint.hit(state, event, Some(*access)) {
+                return Some(stop);
+            }
+        }
+        None
+    }
+}
+
+impl Breakpoint {
+    // counts a hit if the breakpoint is enabled and its condition holds, and says why it stopped once the ignore count
+    // has been passed
+    fn hit(&mut self, state: &State8080, event: String, access: Option<Access>) -> Option<Stop> {
+        if !self.enabled || !self.condition.as_ref().is_none_or(|c| c.holds(state)) {
+            return None;
+        }
+        self.hits += 1;
+        if self.hits <= self.ignore {
+            return None;
+        }
+        Some(Stop { id: self.id, kind: self.kind.clone(), hits: self.hits, event, access })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Sub,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Register(String),
+    Memory(Box<Expr>), // [addr]
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+// a condition, kept with the text it was written as so it can be shown again
+#[derive(Debug)]
+pub struct Condition {
+    pub text: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Condition, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expression(0)?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected '{}' in the condition", token));
+        }
+        Ok(Condition { text: text.trim().to_string(), expr })
+    }
+
+    pub fn holds(&self, state: &State8080) -> bool {
+        evaluate(&self.expr, state) != 0
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphanumeric() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect::<String>().to_lowercase());
+        } else {
+            let pair: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            if ["==", "!=", "<=", ">=", "&&", "||"].contains(&pair.as_str()) {
+                tokens.push(pair);
+                i += 2;
+            } else if "<>+-&|^!()[]".contains(c) {
+                tokens.push(c.to_string());
+                i += 1;
+            } else {
+                return Err(format!("unexpected '{}' in the condition", c));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// the binary operators, with their precedence, lowest first
+fn operator(token: &str) -> Option<(Op, u8)> {
+    Some(match token {
+        "||" => (Op::Or, 0),
+        "&&" => (Op::And, 1),
+        "==" => (Op::Eq, 2),
+        "!=" => (Op::Ne, 2),
+        "<" => (Op::Lt, 2),
+        "<=" => (Op::Le, 2),
+        ">" => (Op::Gt, 2),
+        ">=" => (Op::Ge, 2),
+        "|" => (Op::BitOr, 3),
+        "^" => (Op::BitXor, 4),
+        "&" => (Op::BitAnd, 5),
+        "+" => (Op::Add, 6),
+        "-" => (Op::Sub, 6),
+        _ => return None,
+    })
+}
+
+const REGISTERS: [&str; 18] = ["a", "b", "c", "d", "e", "h", "l", "bc", "de", "hl", "sp", "pc", "psw", "s", "z", "ac", "p", "cy"];
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, wanted: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == wanted => Ok(()),
+            _ => Err(format!("the condition is missing a '{}'", wanted)),
+        }
+    }
+
+    // parses operators of at least the given precedence, all of them left-associative
+    fn expression(&mut self, precedence: u8) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some((op, p)) = self.tokens.get(self.pos).and_then(|t| operator(t)) {
+            if p < precedence {
+                break;
+            }
+            self.pos += 1;
+            let right = self.expression(p + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.next() else {
+            return Err("the condition ends too soon".to_string());
+        };
+        match token.as_str() {
+            "!" => Ok(Expr::Not(Box::new(self.unary()?))),
+            "-" => Ok(Expr::Negate(Box::new(self.unary()?))),
+            "(" => {
+                let expr = self.expression(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            "[" => {
+                let expr = self.expression(0)?;
+                self.expect("]")?;
+                Ok(Expr::Memory(Box::new(expr)))
+            },
+            // m is the byte hl points at, as in MOV A,M
+            "m" => Ok(Expr::Memory(Box::new(Expr::Register("hl".to_string())))),
+            _ if token.starts_with(|c: char| c.is_ascii_digit()) => Ok(Expr::Number(parse_hex(&token)? as i64)),
+            _ if REGISTERS.contains(&token.as_str()) => Ok(Expr::Register(token)),
+            _ => Err(format!("'{}' isn't a register, flag or number", token)),
+        }
+    }
+}
+
+fn evaluate(expr: &Expr, state: &State8080) -> i64 {
+    let pair = |high: u8, low: u8| ((high as i64) << 8) | low as i64;
+    match expr {
+        Expr::Number(n) => *n,
+        Expr::Register(name) => match name.as_str() {
+            "a" => state.a as i64,
+            "b" => state.b as i64,
+            "c" => state.c as i64,
+            "d" => state.d as i64,
+            "e" => state.e as i64,
+            "h" => state.h as i64,
+            "l" => state.l as i64,
+            "bc" => pair(state.b, state.c),
+            "de" => pair(state.d, state.e),
+            "hl" => pair(state.h, state.l),
+            "sp" => state.sp as i64,
+            "pc" => state.pc as i64,
+            "psw" => pair(state.a, state.get_psw()),
+            "s" => state.cc.s as i64,
+            "z" => state.cc.z as i64,
+            "ac" => state.cc.ac as i64,
+            "p" => state.cc.p as i64,
+            "cy" => state.cc.cy as i64,
+            _ => unreachable!(),
+        },
+        // reads memory directly, so that checking a condition doesn't show up as an access
+        Expr::Memory(addr) => state.memory[evaluate(addr, state) as u16 as usize] as i64,
+        Expr::Not(e) => (evaluate(e, state) == 0) as i64,
+        Expr::Negate(e) => evaluate(e, state).wrapping_neg(),
+        Expr::Binary(op, left, right) => {
+            let left = evaluate(left, state);
+            // && and || don't need the right side if the left decides it
+            match op {
+                Op::And if left == 0 => return 0,
+                Op::Or if left != 0 => return 1,
+                _ => {},
+            }
+            let right = evaluate(right, state);
+            match op {
+                Op::Or | Op::And => (right != 0) as i64,
+                Op::Eq => (left == right) as i64,
+                Op::Ne => (left != right) as i64,
+                Op::Lt => (left < right) as i64,
+                Op::Le => (left <= right) as i64,
+                Op::Gt => (left > right) as i64,
+                Op::Ge => (left >= right) as i64,
+                Op::BitOr => left | right,
+                Op::BitXor => left ^ right,
+                Op::BitAnd => left & right,
+                Op::Add => left.wrapping_add(right),
+                Op::Sub => left.wrapping_sub(right),
+            }
+        },
+    }
+}
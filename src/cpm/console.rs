@@ -0,0 +1,109 @@
+// the CP/M console, connected to stdin and stdout.
+// CP/M programs poll the console for a key, so stdin is read on its own thread and the keys are queued.
+use std::io;
+use std::io::BufWriter;
+use std::io::IsTerminal;
+use std::io::Read;
+use std::io::Stdout;
+use std::io::Write;
+use std::process::Command;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::TryRecvError;
+use std::thread;
+
+pub struct Console {
+    keys: Receiver<u8>,
+    pending: Option<u8>, // a key seen by status() but not yet read
+    closed: bool, // true once stdin has ended
+    out: BufWriter<Stdout>,
+    terminal: bool, // true when stdin is a terminal that was switched to unbuffered input
+}
+
+impl Console {
+    pub fn new() -> Console {
+        // CP/M echoes keys itself and expects them one at a time, not a line at a time
+        let terminal = io::stdin().is_terminal() && stty(&["-icanon", "-echo", "min", "1"]);
+
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut byte = [0u8; 1];
+            while let Ok(1) = stdin.read(&mut byte) {
+                // the enter key gives a line feed, but CP/M expects a carriage return
+                let key = if byte[0] == b'\n' { b'\r' } else { byte[0] };
+                if sender.send(key).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Console {
+            keys,
+            pending: None,
+            closed: false,
+            out: BufWriter::new(io::stdout()),
+            terminal,
+        }
+    }
+
+    // returns true if a key is waiting to be read.
+    // once stdin has ended this is always true, so that the program goes on to read the key and stops.
+    pub fn status(&mut self) -> io::Result<bool> {
+        self.out.flush()?;
+        if self.pending.is_none() && !self.closed {
+            match self.keys.try_recv() {
+                Ok(key) => self.pending = Some(key),
+                Err(TryRecvError::Empty) => {},
+                Err(TryRecvError::Disconnected) => self.closed = true,
+            }
+        }
+        Ok(self.pending.is_some() || self.closed)
+    }
+
+    // waits for a key and returns it, or returns None when stdin has ended
+    pub fn read(&mut self) -> io::Result<Option<u8>> {
+        self.out.flush()?;
+        if let Some(key) = self.pending.take() {
+            return Ok(Some(key));
+        }
+        match self.keys.recv() {
+            Ok(key) => Ok(Some(key)),
+            Err(_) => {
+                self.closed = true;
+                Ok(None)
+            },
+        }
+    }
+
+    pub fn write(&mut self, c: u8) -> io::Result<()> {
+        self.out.write_all(&[c])
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Default for Console {
+    fn default() -> Console {
+        Console::new()
+    }
+}
+
+impl Drop for Console {
+    fn drop(&mut self) {
+        let _ = self.out.flush();
+        if self.terminal {
+            stty(&["icanon", "echo"]);
+        }
+    }
+}
+
+// changes the settings of the terminal on stdin. returns false if they couldn't be changed.
+fn stty(args: &[&str]) -> bool {
+    match Command::new("stty").args(args).status() {
+        Ok(status) => status.success(),
+        Err(_) => false,
+    }
+}
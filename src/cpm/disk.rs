@@ -0,0 +1,207 @@
+// CP/M disk images, and the disk parameter blocks (DPB) that describe their layout to the BDOS.
+// an image is the sectors of the disk stored one after another, track by track, in physical order.
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+
+// every CP/M 2.2 sector is 128 bytes
+pub const SECTOR_SIZE: usize = 128;
+
+// the sector skew of the standard 8-inch IBM 3740 disk (a skew factor of 6)
+const IBM_3740_SKEW: [u8; 26] = [
+    1, 7, 13, 19, 25, 5, 11, 17, 23, 3, 9, 15, 21,
+    2, 8, 14, 20, 26, 6, 12, 18, 24, 4, 10, 16, 22,
+];
+
+// the layout of a disk, as given to the BDOS in the disk parameter block
+#[derive(Clone)]
+pub struct DiskParams {
+    pub spt: u16, // sectors per track
+    pub bsh: u8, // block shift factor: a block is 128 << bsh bytes
+    pub blm: u8, // block mask: (1 << bsh) - 1
+    pub exm: u8, // extent mask
+    pub dsm: u16, // the number of the last block on the disk
+    pub drm: u16, // the number of the last directory entry
+    pub al0: u8, // blocks reserved for the directory, as a bit map
+    pub al1: u8,
+    pub cks: u16, // size of the directory check vector. 0 for disks that can't be changed.
+    pub off: u16, // number of reserved (system) tracks
+    pub skew: Option<Vec<u8>>, // sector translation table. sectors number from 1 when there is one, and from 0 otherwise.
+}
+
+impl DiskParams {
+    // the single-sided, single-density 8-inch disk that CP/M was distributed on
+    pub fn ibm_3740() -> DiskParams {
+        DiskParams {
+            spt: 26,
+            bsh: 3,
+            blm: 7,
+            exm: 0,
+            dsm: 242,
+            drm: 63,
+            al0: 0xc0,
+            al1: 0x00,
+            cks: 16,
+            off: 2,
+            skew: Some(IBM_3740_SKEW.to_vec()),
+        }
+    }
+
+    // a 4 MB hard disk with 2K blocks and 1024 directory entries, and no system tracks
+    pub fn hd_4mb() -> DiskParams {
+        DiskParams {
+            spt: 32,
+            bsh: 4,
+            blm: 15,
+            exm: 0,
+            dsm: 2039,
+            drm: 1023,
+            al0: 0xff,
+            al1: 0xff,
+            cks: 0,
+            off: 0,
+            skew: None,
+        }
+    }
+
+    // parses either the name of a known format ("ibm3740" or "hd4mb"),
+    // or every DPB field as a comma separated list, e.g. "spt=32,bsh=4,blm=15,exm=0,dsm=2039,drm=1023,al0=0xff,al1=0xff,cks=0,off=0".
+    // disks given by their fields have no sector translation.
+    pub fn parse(s: &str) -> Result<DiskParams, String> {
+        match s {
+            "ibm3740" => return Ok(DiskParams::ibm_3740()),
+            "hd4mb" => return Ok(DiskParams::hd_4mb()),
+            _ => {},
+        }
+
+        let mut values: Vec<(&str, u16)> = Vec::new();
+        for field in s.split(',') {
+            let (name, value) = field.split_once('=').ok_or(format!("expected name=value, found \"{}\"", field))?;
+            let (name, value) = (name.trim(), value.trim());
+            let parsed = match value.strip_prefix("0x") {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => value.parse::<u16>(),
+            };
+            values.push((name, parsed.map_err(|_| format!("invalid value for {}: \"{}\"", name, value))?));
+        }
+
+        let names = ["spt", "bsh", "blm", "exm", "dsm", "drm", "al0", "al1", "cks", "off"];
+        if let Some((name, _)) = values.iter().find(|(name, _)| !names.contains(name)) {
+            return Err(format!("unknown DPB field \"{}\"", name));
+        }
+        let word = |name: &str| -> Result<u16, String> {
+            let field = values.iter().find(|(n, _)| *n == name);
+            field.map(|(_, value)| *value).ok_or(format!("missing DPB field \"{}\"", name))
+        };
+        let byte = |name: &str| -> Result<u8, String> {
+            u8::try_from(word(name)?).map_err(|_| format!("{} must fit in a byte", name))
+        };
+
+        let params = DiskParams {
+            spt: word("spt")?,
+            bsh: byte("bsh")?,
+            blm: byte("blm")?,
+            exm: byte("exm")?,
+            dsm: word("dsm")?,
+            drm: word("drm")?,
+            al0: byte("al0")?,
+            al1: byte("al1")?,
+            cks: word("cks")?,
+            off: word("off")?,
+            skew: None,
+        };
+        if params.spt == 0 || params.bsh < 3 || params.bsh > 7 || params.blm as u16 != (1 << params.bsh) - 1 {
+            return Err("spt must not be 0, and bsh and blm must describe a block of 1K to 16K".to_string());
+        }
+        Ok(params)
+    }
+
+    // the disk parameter block, as laid out in memory for the BDOS
+    pub fn dpb(&self) -> [u8; 15] {
+        [
+            self.spt as u8, (self.spt >> 8) as u8,
+            self.bsh,
+            self.blm,
+            self.exm,
+            self.dsm as u8, (self.dsm >> 8) as u8,
+            self.drm as u8, (self.drm >> 8) as u8,
+            self.al0,
+            self.al1,
+            self.cks as u8, (self.cks >> 8) as u8,
+            self.off as u8, (self.off >> 8) as u8,
+        ]
+    }
+
+    // the number of the first sector on each track
+    pub fn first_sector(&self) -> u16 {
+        if self.skew.is_some() {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+// a disk image file
+pub struct Disk {
+    pub params: DiskParams,
+    file: File,
+    read_only: bool,
+}
+
+impl Disk {
+    // opens the image for reading and writing, or just for reading if the file can't be written
+    pub fn open(path: &str, params: DiskParams) -> io::Result<Disk> {
+        match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(file) => Ok(Disk { params, file, read_only: false }),
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Disk::open_read_only(path, params),
+            Err(e) => Err(e),
+        }
+    }
+
+    // opens the image just for reading, so that writing a sector to it fails
+    pub fn open_read_only(path: &str, params: DiskParams) -> io::Result<Disk> {
+        Ok(Disk { params, file: File::open(path)?, read_only: true })
+    }
+
+    // returns the offset of the physical sector in the image, or None if it isn't on the disk
+    fn offset(&self, track: u16, sector: u16) -> Option<u64> {
+        let first = self.params.first_sector();
+        if sector < first || sector - first >= self.params.spt {
+            return None;
+        }
+        let index = track as u64 * self.params.spt as u64 + (sector - first) as u64;
+        Some(index * SECTOR_SIZE as u64)
+    }
+
+    // reads the physical sector into buf.
+    // the part of the disk past the end of the image reads as freshly formatted (filled with 0xe5),
+    // so an empty file can be used as a blank disk.
+    pub fn read_sector(&mut self, track: u16, sector: u16, buf: &mut [u8; SECTOR_SIZE]) -> io::Result<()> {
+        let offset = self.offset(track, sector).ok_or(io::ErrorKind::InvalidInput)?;
+        self.file.seek(SeekFrom::Start(offset))?;
+        let mut read = 0;
+        while read < SECTOR_SIZE {
+            match self.file.read(&mut buf[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        buf[read..].fill(0xe5);
+        Ok(())
+    }
+
+    // writes buf to the physical sector. the image grows if the sector is past its end.
+    pub fn write_sector(&mut self, track: u16, sector: u16, buf: &[u8; SECTOR_SIZE]) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        let offset = self.offset(track, sector).ok_or(io::ErrorKind::InvalidInput)?;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(buf)
+    }
+}
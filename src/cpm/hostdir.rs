@@ -0,0 +1,381 @@
+// a CP/M drive backed by a directory on the host, so files can be copied in and out without a disk image tool.
+// the BDOS never sees the files: calls to the BDOS file functions for this drive are done here instead,
+// by synthesising directory entries from the files in the directory and reading and writing them record by record.
+// the BDOS sees an empty disk, so that it can still select the drive.
+//
+// only files with names that fit in 8.3 are visible, and they are visible to every user number.
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::cpm::disk::DiskParams;
+use crate::cpm::disk::SECTOR_SIZE;
+use crate::cpu::State8080;
+
+// offsets of the fields of a file control block (FCB)
+const FCB_NAME: u16 = 1; // 8 bytes of name, then 3 bytes of type
+const FCB_EX: u16 = 12; // the extent of the file: each one is 128 records
+const FCB_S2: u16 = 14; // the high bits of the extent
+const FCB_RC: u16 = 15; // the number of records in the extent
+const FCB_AL: u16 = 16; // the allocation map, or the new name for a rename
+const FCB_CR: u16 = 32; // the current record in the extent
+const FCB_R0: u16 = 33; // the random record number, 3 bytes
+
+// the BDOS return codes
+const OK: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01; // also "reading unwritten data" for random reads
+const NOT_FOUND: u8 = 0xff; // also the error for a bad file name, or a file the host couldn't read or write
+
+// the records in an extent, which is what a directory entry covers
+const EXTENT_RECORDS: u64 = 128;
+
+// CP/M marks the end of a text file in its last record with control-z
+const EOF_MARK: u8 = 0x1a;
+
+pub struct HostDrive {
+    root: PathBuf,
+    found: Vec<[u8; 32]>, // directory entries left to return from search next
+}
+
+impl HostDrive {
+    pub fn new(root: &str) -> io::Result<HostDrive> {
+        let root = PathBuf::from(root);
+        if !root.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "the host drive must be a directory"));
+        }
+        Ok(HostDrive { root, found: Vec::new() })
+    }
+
+    // the layout the BDOS sees. it only ever reads the empty directory, so this just has to be valid.
+    pub fn params() -> DiskParams {
+        DiskParams {
+            spt: 32,
+            bsh: 4,
+            blm: 15,
+            exm: 0,
+            dsm: 255,
+            drm: 63,
+            al0: 0x80,
+            al1: 0x00,
+            cks: 0,
+            off: 0,
+            skew: None,
+        }
+    }
+
+    // reads a sector of the empty disk that the BDOS sees
+    pub fn read_sector(buf: &mut [u8; SECTOR_SIZE]) {
+        buf.fill(0xe5);
+    }
+
+    // performs the BDOS file function for the FCB at fcb, with the DMA buffer at dma. returns the value for a.
+    // an error from the host (a file that has gone, or that it isn't allowed to write) goes back to the program as a
+    // BDOS error, rather than ending the session.
+    pub fn bdos(&mut self, function: u8, state: &mut State8080, fcb: u16, dma: u16) -> u8 {
+        self.file_function(function, state, fcb, dma).unwrap_or(NOT_FOUND)
+    }
+
+    fn file_function(&mut self, function: u8, state: &mut State8080, fcb: u16, dma: u16) -> io::Result<u8> {
+        match function {
+            15 => {
+                // open file
+                let Some(name) = self.find(state, fcb)?.into_iter().next() else {
+                    return Ok(NOT_FOUND);
+                };
+                set_name(state, fcb, &name);
+                let size = fs::metadata(self.root.join(&name))?.len();
+                set_record_count(state, fcb, size);
+                Ok(OK)
+            },
+            16 | 30 => {
+                // close file, set file attributes (every write already went to the file, and there are no attributes)
+                Ok(if self.find(state, fcb)?.is_empty() { NOT_FOUND } else { OK })
+            },
+            17 => {
+                // search for first
+                self.found.clear();
+                for name in self.find(state, fcb)?.iter().rev() {
+                    let size = fs::metadata(self.root.join(name))?.len();
+                    self.found.extend(dir_entries(name, size).into_iter().rev());
+                }
+                self.search_next(state, dma)
+            },
+            18 => {
+                // search for next
+                self.search_next(state, dma)
+            },
+            19 => {
+                // delete file
+                let names = self.find(state, fcb)?;
+                for name in names.iter() {
+                    fs::remove_file(self.root.join(name))?;
+                }
+                Ok(if names.is_empty() { NOT_FOUND } else { OK })
+            },
+            20 => {
+                // read sequential
+                let Some(name) = self.open_name(state, fcb)? else {
+                    return Ok(NOT_FOUND);
+                };
+                let record = sequential_record(state, fcb);
+                if !self.read_record(&name, record, state, dma)? {
+                    return Ok(END_OF_FILE);
+                }
+                set_sequential_record(state, fcb, record + 1);
+                Ok(OK)
+            },
+            21 => {
+                // write sequential
+                let Some(name) = self.open_name(state, fcb)? else {
+                    return Ok(NOT_FOUND);
+                };
+                let record = sequential_record(state, fcb);
+                self.write_record(&name, record, state, dma)?;
+                set_sequential_record(state, fcb, record + 1);
+                Ok(OK)
+            },
+            22 => {
+                // make file
+                let Some(name) = fcb_name(state, fcb) else {
+                    return Ok(NOT_FOUND);
+                };
+                File::create(self.root.join(&name))?;
+                for i in FCB_EX..FCB_CR + 1 {
+                    state.set_mem(fcb.wrapping_add(i), 0);
+                }
+                Ok(OK)
+            },
+            23 => {
+                // rename file: the new name is in the second half of the FCB
+                let Some(old) = self.find(state, fcb)?.into_iter().next() else {
+                    return Ok(NOT_FOUND);
+                };
+                let Some(new) = fcb_name(state, fcb.wrapping_add(FCB_AL)) else {
+                    return Ok(NOT_FOUND);
+                };
+                fs::rename(self.root.join(old), self.root.join(new))?;
+                Ok(OK)
+            },
+            33 | 34 | 40 => {
+                // read random, write random, write random with zero fill
+                let Some(name) = self.open_name(state, fcb)? else {
+                    return Ok(NOT_FOUND);
+                };
+                let record = random_record(state, fcb);
+                if function == 33 {
+                    if !self.read_record(&name, record, state, dma)? {
+                        return Ok(END_OF_FILE);
+                    }
+                } else {
+                    // the host file system fills any gap before the record with zeros
+                    self.write_record(&name, record, state, dma)?;
+                }
+                // sequential reads and writes carry on from the random record
+                set_sequential_record(state, fcb, record);
+                Ok(OK)
+            },
+            35 => {
+                // compute file size, in records
+                let Some(name) = self.open_name(state, fcb)? else {
+                    return Ok(NOT_FOUND);
+                };
+                let size = fs::metadata(self.root.join(name))?.len();
+                set_random_record(state, fcb, size.div_ceil(SECTOR_SIZE as u64) as u32);
+                Ok(OK)
+            },
+            36 => {
+                // set random record
+                let record = sequential_record(state, fcb);
+                set_random_record(state, fcb, record);
+                Ok(OK)
+            },
+            _ => {
+                eprintln!("unimplemented BDOS function for a host drive: {}", function);
+                Ok(NOT_FOUND)
+            },
+        }
+    }
+
+    // returns the next directory entry found by search for first, copying it to the start of the DMA buffer
+    fn search_next(&mut self, state: &mut State8080, dma: u16) -> io::Result<u8> {
+        let Some(entry) = self.found.pop() else {
+            return Ok(NOT_FOUND);
+        };
+        for (i, byte) in entry.iter().enumerate() {
+            state.set_mem(dma.wrapping_add(i as u16), *byte);
+        }
+        Ok(OK)
+    }
+
+    // returns the names of the host files that match the name in the FCB, which may contain '?' wildcards
+    fn find(&self, state: &mut State8080, fcb: u16) -> io::Result<Vec<String>> {
+        let mut pattern = [0u8; 11];
+        for (i, c) in pattern.iter_mut().enumerate() {
+            *c = state.get_mem(fcb.wrapping_add(FCB_NAME + i as u16)) & 0x7f;
+        }
+
+        let mut names: Vec<String> = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let Some(name) = entry.file_name().to_str().map(|s| s.to_string()) else {
+                continue;
+            };
+            let Some(cpm_name) = to_cpm_name(&name) else {
+                continue;
+            };
+            if pattern.iter().zip(cpm_name.iter()).all(|(p, c)| *p == b'?' || p.to_ascii_uppercase() == *c) {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    // returns the host file for an FCB that doesn't use wildcards, which the file functions after open require
+    fn open_name(&self, state: &mut State8080, fcb: u16) -> io::Result<Option<String>> {
+        if fcb_name(state, fcb).is_none() {
+            return Ok(None);
+        }
+        Ok(self.find(state, fcb)?.into_iter().next())
+    }
+
+    // reads the record into the DMA buffer. returns false if the record is past the end of the file.
+    fn read_record(&self, name: &str, record: u32, state: &mut State8080, dma: u16) -> io::Result<bool> {
+        let mut file = File::open(self.root.join(name))?;
+        file.seek(SeekFrom::Start(record as u64 * SECTOR_SIZE as u64))?;
+        let mut buf = [0u8; SECTOR_SIZE];
+        let mut read = 0;
+        while read < SECTOR_SIZE {
+            match file.read(&mut buf[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        if read == 0 {
+            return Ok(false);
+        }
+
+        // host files don't have to be a whole number of records long
+        buf[read..].fill(EOF_MARK);
+        for (i, byte) in buf.iter().enumerate() {
+            state.set_mem(dma.wrapping_add(i as u16), *byte);
+        }
+        Ok(true)
+    }
+
+    // writes the DMA buffer to the record
+    fn write_record(&self, name: &str, record: u32, state: &mut State8080, dma: u16) -> io::Result<()> {
+        let mut file = OpenOptions::new().write(true).open(self.root.join(name))?;
+        file.seek(SeekFrom::Start(record as u64 * SECTOR_SIZE as u64))?;
+        let mut buf = [0u8; SECTOR_SIZE];
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = state.get_mem(dma.wrapping_add(i as u16));
+        }
+        file.write_all(&buf)
+    }
+}
+
+// converts a host file name to the 11 bytes of a CP/M name and type, or None if it doesn't fit
+fn to_cpm_name(name: &str) -> Option<[u8; 11]> {
+    let (base, ext) = name.split_once('.').unwrap_or((name, ""));
+    if base.is_empty() || base.len() > 8 || ext.len() > 3 {
+        return None;
+    }
+    // the punctuation CP/M doesn't allow in names, and the path separators the host would take as directories
+    let valid = |c: u8| c.is_ascii_graphic() && !b".,;:=?*[]<>|/\\".contains(&c);
+    if !base.bytes().chain(ext.bytes()).all(valid) {
+        return None;
+    }
+
+    let mut cpm_name = [b' '; 11];
+    for (i, c) in base.bytes().enumerate() {
+        cpm_name[i] = c.to_ascii_uppercase();
+    }
+    for (i, c) in ext.bytes().enumerate() {
+        cpm_name[8 + i] = c.to_ascii_uppercase();
+    }
+    Some(cpm_name)
+}
+
+// returns the name in the FCB as a host file name, e.g. "HELLO.COM", or None if it has wildcards or isn't a name
+// to_cpm_name would give (which keeps a program from reaching outside the directory with a name like ../../x)
+fn fcb_name(state: &mut State8080, fcb: u16) -> Option<String> {
+    let mut base = String::new();
+    let mut ext = String::new();
+    for i in 0..11 {
+        let c = (state.get_mem(fcb.wrapping_add(FCB_NAME + i)) & 0x7f) as char;
+        if c != ' ' {
+            if i < 8 { base.push(c) } else { ext.push(c) }
+        }
+    }
+    let name = if ext.is_empty() { base } else { base + "." + &ext };
+    to_cpm_name(&name).map(|_| name)
+}
+
+// copies the CP/M form of the host file name into the FCB, replacing any wildcards
+fn set_name(state: &mut State8080, fcb: u16, name: &str) {
+    if let Some(cpm_name) = to_cpm_name(name) {
+        for (i, c) in cpm_name.iter().enumerate() {
+            state.set_mem(fcb.wrapping_add(FCB_NAME + i as u16), *c);
+        }
+    }
+}
+
+// builds the directory entries for a file: user 0, one for each 16K extent with the records in it
+fn dir_entries(name: &str, size: u64) -> Vec<[u8; 32]> {
+    let records = size.div_ceil(SECTOR_SIZE as u64);
+    let extents = records.div_ceil(EXTENT_RECORDS).max(1);
+    (0..extents)
+        .map(|extent| {
+            let mut entry = [0u8; 32];
+            entry[1..12].copy_from_slice(&to_cpm_name(name).unwrap());
+            entry[FCB_EX as usize] = (extent & 0x1f) as u8;
+            entry[FCB_S2 as usize] = (extent >> 5) as u8 & 0x3f;
+            entry[FCB_RC as usize] = (records - extent * EXTENT_RECORDS).min(EXTENT_RECORDS) as u8;
+            entry
+        })
+        .collect()
+}
+
+// the record that the next sequential read or write uses, from the extent and current record
+fn sequential_record(state: &mut State8080, fcb: u16) -> u32 {
+    let s2 = (state.get_mem(fcb.wrapping_add(FCB_S2)) & 0x3f) as u32;
+    let ex = (state.get_mem(fcb.wrapping_add(FCB_EX)) & 0x1f) as u32;
+    let cr = (state.get_mem(fcb.wrapping_add(FCB_CR)) & 0x7f) as u32;
+    s2 << 12 | ex << 7 | cr
+}
+
+// sets the extent and current record so that the next sequential read or write uses the record
+fn set_sequential_record(state: &mut State8080, fcb: u16, record: u32) {
+    state.set_mem(fcb.wrapping_add(FCB_S2), (record >> 12) as u8 & 0x3f);
+    state.set_mem(fcb.wrapping_add(FCB_EX), (record >> 7) as u8 & 0x1f);
+    state.set_mem(fcb.wrapping_add(FCB_CR), record as u8 & 0x7f);
+}
+
+// sets the record count of the extent in the FCB from the size of the file
+fn set_record_count(state: &mut State8080, fcb: u16, size: u64) {
+    let first = (sequential_record(state, fcb) & !0x7f) as u64;
+    let records = size.div_ceil(SECTOR_SIZE as u64).saturating_sub(first).min(128);
+    state.set_mem(fcb.wrapping_add(FCB_RC), records as u8);
+}
+
+fn random_record(state: &mut State8080, fcb: u16) -> u32 {
+    let r0 = state.get_mem(fcb.wrapping_add(FCB_R0)) as u32;
+    let r1 = state.get_mem(fcb.wrapping_add(FCB_R0 + 1)) as u32;
+    r1 << 8 | r0
+}
+
+fn set_random_record(state: &mut State8080, fcb: u16, record: u32) {
+    state.set_mem(fcb.wrapping_add(FCB_R0), record as u8);
+    state.set_mem(fcb.wrapping_add(FCB_R0 + 1), (record >> 8) as u8);
+    state.set_mem(fcb.wrapping_add(FCB_R0 + 2), (record >> 16) as u8);
+}
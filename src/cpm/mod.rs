@@ -0,0 +1,95 @@
+// runs CP/M .COM programs on the 8080, such as the cpu diagnostics (TST8080, CPUTEST, 8080PRE, 8080EXM).
+// there is no real CP/M here: calls to the BDOS are trapped and the console functions are done in rust.
+// a complete CP/M system, which runs the real CCP and BDOS, is in the system module.
+use std::io;
+use std::io::Write;
+
+use crate::cpu::emulate;
+use crate::cpu::State8080;
+
+pub mod console;
+pub mod disk;
+pub mod hostdir;
+pub mod system;
+
+// .COM programs are loaded at the start of the transient program area (TPA)
+const TPA: u16 = 0x0100;
+// programs call the BDOS with the function number in c
+pub const BDOS: u16 = 0x0005;
+// jumping here (a warm boot) ends the program
+pub const WBOOT: u16 = 0x0000;
+// the top of the TPA, stored at 0x0006 as the address of the BDOS. programs use it to find the top of memory.
+const TPA_TOP: u16 = 0xfe00;
+
+// loads the .COM program into a fresh cpu and runs it until it exits, writing console output to out
+pub fn run_com(program: &[u8], out: &mut impl Write) -> Result<(), String> {
+    let state = &mut State8080::new();
+    load_com(state, program)?;
+
+    let written = |e: io::Error| format!("cannot write the program's output: {}", e);
+    loop {
+        if state.pc == WBOOT {
+            break;
+        }
+        if state.pc == BDOS && !bdos(state, out).map_err(written)? {
+            break;
+        }
+        emulate(state);
+    }
+
+    out.flush().map_err(written)
+}
+
+// loads the .COM program into the TPA, and sets up the BDOS entry and the stack to run it.
+// whatever runs it has to call bdos when the pc gets to BDOS, and stop when it gets to WBOOT.
+pub fn load_com(state: &mut State8080, program: &[u8]) -> Result<(), String> {
+    let room = (TPA_TOP - TPA) as usize;
+    if program.len() > room {
+        return Err(format!("a .COM program has to fit in the {} bytes of the TPA, but this one is {}", room, program.len()));
+    }
+    state.memory[TPA as usize..TPA as usize + program.len()].copy_from_slice(program);
+
+    // after the BDOS function is done, the RET placed here returns to the program.
+    // the address after it is the top of the TPA, just like in a real CP/M system.
+    state.set_mem(BDOS, 0xc9);
+    state.set_mem(BDOS + 1, TPA_TOP as u8);
+    state.set_mem(BDOS + 2, (TPA_TOP >> 8) as u8);
+
+    // programs can also end by returning to the CCP, so push a return to the warm boot address
+    state.sp = TPA_TOP;
+    state.set_mem(state.sp.wrapping_sub(1), (WBOOT >> 8) as u8);
+    state.set_mem(state.sp.wrapping_sub(2), WBOOT as u8);
+    state.sp = state.sp.wrapping_sub(2);
+    state.pc = TPA;
+    Ok(())
+}
+
+// performs the BDOS function in c. returns false when the function ends the program.
+pub fn bdos(state: &mut State8080, out: &mut impl Write) -> io::Result<bool> {
+    match state.c {
+        0 => {
+            // system reset
+            return Ok(false);
+        },
+        2 => {
+            // console output of the character in e
+            out.write_all(&[state.e])?;
+        },
+        9 => {
+            // print the string at de, which is terminated by '$'
+            let mut addr: u16 = (state.d as u16) << 8 | (state.e as u16);
+            loop {
+                let c = state.get_mem(addr);
+                if c == b'$' {
+                    break;
+                }
+                out.write_all(&[c])?;
+                addr = addr.wrapping_add(1);
+            }
+        },
+        _ => {
+            eprintln!("unimplemented BDOS function: {}", state.c);
+        },
+    }
+    Ok(true)
+}
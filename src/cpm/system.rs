@@ -0,0 +1,401 @@
+// a complete CP/M 2.2 machine: the CCP and BDOS run on the 8080 from a system image,
+// and the BIOS they call is done in rust, with disk images for drives and stdin/stdout as the console.
+//
+// the memory map is the standard one for a 64K system:
+//   0x0000  page zero (jumps to the warm boot and the BDOS, the default FCB and DMA buffer)
+//   0x0100  transient program area
+//   0xe400  CCP
+//   0xec00  BDOS
+//   0xfa00  BIOS jump table, followed by the disk parameter headers and the BDOS's work areas
+//
+// calls to the BDOS entry are watched, so that the file functions for host drives can be done in rust.
+use std::io;
+
+use crate::cpm::console::Console;
+use crate::cpm::disk::Disk;
+use crate::cpm::disk::DiskParams;
+use crate::cpm::disk::SECTOR_SIZE;
+use crate::cpm::hostdir::HostDrive;
+use crate::cpu::emulate;
+use crate::cpu::State8080;
+
+const CCP: u16 = 0xe400;
+const BDOS: u16 = 0xec06; // the entry point, 6 bytes past the start of the BDOS
+const BIOS: u16 = 0xfa00;
+// the CCP and BDOS are 0x1600 bytes long, or 44 sectors
+const SYSTEM_SIZE: usize = 0x1600;
+// the number of entries in the BIOS jump table
+const BIOS_FUNCTIONS: u16 = 17;
+// CP/M 2.2 supports drives A to P
+const MAX_DRIVES: usize = 16;
+
+// what the BIOS function asks the machine to do next
+enum Next {
+    Return, // return to the caller
+    Jump, // the function has set pc itself
+    Stop, // stop the machine (the console has no more input)
+}
+
+// a drive of the CP/M system
+pub enum Drive {
+    Image(Disk), // a disk image, which the BDOS reads and writes through the BIOS
+    Host(HostDrive), // a directory on the host, whose files are read and written in place of the BDOS
+}
+
+impl Drive {
+    fn params(&self) -> DiskParams {
+        match self {
+            Drive::Image(disk) => disk.params.clone(),
+            Drive::Host(_) => HostDrive::params(),
+        }
+    }
+}
+
+pub struct CpmSystem {
+    pub state: State8080,
+    drives: Vec<Drive>, // indexed by drive number: A is 0
+    dph: Vec<u16>, // the address of each drive's disk parameter header
+    system: Vec<u8>, // the CCP and BDOS, reloaded on every warm boot
+    console: Console,
+    disk: usize, // the drive selected by SELDSK
+    track: u16, // set by SETTRK
+    sector: u16, // set by SETSEC
+    dma: u16, // set by SETDMA
+    current_drive: u8, // the drive that FCBs with a drive of 0 refer to, kept in step with the BDOS
+    bdos_dma: u16, // the DMA buffer set with the BDOS, which is not the one the BDOS sets with SETDMA
+    searching: Option<usize>, // the host drive that search for next continues on
+}
+
+impl CpmSystem {
+    // builds the machine with the drives given, A first.
+    // the CCP and BDOS come from the system image if there is one, and otherwise from the system tracks of drive A.
+    pub fn new(drives: Vec<Drive>, system: Option<Vec<u8>>) -> io::Result<CpmSystem> {
+        if drives.is_empty() || drives.len() > MAX_DRIVES {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "CP/M needs between 1 and 16 drives"));
+        }
+
+        let mut cpm = CpmSystem {
+            state: State8080::new(),
+            drives,
+            dph: Vec::new(),
+            system: Vec::new(),
+            console: Console::new(),
+            disk: 0,
+            track: 0,
+            sector: 0,
+            dma: 0x80,
+            current_drive: 0,
+            bdos_dma: 0x80,
+            searching: None,
+        };
+
+        cpm.system = match system {
+            Some(image) if image.len() <= SYSTEM_SIZE => image,
+            Some(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "the CCP and BDOS image is larger than 0x1600 bytes")),
+            None => cpm.read_system_tracks()?,
+        };
+        cpm.build_bios()?;
+        Ok(cpm)
+    }
+
+    // reads the CCP and BDOS from the system tracks of drive A, which start at the second sector (the first is the cold boot loader)
+    fn read_system_tracks(&mut self) -> io::Result<Vec<u8>> {
+        let Drive::Image(disk) = &mut self.drives[0] else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "drive A is a host directory. Pass a CCP and BDOS image to boot from."));
+        };
+        let params = disk.params.clone();
+        let sectors = SYSTEM_SIZE / SECTOR_SIZE;
+        if (params.off as usize) * (params.spt as usize) < sectors + 1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "drive A has no room for CP/M on its system tracks. Pass a CCP and BDOS image instead."));
+        }
+
+        let mut system = Vec::with_capacity(SYSTEM_SIZE);
+        let mut buf = [0u8; SECTOR_SIZE];
+        for i in 1..=sectors as u16 {
+            let track = i / params.spt;
+            let sector = i % params.spt + params.first_sector();
+            disk.read_sector(track, sector, &mut buf)?;
+            system.extend_from_slice(&buf);
+        }
+        Ok(system)
+    }
+
+    // fills in the BIOS jump table, then lays out a disk parameter header (DPH) for each drive after it, along with
+    // the DPB, sector translation table, check vector and allocation vector that it points to.
+    fn build_bios(&mut self) -> io::Result<()> {
+        // the cpu never runs the jump table: reaching an entry runs the BIOS function in rust instead.
+        // each entry is a RET, which returns to the caller once the function is done.
+        for i in 0..BIOS_FUNCTIONS {
+            self.state.set_mem(BIOS + i * 3, 0xc9);
+        }
+
+        // the directory buffer is shared by all of the drives
+        let mut next: usize = (BIOS + BIOS_FUNCTIONS * 3) as usize;
+        let dirbuf = next as u16;
+        next += SECTOR_SIZE;
+
+        for drive in self.drives.iter() {
+            let params = drive.params();
+
+            let dph = next;
+            let dpb = dph + 16;
+            let xlt = dpb + 15;
+            let csv = xlt + params.skew.as_ref().map_or(0, |skew| skew.len());
+            let alv = csv + params.cks as usize;
+            next = alv + (params.dsm as usize / 8) + 1;
+            if next > 0x10000 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "the disk parameters don't fit in memory above the BIOS"));
+            }
+
+            let words: [u16; 8] = [
+                if params.skew.is_some() { xlt as u16 } else { 0 },
+                0, 0, 0, // scratch words used by the BDOS
+                dirbuf,
+                dpb as u16,
+                csv as u16,
+                alv as u16,
+            ];
+            for (i, word) in words.iter().enumerate() {
+                self.state.set_mem((dph + i * 2) as u16, *word as u8);
+                self.state.set_mem((dph + i * 2 + 1) as u16, (*word >> 8) as u8);
+            }
+            for (i, byte) in params.dpb().iter().enumerate() {
+                self.state.set_mem((dpb + i) as u16, *byte);
+            }
+            if let Some(skew) = &params.skew {
+                for (i, sector) in skew.iter().enumerate() {
+                    self.state.set_mem((xlt + i) as u16, *sector);
+                }
+            }
+            self.dph.push(dph as u16);
+        }
+        Ok(())
+    }
+
+    // loads the CCP and BDOS, sets up page zero and jumps to the CCP, keeping the current drive
+    fn warm_boot(&mut self) {
+        for (i, byte) in self.system.iter().enumerate() {
+            self.state.memory[CCP as usize + i] = *byte;
+        }
+
+        // JMP WBOOT
+        self.state.set_mem(0x0000, 0xc3);
+        self.state.set_mem(0x0001, (BIOS + 3) as u8);
+        self.state.set_mem(0x0002, ((BIOS + 3) >> 8) as u8);
+        // JMP BDOS
+        self.state.set_mem(0x0005, 0xc3);
+        self.state.set_mem(0x0006, BDOS as u8);
+        self.state.set_mem(0x0007, (BDOS >> 8) as u8);
+
+        self.dma = 0x80;
+        self.state.sp = 0x0100;
+        // the CCP expects the current drive and user in c
+        self.state.c = self.state.get_mem(0x0004);
+        self.current_drive = self.state.c & 0x0f;
+        self.state.pc = CCP;
+    }
+
+    // boots CP/M and runs it until the console has no more input
+    pub fn run(&mut self) -> io::Result<()> {
+        self.boot();
+        while self.step()? {}
+        self.console.flush()
+    }
+
+    // cold boot: drive A, user 0, and the default IOBYTE
+    pub fn boot(&mut self) {
+        self.state.set_mem(0x0003, 0);
+        self.state.set_mem(0x0004, 0);
+        self.warm_boot();
+    }
+
+    // runs the instruction at the pc, or the BDOS or BIOS function the pc has got to.
+    // returns false when the machine stops because the console has no more input.
+    pub fn step(&mut self) -> io::Result<bool> {
+        let pc = self.state.pc;
+        if pc == BDOS && self.bdos()? {
+            // done in rust, so return to the caller of the BDOS
+            self.state.pc = (self.state.get_mem(self.state.sp) as u16) | ((self.state.get_mem(self.state.sp.wrapping_add(1)) as u16) << 8);
+            self.state.sp = self.state.sp.wrapping_add(2);
+            return Ok(true);
+        }
+        if (BIOS..BIOS + BIOS_FUNCTIONS * 3).contains(&pc) && (pc - BIOS).is_multiple_of(3) {
+            match self.bios((pc - BIOS) / 3)? {
+                Next::Return => {},
+                Next::Jump => return Ok(true),
+                Next::Stop => return Ok(false),
+            }
+        }
+        emulate(&mut self.state);
+        Ok(true)
+    }
+
+    // called at the BDOS entry, before the BDOS runs. the file functions for host drives are done here, and return true.
+    // the other functions are left to the BDOS, but are watched to keep track of the current drive and DMA buffer.
+    fn bdos(&mut self) -> io::Result<bool> {
+        let function = self.state.c;
+        let de = (self.state.d as u16) << 8 | self.state.e as u16;
+        let drive = match function {
+            13 => {
+                // reset disk system
+                self.current_drive = 0;
+                self.bdos_dma = 0x80;
+                return Ok(false);
+            },
+            14 => {
+                // select disk
+                self.current_drive = self.state.e & 0x0f;
+                return Ok(false);
+            },
+            26 => {
+                // set DMA address
+                self.bdos_dma = de;
+                return Ok(false);
+            },
+            18 => {
+                // search for next, which carries on from search for first
+                match self.searching {
+                    Some(drive) => drive,
+                    None => return Ok(false),
+                }
+            },
+            15..=17 | 19..=23 | 30 | 33..=36 | 40 => {
+                // a function on the FCB at de. the drive is 1 for A, or 0 (or '?' when searching) for the current drive.
+                let drive = match self.state.get_mem(de) {
+                    0 | b'?' => self.current_drive as usize,
+                    drive => drive as usize - 1,
+                };
+                if function == 17 {
+                    self.searching = None;
+                }
+                drive
+            },
+            _ => return Ok(false),
+        };
+
+        let Some(Drive::Host(host)) = self.drives.get_mut(drive) else {
+            return Ok(false);
+        };
+        if function == 17 {
+            self.searching = Some(drive);
+        }
+        let result = host.bdos(function, &mut self.state, de, self.bdos_dma);
+
+        // the BDOS returns a byte in both a and l, with b and h cleared
+        self.state.a = result;
+        self.state.l = result;
+        self.state.b = 0;
+        self.state.h = 0;
+        Ok(true)
+    }
+
+    // performs the BIOS function at the entry in the jump table
+    fn bios(&mut self, function: u16) -> io::Result<Next> {
+        match function {
+            0 | 1 => {
+                // BOOT, WBOOT
+                self.warm_boot();
+                return Ok(Next::Jump);
+            },
+            2 => {
+                // CONST
+                self.state.a = if self.console.status()? { 0xff } else { 0x00 };
+            },
+            3 => {
+                // CONIN
+                match self.console.read()? {
+                    Some(key) => self.state.a = key,
+                    None => return Ok(Next::Stop),
+                }
+            },
+            4 => {
+                // CONOUT
+                self.console.write(self.state.c & 0x7f)?;
+            },
+            5 | 6 => {
+                // LIST, PUNCH (there are no printer or punch devices, so the output is dropped)
+            },
+            7 => {
+                // READER (there is no reader device, so it is always at the end of the file)
+                self.state.a = 0x1a;
+            },
+            8 => {
+                // HOME
+                self.track = 0;
+            },
+            9 => {
+                // SELDSK
+                let disk = self.state.c as usize;
+                // a dph of 0 tells the BDOS that there is no such drive
+                let dph = self.dph.get(disk).copied().unwrap_or(0);
+                if dph != 0 {
+                    self.disk = disk;
+                }
+                self.state.h = (dph >> 8) as u8;
+                self.state.l = dph as u8;
+            },
+            10 => {
+                // SETTRK
+                self.track = (self.state.b as u16) << 8 | self.state.c as u16;
+            },
+            11 => {
+                // SETSEC
+                self.sector = (self.state.b as u16) << 8 | self.state.c as u16;
+            },
+            12 => {
+                // SETDMA
+                self.dma = (self.state.b as u16) << 8 | self.state.c as u16;
+            },
+            13 => {
+                // READ
+                let mut buf = [0u8; SECTOR_SIZE];
+                let read = match &mut self.drives[self.disk] {
+                    Drive::Image(disk) => disk.read_sector(self.track, self.sector, &mut buf).is_ok(),
+                    Drive::Host(_) => {
+                        HostDrive::read_sector(&mut buf);
+                        true
+                    },
+                };
+                if read {
+                    for (i, byte) in buf.iter().enumerate() {
+                        self.state.set_mem(self.dma.wrapping_add(i as u16), *byte);
+                    }
+                    self.state.a = 0;
+                } else {
+                    self.state.a = 1;
+                }
+            },
+            14 => {
+                // WRITE
+                let mut buf = [0u8; SECTOR_SIZE];
+                for (i, byte) in buf.iter_mut().enumerate() {
+                    *byte = self.state.get_mem(self.dma.wrapping_add(i as u16));
+                }
+                let written = match &mut self.drives[self.disk] {
+                    Drive::Image(disk) => disk.write_sector(self.track, self.sector, &buf).is_ok(),
+                    // the BDOS never writes to a host drive, since it has no files there
+                    Drive::Host(_) => true,
+                };
+                self.state.a = if written { 0 } else { 1 };
+            },
+            15 => {
+                // LISTST (the list device is always ready)
+                self.state.a = 0xff;
+            },
+            16 => {
+                // SECTRAN: translates the logical sector in bc with the table at de
+                let sector = (self.state.b as u16) << 8 | self.state.c as u16;
+                let xlt = (self.state.d as u16) << 8 | self.state.e as u16;
+                let translated = if xlt == 0 {
+                    sector
+                } else {
+                    self.state.get_mem(xlt.wrapping_add(sector)) as u16
+                };
+                self.state.h = (translated >> 8) as u8;
+                self.state.l = translated as u8;
+            },
+            _ => unreachable!(),
+        }
+        Ok(Next::Return)
+    }
+}
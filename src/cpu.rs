@@ -0,0 +1,2402 @@
+// the 8080 cpu: its registers, flags and memory, and the emulation of each instruction
+
+// flags used for arithmetic operations
+#[derive(Clone)]
+pub struct ConditionCodes {
+    pub z: bool, // true when result is 0
+    pub s: bool, // true when MSB (bit 7) is 1
+    pub p: bool, // true when result has even parity
+    pub cy: bool, // true when instruction caused a carry out to a higher bit
+    pub ac: bool, // true when instruction caused a carry out of bit 3 (used by DAA)
+}
+
+// a memory read or write made by an instruction, recorded for watchpoints
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Access {
+    pub addr: u16,
+    pub value: u8, // the byte read, or the byte written
+    pub write: bool,
+}
+
+// the size of the address space, and of memory
+pub const MEMORY_SIZE: usize = 0x10000;
+
+// a fresh 64K of memory, set to 0. it is made on the heap, since an array that size is too big to build on the stack.
+pub fn new_memory() -> Box<[u8; MEMORY_SIZE]> {
+    vec![0; MEMORY_SIZE].into_boxed_slice().try_into().unwrap()
+}
+
+// the zero, sign and parity flags of every byte, worked out once rather than after every instruction
+const Z: u8 = 0x40;
+const S: u8 = 0x80;
+const P: u8 = 0x04;
+const ZSP: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut n = 0;
+    while n < 256 {
+        let byte = n as u8;
+        if byte == 0 {
+            table[n] |= Z;
+        }
+        if byte & 0x80 != 0 {
+            table[n] |= S;
+        }
+        if byte.count_ones().is_multiple_of(2) {
+            table[n] |= P;
+        }
+        n += 1;
+    }
+    table
+};
+
+#[derive(Clone)]
+pub struct State8080 {
+    pub a: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub memory: Box<[u8; MEMORY_SIZE]>, // all 64K, so that any u16 address is in bounds without a check
+    pub cc: ConditionCodes,
+    pub int_enable: bool, // set by EI, cleared by DI
+    pub halted: bool, // set by HLT until the next interrupt
+    pub accesses: Option<Vec<Access>>, // when set, get_mem and set_mem record every access here
+}
+
+impl Default for State8080 {
+    fn default() -> State8080 {
+        State8080::new()
+    }
+}
+
+impl State8080 {
+    // creates a cpu with every register and flag cleared, and all of memory set to 0
+    pub fn new() -> State8080 {
+        let cc = ConditionCodes {
+            z: false,
+            s: false,
+            p: false,
+            cy: false,
+            ac: false,
+        };
+
+        State8080 {
+            a: 0,
+            b: 0,
+            c: 0,
+            d: 0,
+            e: 0,
+            h: 0,
+            l: 0,
+            sp: 0,
+            pc: 0,
+            memory: new_memory(),
+            cc,
+            int_enable: false,
+            halted: false,
+            accesses: None,
+        }
+    }
+
+    // for debugging. converts the fields to strings for printing.
+    pub fn dump_state(&self) -> String {
+        let mut s = "a:".to_string();
+        s.push_str(&self.a.to_string());
+        s.push_str(" b:");
+        s.push_str(&self.b.to_string());
+        s.push_str(" c:");
+        s.push_str(&self.c.to_string());
+        s.push_str(" d:");
+        s.push_str(&self.d.to_string());
+        s.push_str(" e:");
+        s.push_str(&self.e.to_string());
+        s.push_str(" h:");
+        s.push_str(&self.h.to_string());
+        s.push_str(" l:");
+        s.push_str(&self.l.to_string());
+        s.push_str(" sp:");
+        s.push_str(&self.sp.to_string());
+        s.push_str(" pc:");
+        s.push_str(&self.pc.to_string());
+        s.push_str(" memory size:");
+        s.push_str(&self.memory.len().to_string());
+
+        s
+    }
+
+    // the registers and flags on one line, for the debugger
+    pub fn registers(&self) -> String {
+        let flag = |set: bool, name: &'static str| if set { name } else { "-" };
+        let mut s = format!(
+            "A={:02x} BC={:02x}{:02x} DE={:02x}{:02x} HL={:02x}{:02x} SP={:04x} PC={:04x}  flags {} {} {} {} {}",
+            self.a, self.b, self.c, self.d, self.e, self.h, self.l, self.sp, self.pc,
+            flag(self.cc.s, "S"), flag(self.cc.z, "Z"), flag(self.cc.ac, "AC"), flag(self.cc.p, "P"), flag(self.cc.cy, "CY"),
+        );
+        s.push_str(if self.int_enable { "  EI" } else { "  DI" });
+        if self.halted {
+            s.push_str("  halted");
+        }
+        s
+    }
+
+    // sets the zero (z), sign (s) and parity (p) condition codes from the low 8 bits of the result, which is all that
+    // is stored, so a carry out still gives zero
+    fn set_zsp(&mut self, result: u16) {
+        let flags = ZSP[(result & 0xff) as usize];
+        self.cc.z = flags & Z != 0;
+        self.cc.s = flags & S != 0;
+        self.cc.p = flags & P != 0;
+    }
+
+    // sets the carry (cy) condition code (for u16)
+    fn set_carry_flag(&mut self, result: u16) {
+        self.cc.cy = result > 0xff;
+    }
+
+    // sets the carry (cy) condition code (for u32)
+    fn set_carry_flag_double(&mut self, result: u32) {
+        self.cc.cy = result > 0xffff;
+    }
+
+    // packs the condition codes into the flags byte pushed by PUSH PSW.
+    // the layout is S Z 0 AC 0 P 1 CY, from bit 7 down to bit 0.
+    pub fn get_psw(&self) -> u8 {
+        (self.cc.s as u8) << 7 |
+        (self.cc.z as u8) << 6 |
+        (self.cc.ac as u8) << 4 |
+        (self.cc.p as u8) << 2 |
+        1 << 1 |
+        (self.cc.cy as u8)
+    }
+
+    // unpacks the flags byte popped by POP PSW into the condition codes
+    pub fn set_psw(&mut self, psw: u8) {
+        self.cc.s = 0x80 == psw & 0x80;
+        self.cc.z = 0x40 == psw & 0x40;
+        self.cc.ac = 0x10 == psw & 0x10;
+        self.cc.p = 0x04 == psw & 0x04;
+        self.cc.cy = 0x01 == psw & 0x01;
+    }
+
+    // concatenates h and l register values, and returns hl
+    pub fn get_hl(&self) -> u16 {
+        (self.h as u16) << 8 | (self.l as u16)
+    }
+
+    // returns the byte at the 16-bit address passed-in
+    pub fn get_mem(&mut self, addr: u16) -> u8 {
+        let value = self.memory[addr as usize];
+        if let Some(accesses) = &mut self.accesses {
+            accesses.push(Access { addr, value, write: false });
+        }
+        value
+    }
+
+    // sets the byte at the 16-bit address passed-in
+    pub fn set_mem(&mut self, addr: u16, val: u8) {
+        self.memory[addr as usize] = val;
+        if let Some(accesses) = &mut self.accesses {
+            accesses.push(Access { addr, value: val, write: true });
+        }
+    }
+}
+
+
+// the number of clock cycles (states) each instruction takes.
+// conditional calls and returns take 6 more when the condition is met.
+pub(crate) const CYCLES: [u8; 256] = [
+    4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4, // 0x00..0x0f
+    4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4, // 0x10..0x1f
+    4, 10, 16, 5, 5, 5, 7, 4, 4, 10, 16, 5, 5, 5, 7, 4, // 0x20..0x2f
+    4, 10, 13, 5, 10, 10, 10, 4, 4, 10, 13, 5, 5, 5, 7, 4, // 0x30..0x3f
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5, // 0x40..0x4f
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5, // 0x50..0x5f
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5, // 0x60..0x6f
+    7, 7, 7, 7, 7, 7, 7, 7, 5, 5, 5, 5, 5, 5, 7, 5, // 0x70..0x7f
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x80..0x8f
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x90..0x9f
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0xa0..0xaf
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0xb0..0xbf
+    5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11, // 0xc0..0xcf
+    5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11, // 0xd0..0xdf
+    5, 10, 10, 18, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11, // 0xe0..0xef
+    5, 10, 10, 4, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11, // 0xf0..0xff
+];
+
+// emulates one 8080 instruction, and returns the number of clock cycles it took
+pub fn emulate(state: &mut State8080) -> u8 {
+    if state.halted {
+        // HLT only ends when an interrupt arrives. the cpu idles, as if running NOPs.
+        return 4;
+    }
+
+    // fetching the instruction reads memory directly, so that only the instruction's own reads reach get_mem
+    let opcode: u8 = state.memory[state.pc as usize]; // only needs 4 bytes, but rust doesn't have that...
+    let byte_2: u8 = state.memory[state.pc.wrapping_add(1) as usize];
+    let byte_3: u8 = state.memory[state.pc.wrapping_add(2) as usize];
+    let mut cycles: u8 = CYCLES[opcode as usize];
+    match opcode {
+        0x00 => {
+            // NOP
+            // (do nothing)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x01 => {
+            // LXI B,D16
+            state.b = byte_3;
+            state.c = byte_2;
+            state.pc = state.pc.wrapping_add(3);
+        },
+        0x02 => {
+            // STAX B
+            let bc: u16 = ((state.b as u16) << 8) | (state.c as u16);
+            state.set_mem(bc, state.a);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x03 => {
+            // INX B
+            let bc: u16 = ((state.b as u16) << 8) | (state.c as u16);
+            let bc_inc: u16 = bc.wrapping_add(1);
+
+            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
+            state.b = (bc_inc >> 8) as u8;
+
+            // truncate the leftmost 8 bits
+            state.c = bc_inc as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x04 => {
+            // INR B
+            let sum: u16 = (state.b as u16) + 1;
+            state.b = sum as u8;
+            state.set_zsp(sum);
+            state.cc.ac = (sum & 0xf) == 0;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x05 => {
+            // DCR B
+            let diff: u16 = (state.b as u16).wrapping_sub(1);
+            state.b = diff as u8;
+            state.set_zsp(diff);
+            state.cc.ac = (diff & 0xf) != 0xf;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x06 => {
+            // MVI B,D8
+            state.b = byte_2;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0x07 => {
+            // RLC
+            let x: u8 = state.a;
+            state.a = x.rotate_left(1);
+            state.cc.cy = 0x80 == (x & 0x80);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x08 => {
+            // - (undocumented, behaves like NOP)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x09 => {
+            // DAD B
+            let hl: u16 = state.get_hl();
+            let bc: u16 = ((state.b as u16) << 8) | (state.c as u16);
+
+            let sum: u32 = (hl as u32) + (bc as u32);
+
+            // h stores the leftmost 8 bits. l stores the rightmost 8 bits.
+            // if we cast sum from u16 to u8, then the leftmost 8 bits are dropped.
+            state.l = sum as u8;
+            state.h = (sum >> 8) as u8;
+
+            state.set_carry_flag_double(sum);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x0a => {
+            // LDAX B
+            let bc: u16 = ((state.b as u16) << 8) | (state.c as u16);
+            state.a = state.get_mem(bc);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x0b => {
+            // DCX B
+            let bc: u16 = ((state.b as u16) << 8) | (state.c as u16);
+            let bc_dec: u16 = bc.wrapping_sub(1);
+
+            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
+            state.b = (bc_dec >> 8) as u8;
+
+            // truncate the leftmost 8 bits
+            state.c = bc_dec as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x0c => {
+            // INR C
+            let sum: u16 = (state.c as u16) + 1;
+            state.c = sum as u8;
+            state.set_zsp(sum);
+            state.cc.ac = (sum & 0xf) == 0;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x0d => {
+            // DCR C
+            let diff: u16 = (state.c as u16).wrapping_sub(1);
+            state.c = diff as u8;
+            state.set_zsp(diff);
+            state.cc.ac = (diff & 0xf) != 0xf;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x0e => {
+            // MVI C,D8
+            state.c = byte_2;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0x0f => {
+            // RRC
+            let x: u8 = state.a;
+            state.a = x.rotate_right(1);
+            state.cc.cy = 1 == (x & 1);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x10 => {
+            // - (undocumented, behaves like NOP)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x11 => {
+            // LXI D,D16
+            state.d = byte_3;
+            state.e = byte_2;
+            state.pc = state.pc.wrapping_add(3);
+        },
+        0x12 => {
+            // STAX D
+            let de: u16 = ((state.d as u16) << 8) | (state.e as u16);
+            state.set_mem(de, state.a);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x13 => {
+            // INX D
+            let de: u16 = ((state.d as u16) << 8) | (state.e as u16);
+            let de_inc: u16 = de.wrapping_add(1);
+
+            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
+            state.d = (de_inc >> 8) as u8;
+
+            // truncate the leftmost 8 bits
+            state.e = de_inc as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x14 => {
+            // INR D
+            let sum: u16 = (state.d as u16) + 1;
+            state.d = sum as u8;
+            state.set_zsp(sum);
+            state.cc.ac = (sum & 0xf) == 0;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x15 => {
+            // DCR D
+            let diff: u16 = (state.d as u16).wrapping_sub(1);
+            state.d = diff as u8;
+            state.set_zsp(diff);
+            state.cc.ac = (diff & 0xf) != 0xf;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x16 => {
+            // MVI D,D8
+            state.d = byte_2;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0x17 => {
+            // RAL
+            let x: u8 = state.a;
+            state.a = (x << 1) | (state.cc.cy as u8);
+            state.cc.cy = 0x80 == (x & 0x80);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x18 => {
+            // - (undocumented, behaves like NOP)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x19 => {
+            // DAD D
+            let hl: u16 = state.get_hl();
+            let de: u16 = ((state.d as u16) << 8) | (state.e as u16);
+
+            let sum: u32 = (hl as u32) + (de as u32);
+
+            // h stores the leftmost 8 bits. l stores the rightmost 8 bits.
+            // if we cast sum from u16 to u8, then the leftmost 8 bits are dropped.
+            state.l = sum as u8;
+            state.h = (sum >> 8) as u8;
+
+            state.set_carry_flag_double(sum);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x1a => {
+            // LDAX D
+            let de: u16 = ((state.d as u16) << 8) | (state.e as u16);
+            state.a = state.get_mem(de);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x1b => {
+            // DCX D
+            let de: u16 = ((state.d as u16) << 8) | (state.e as u16);
+            let de_dec: u16 = de.wrapping_sub(1);
+
+            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
+            state.d = (de_dec >> 8) as u8;
+
+            // truncate the leftmost 8 bits
+            state.e = de_dec as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x1c => {
+            // INR E
+            let sum: u16 = (state.e as u16) + 1;
+            state.e = sum as u8;
+            state.set_zsp(sum);
+            state.cc.ac = (sum & 0xf) == 0;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x1d => {
+            // DCR E
+            let diff: u16 = (state.e as u16).wrapping_sub(1);
+            state.e = diff as u8;
+            state.set_zsp(diff);
+            state.cc.ac = (diff & 0xf) != 0xf;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x1e => {
+            // MVI E,D8
+            state.e = byte_2;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0x1f => {
+            // RAR
+            let x: u8 = state.a;
+            state.a = ((state.cc.cy as u8) << 7) | (x >> 1);
+            state.cc.cy = 1 == (x & 1);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x20 => {
+            // - (undocumented, behaves like NOP)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x21 => {
+            // LXI H,D16
+            state.h = byte_3;
+            state.l = byte_2;
+            state.pc = state.pc.wrapping_add(3);
+        },
+        0x22 => {
+            // SHLD adr
+            let addr: u16 = ((byte_3 as u16) << 8) | (byte_2 as u16);
+            state.set_mem(addr, state.l);
+            state.set_mem(addr.wrapping_add(1), state.h);
+            state.pc = state.pc.wrapping_add(3);
+        },
+        0x23 => {
+            // INX H
+            let hl: u16 = state.get_hl();
+            let hl_inc: u16 = hl.wrapping_add(1);
+
+            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
+            state.h = (hl_inc >> 8) as u8;
+
+            // truncate the leftmost 8 bits
+            state.l = hl_inc as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x24 => {
+            // INR H
+            let sum: u16 = (state.h as u16) + 1;
+            state.h = sum as u8;
+            state.set_zsp(sum);
+            state.cc.ac = (sum & 0xf) == 0;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x25 => {
+            // DCR H
+            let diff: u16 = (state.h as u16).wrapping_sub(1);
+            state.h = diff as u8;
+            state.set_zsp(diff);
+            state.cc.ac = (diff & 0xf) != 0xf;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x26 => {
+            // MVI H,D8
+            state.h = byte_2;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0x27 => {
+            // DAA
+            // first correct the low nibble, then the high nibble, as if adding the correction with ADI
+            let mut correction: u8 = 0;
+            let mut cy: bool = state.cc.cy;
+            let lsb: u8 = state.a & 0x0f;
+            let msb: u8 = state.a >> 4;
+            if state.cc.ac || lsb > 9 {
+                correction += 0x06;
+            }
+            if state.cc.cy || msb > 9 || (msb >= 9 && lsb > 9) {
+                correction += 0x60;
+                cy = true;
+            }
+
+            let sum: u16 = add(state.a, correction);
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (correction & 0xf) > 0xf;
+            state.cc.cy = cy;
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x28 => {
+            // - (undocumented, behaves like NOP)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x29 => {
+            // DAD H
+            let hl: u16 = state.get_hl();
+
+            let sum: u32 = (hl as u32) + (hl as u32);
+
+            // h stores the leftmost 8 bits. l stores the rightmost 8 bits.
+            // if we cast sum from u16 to u8, then the leftmost 8 bits are dropped.
+            state.l = sum as u8;
+            state.h = (sum >> 8) as u8;
+
+            state.set_carry_flag_double(sum);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x2a => {
+            // LHLD adr
+            let addr: u16 = ((byte_3 as u16) << 8) | (byte_2 as u16);
+            state.l = state.get_mem(addr);
+            state.h = state.get_mem(addr.wrapping_add(1));
+            state.pc = state.pc.wrapping_add(3);
+        },
+        0x2b => {
+            // DCX H
+            let hl: u16 = state.get_hl();
+            let hl_dec: u16 = hl.wrapping_sub(1);
+
+            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
+            state.h = (hl_dec >> 8) as u8;
+
+            // truncate the leftmost 8 bits
+            state.l = hl_dec as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x2c => {
+            // INR L
+            let sum: u16 = (state.l as u16) + 1;
+            state.l = sum as u8;
+            state.set_zsp(sum);
+            state.cc.ac = (sum & 0xf) == 0;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x2d => {
+            // DCR L
+            let diff: u16 = (state.l as u16).wrapping_sub(1);
+            state.l = diff as u8;
+            state.set_zsp(diff);
+            state.cc.ac = (diff & 0xf) != 0xf;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x2e => {
+            // MVI L,D8
+            state.l = byte_2;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0x2f => {
+            // CMA (not)
+            state.a = !state.a;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x30 => {
+            // - (undocumented, behaves like NOP)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x31 => {
+            // LXI SP,D16
+            state.sp = ((byte_3 as u16) << 8) | (byte_2 as u16);
+            state.pc = state.pc.wrapping_add(3);
+        },
+        0x32 => {
+            // STA adr
+            let addr: u16 = ((byte_3 as u16) << 8) | (byte_2 as u16);
+            state.set_mem(addr, state.a);
+            state.pc = state.pc.wrapping_add(3);
+        },
+        0x33 => {
+            // INX SP
+            state.sp = state.sp.wrapping_add(1);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x34 => {
+            // INR M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            let sum: u16 = (m as u16) + 1;
+            state.set_mem(hl, sum as u8);
+            state.set_zsp(sum);
+            state.cc.ac = (sum & 0xf) == 0;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x35 => {
+            // DCR M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            let diff: u16 = (m as u16).wrapping_sub(1);
+            state.set_mem(hl, diff as u8);
+            state.set_zsp(diff);
+            state.cc.ac = (diff & 0xf) != 0xf;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x36 => {
+            // MVI M,D8
+            let hl: u16 = state.get_hl();
+            state.set_mem(hl, byte_2);
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0x37 => {
+            // STC
+            state.cc.cy = true;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x38 => {
+            // - (undocumented, behaves like NOP)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x39 => {
+            // DAD SP
+            let hl: u16 = state.get_hl();
+
+            let sum: u32 = (hl as u32) + (state.sp as u32);
+
+            // h stores the leftmost 8 bits. l stores the rightmost 8 bits.
+            // if we cast sum from u16 to u8, then the leftmost 8 bits are dropped.
+            state.l = sum as u8;
+            state.h = (sum >> 8) as u8;
+
+            state.set_carry_flag_double(sum);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x3a => {
+            // LDA adr
+            let addr: u16 = ((byte_3 as u16) << 8) | (byte_2 as u16);
+            state.a = state.get_mem(addr);
+            state.pc = state.pc.wrapping_add(3);
+        },
+        0x3b => {
+            // DCX SP
+            state.sp = state.sp.wrapping_sub(1);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x3c => {
+            // INR A
+            let sum: u16 = (state.a as u16) + 1;
+            state.a = sum as u8;
+            state.set_zsp(sum);
+            state.cc.ac = (sum & 0xf) == 0;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x3d => {
+            // DCR A
+            let diff: u16 = (state.a as u16).wrapping_sub(1);
+            state.a = diff as u8;
+            state.set_zsp(diff);
+            state.cc.ac = (diff & 0xf) != 0xf;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x3e => {
+            // MVI A,D8
+            state.a = byte_2;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0x3f => {
+            // CMC
+            state.cc.cy = !state.cc.cy;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x40 => {
+            // MOV B,B
+            // (does nothing)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x41 => {
+            // MOV B,C
+            state.b = state.c;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x42 => {
+            // MOV B,D
+            state.b = state.d;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x43 => {
+            // MOV B,E
+            state.b = state.e;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x44 => {
+            // MOV B,H
+            state.b = state.h;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x45 => {
+            // MOV B,L
+            state.b = state.l;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x46 => {
+            // MOV B,M
+            let hl: u16 = state.get_hl();
+            state.b = state.get_mem(hl);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x47 => {
+            // MOV B,A
+            state.b = state.a;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x48 => {
+            // MOV C,B
+            state.c = state.b;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x49 => {
+            // MOV C,C
+            // (does nothing)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x4a => {
+            // MOV C,D
+            state.c = state.d;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x4b => {
+            // MOV C,E
+            state.c = state.e;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x4c => {
+            // MOV C,H
+            state.c = state.h;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x4d => {
+            // MOV C,L
+            state.c = state.l;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x4e => {
+            // MOV C,M
+            let hl: u16 = state.get_hl();
+            state.c = state.get_mem(hl);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x4f => {
+            // MOV C,A
+            state.c = state.a;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x50 => {
+            // MOV D,B
+            state.d = state.b;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x51 => {
+            // MOV D,C
+            state.d = state.c;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x52 => {
+            // MOV D,D
+            // (does nothing)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x53 => {
+            // MOV D,E
+            state.d = state.e;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x54 => {
+            // MOV D,H
+            state.d = state.h;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x55 => {
+            // MOV D,L
+            state.d = state.l;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x56 => {
+            // MOV D,M
+            let hl: u16 = state.get_hl();
+            state.d = state.get_mem(hl);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x57 => {
+            // MOV D,A
+            state.d = state.a;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x58 => {
+            // MOV E,B
+            state.e = state.b;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x59 => {
+            // MOV E,C
+            state.e = state.c;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x5a => {
+            // MOV E,D
+            state.e = state.d;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x5b => {
+            // MOV E,E
+            // (does nothing)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x5c => {
+            // MOV E,H
+            state.e = state.h;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x5d => {
+            // MOV E,L
+            state.e = state.l;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x5e => {
+            // MOV E,M
+            let hl: u16 = state.get_hl();
+            state.e = state.get_mem(hl);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x5f => {
+            // MOV E,A
+            state.e = state.a;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x60 => {
+            // MOV H,B
+            state.h = state.b;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x61 => {
+            // MOV H,C
+            state.h = state.c;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x62 => {
+            // MOV H,D
+            state.h = state.d;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x63 => {
+            // MOV H,E
+            state.h = state.e;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x64 => {
+            // MOV H,H
+            // (does nothing)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x65 => {
+            // MOV H,L
+            state.h = state.l;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x66 => {
+            // MOV H,M
+            let hl: u16 = state.get_hl();
+            state.h = state.get_mem(hl);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x67 => {
+            // MOV H,A
+            state.h = state.a;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x68 => {
+            // MOV L,B
+            state.l = state.b;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x69 => {
+            // MOV L,C
+            state.l = state.c;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x6a => {
+            // MOV L,D
+            state.l = state.d;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x6b => {
+            // MOV L,E
+            state.l = state.e;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x6c => {
+            // MOV L,H
+            state.l = state.h;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x6d => {
+            // MOV L,L
+            // (does nothing)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x6e => {
+            // MOV L,M
+            let hl: u16 = state.get_hl();
+            state.l = state.get_mem(hl);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x6f => {
+            // MOV L,A
+            state.l = state.a;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x70 => {
+            // MOV M,B
+            let hl: u16 = state.get_hl();
+            state.set_mem(hl, state.b);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x71 => {
+            // MOV M,C
+            let hl: u16 = state.get_hl();
+            state.set_mem(hl, state.c);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x72 => {
+            // MOV M,D
+            let hl: u16 = state.get_hl();
+            state.set_mem(hl, state.d);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x73 => {
+            // MOV M,E
+            let hl: u16 = state.get_hl();
+            state.set_mem(hl, state.e);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x74 => {
+            // MOV M,H
+            let hl: u16 = state.get_hl();
+            state.set_mem(hl, state.h);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x75 => {
+            // MOV M,L
+            let hl: u16 = state.get_hl();
+            state.set_mem(hl, state.l);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x76 => {
+            // HLT
+            // stop until the next interrupt
+            state.halted = true;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x77 => {
+            // MOV M,A
+            let hl: u16 = state.get_hl();
+            state.set_mem(hl, state.a);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x78 => {
+            // MOV A,B
+            state.a = state.b;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x79 => {
+            // MOV A,C
+            state.a = state.c;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x7a => {
+            // MOV A,D
+            state.a = state.d;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x7b => {
+            // MOV A,E
+            state.a = state.e;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x7c => {
+            // MOV A,H
+            state.a = state.h;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x7d => {
+            // MOV A,L
+            state.a = state.l;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x7e => {
+            // MOV A,M
+            let hl: u16 = state.get_hl();
+            state.a = state.get_mem(hl);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x7f => {
+            // MOV A,A
+            // (does nothing)
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x80 => {
+            // ADD B
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, state.b);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (state.b & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x81 => {
+            // ADD C
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, state.c);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (state.c & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x82 => {
+            // ADD D
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, state.d);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (state.d & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x83 => {
+            // ADD E
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, state.e);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (state.e & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x84 => {
+            // ADD H
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, state.h);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (state.h & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x85 => {
+            // ADD L
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, state.l);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (state.l & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x86 => {
+            // ADD M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, m);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (m & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x87 => {
+            // ADD A
+            let a: u8 = state.a;
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, a);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (a & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x88 => {
+            // ADC B
+            let sum: u16 = add(state.a, state.b) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (state.b & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x89 => {
+            // ADC C
+            let sum: u16 = add(state.a, state.c) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (state.c & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x8a => {
+            // ADC D
+            let sum: u16 = add(state.a, state.d) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (state.d & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x8b => {
+            // ADC E
+            let sum: u16 = add(state.a, state.e) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (state.e & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x8c => {
+            // ADC H
+            let sum: u16 = add(state.a, state.h) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (state.h & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x8d => {
+            // ADC L
+            let sum: u16 = add(state.a, state.l) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (state.l & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x8e => {
+            // ADC M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            let sum: u16 = add(state.a, m) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (m & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x8f => {
+            // ADC A
+            let a: u8 = state.a;
+            let sum: u16 = add(state.a, a) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (a & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x90 => {
+            // SUB B
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.b);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.b & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x91 => {
+            // SUB C
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.c);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.c & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x92 => {
+            // SUB D
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.d);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.d & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x93 => {
+            // SUB E
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.e);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.e & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x94 => {
+            // SUB H
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.h);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.h & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x95 => {
+            // SUB L
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.l);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.l & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x96 => {
+            // SUB M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, m);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (m & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x97 => {
+            // SUB A
+            let a: u8 = state.a;
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, a);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (a & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x98 => {
+            // SBB B
+            let diff: u16 = sub(state.a, state.b).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.b & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x99 => {
+            // SBB C
+            let diff: u16 = sub(state.a, state.c).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.c & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x9a => {
+            // SBB D
+            let diff: u16 = sub(state.a, state.d).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.d & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x9b => {
+            // SBB E
+            let diff: u16 = sub(state.a, state.e).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.e & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x9c => {
+            // SBB H
+            let diff: u16 = sub(state.a, state.h).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.h & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x9d => {
+            // SBB L
+            let diff: u16 = sub(state.a, state.l).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.l & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x9e => {
+            // SBB M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            let diff: u16 = sub(state.a, m).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (m & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0x9f => {
+            // SBB A
+            let a: u8 = state.a;
+            let diff: u16 = sub(state.a, a).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (a & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa0 => {
+            // ANA B
+            let and: u16 = (state.a as u16) & (state.b as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | state.b) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa1 => {
+            // ANA C
+            let and: u16 = (state.a as u16) & (state.c as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | state.c) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa2 => {
+            // ANA D
+            let and: u16 = (state.a as u16) & (state.d as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | state.d) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa3 => {
+            // ANA E
+            let and: u16 = (state.a as u16) & (state.e as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | state.e) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa4 => {
+            // ANA H
+            let and: u16 = (state.a as u16) & (state.h as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | state.h) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa5 => {
+            // ANA L
+            let and: u16 = (state.a as u16) & (state.l as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | state.l) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa6 => {
+            // ANA M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            let and: u16 = (state.a as u16) & (m as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | m) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa7 => {
+            // ANA A
+            let a: u8 = state.a;
+            let and: u16 = (state.a as u16) & (a as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | a) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa8 => {
+            // XRA B
+            let xor: u16 = (state.a as u16) ^ (state.b as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xa9 => {
+            // XRA C
+            let xor: u16 = (state.a as u16) ^ (state.c as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xaa => {
+            // XRA D
+            let xor: u16 = (state.a as u16) ^ (state.d as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xab => {
+            // XRA E
+            let xor: u16 = (state.a as u16) ^ (state.e as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xac => {
+            // XRA H
+            let xor: u16 = (state.a as u16) ^ (state.h as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xad => {
+            // XRA L
+            let xor: u16 = (state.a as u16) ^ (state.l as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xae => {
+            // XRA M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            let xor: u16 = (state.a as u16) ^ (m as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xaf => {
+            // XRA A
+            let a: u8 = state.a;
+            let xor: u16 = (state.a as u16) ^ (a as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb0 => {
+            // ORA B
+            let or: u16 = (state.a as u16) | (state.b as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb1 => {
+            // ORA C
+            let or: u16 = (state.a as u16) | (state.c as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb2 => {
+            // ORA D
+            let or: u16 = (state.a as u16) | (state.d as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb3 => {
+            // ORA E
+            let or: u16 = (state.a as u16) | (state.e as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb4 => {
+            // ORA H
+            let or: u16 = (state.a as u16) | (state.h as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb5 => {
+            // ORA L
+            let or: u16 = (state.a as u16) | (state.l as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb6 => {
+            // ORA M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            let or: u16 = (state.a as u16) | (m as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb7 => {
+            // ORA A
+            let a: u8 = state.a;
+            let or: u16 = (state.a as u16) | (a as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb8 => {
+            // CMP B
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.b);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.b & 0xf);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xb9 => {
+            // CMP C
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.c);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.c & 0xf);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xba => {
+            // CMP D
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.d);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.d & 0xf);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xbb => {
+            // CMP E
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.e);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.e & 0xf);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xbc => {
+            // CMP H
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.h);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.h & 0xf);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xbd => {
+            // CMP L
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, state.l);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (state.l & 0xf);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xbe => {
+            // CMP M
+            let hl: u16 = state.get_hl();
+            let m: u8 = state.get_mem(hl);
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, m);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (m & 0xf);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xbf => {
+            // CMP A
+            let a: u8 = state.a;
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, a);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (a & 0xf);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xc0 => {
+            // RNZ
+            if !state.cc.z {
+                state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+                state.sp = state.sp.wrapping_add(2);
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(1);
+            }
+        },
+        0xc1 => {
+            // POP B
+            state.c = state.get_mem(state.sp);
+            state.b = state.get_mem(state.sp.wrapping_add(1));
+            state.sp = state.sp.wrapping_add(2);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xc2 => {
+            // JNZ address
+            if !state.cc.z {
+                // take the branch and set the PC accordingly
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+            } else {
+                // skip to the next instruction if the branch isn't taken
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xc3 => {
+            // JMP address
+            state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+        },
+        0xc4 => {
+            // CNZ address
+            if !state.cc.z {
+                // push the address of the next instruction, then jump
+                let ret: u16 = state.pc.wrapping_add(3);
+                state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+                state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+                state.sp = state.sp.wrapping_sub(2);
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xc5 => {
+            // PUSH B
+            state.set_mem(state.sp.wrapping_sub(1), state.b);
+            state.set_mem(state.sp.wrapping_sub(2), state.c);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xc6 => {
+            // ADI byte
+            // a and the operand are u8, but we need to capture the carry-out, so we use u16
+            let sum: u16 = add(state.a, byte_2);
+
+            state.set_zsp(sum);
+            state.set_carry_flag(sum);
+            state.cc.ac = (state.a & 0xf) + (byte_2 & 0xf) > 0xf;
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xc7 => {
+            // RST 0
+            let ret: u16 = state.pc.wrapping_add(1);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = 0x00;
+        },
+        0xc8 => {
+            // RZ
+            if state.cc.z {
+                state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+                state.sp = state.sp.wrapping_add(2);
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(1);
+            }
+        },
+        0xc9 => {
+            // RET
+            state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+            state.sp = state.sp.wrapping_add(2);
+        },
+        0xca => {
+            // JZ address
+            if state.cc.z {
+                // take the branch and set the PC accordingly
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+            } else {
+                // skip to the next instruction if the branch isn't taken
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xcb => {
+            // - (undocumented, behaves like JMP)
+            state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+        },
+        0xcc => {
+            // CZ address
+            if state.cc.z {
+                // push the address of the next instruction, then jump
+                let ret: u16 = state.pc.wrapping_add(3);
+                state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+                state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+                state.sp = state.sp.wrapping_sub(2);
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xcd => {
+            // CALL address
+            // push the address of the next instruction, then jump
+            let ret: u16 = state.pc.wrapping_add(3);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+        },
+        0xce => {
+            // ACI byte
+            let sum: u16 = add(state.a, byte_2) + state.cc.cy as u16;
+
+            state.set_zsp(sum);
+            state.cc.ac = (state.a & 0xf) + (byte_2 & 0xf) + (state.cc.cy as u8) > 0xf;
+            state.set_carry_flag(sum);
+
+            state.a = sum as u8;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xcf => {
+            // RST 1
+            let ret: u16 = state.pc.wrapping_add(1);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = 0x08;
+        },
+        0xd0 => {
+            // RNC
+            if !state.cc.cy {
+                state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+                state.sp = state.sp.wrapping_add(2);
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(1);
+            }
+        },
+        0xd1 => {
+            // POP D
+            state.e = state.get_mem(state.sp);
+            state.d = state.get_mem(state.sp.wrapping_add(1));
+            state.sp = state.sp.wrapping_add(2);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xd2 => {
+            // JNC address
+            if !state.cc.cy {
+                // take the branch and set the PC accordingly
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+            } else {
+                // skip to the next instruction if the branch isn't taken
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xd3 => {
+            // OUT byte
+            // the port write is handled by the machine before emulate is called
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xd4 => {
+            // CNC address
+            if !state.cc.cy {
+                // push the address of the next instruction, then jump
+                let ret: u16 = state.pc.wrapping_add(3);
+                state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+                state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+                state.sp = state.sp.wrapping_sub(2);
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xd5 => {
+            // PUSH D
+            state.set_mem(state.sp.wrapping_sub(1), state.d);
+            state.set_mem(state.sp.wrapping_sub(2), state.e);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xd6 => {
+            // SUI byte
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, byte_2);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (byte_2 & 0xf);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xd7 => {
+            // RST 2
+            let ret: u16 = state.pc.wrapping_add(1);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = 0x10;
+        },
+        0xd8 => {
+            // RC
+            if state.cc.cy {
+                state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+                state.sp = state.sp.wrapping_add(2);
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(1);
+            }
+        },
+        0xd9 => {
+            // - (undocumented, behaves like RET)
+            state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+            state.sp = state.sp.wrapping_add(2);
+        },
+        0xda => {
+            // JC address
+            if state.cc.cy {
+                // take the branch and set the PC accordingly
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+            } else {
+                // skip to the next instruction if the branch isn't taken
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xdb => {
+            // IN byte
+            // the port read is handled by the machine before emulate is called
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xdc => {
+            // CC address
+            if state.cc.cy {
+                // push the address of the next instruction, then jump
+                let ret: u16 = state.pc.wrapping_add(3);
+                state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+                state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+                state.sp = state.sp.wrapping_sub(2);
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xdd => {
+            // - (undocumented, behaves like CALL)
+            // push the address of the next instruction, then jump
+            let ret: u16 = state.pc.wrapping_add(3);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+        },
+        0xde => {
+            // SBI byte
+            let diff: u16 = sub(state.a, byte_2).wrapping_sub(state.cc.cy as u16);
+
+            state.set_zsp(diff);
+            state.cc.ac = (state.a & 0xf) >= (byte_2 & 0xf) + (state.cc.cy as u8);
+            state.set_carry_flag(diff);
+
+            state.a = diff as u8;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xdf => {
+            // RST 3
+            let ret: u16 = state.pc.wrapping_add(1);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = 0x18;
+        },
+        0xe0 => {
+            // RPO
+            if !state.cc.p {
+                state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+                state.sp = state.sp.wrapping_add(2);
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(1);
+            }
+        },
+        0xe1 => {
+            // POP H
+            state.l = state.get_mem(state.sp);
+            state.h = state.get_mem(state.sp.wrapping_add(1));
+            state.sp = state.sp.wrapping_add(2);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xe2 => {
+            // JPO address
+            if !state.cc.p {
+                // take the branch and set the PC accordingly
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+            } else {
+                // skip to the next instruction if the branch isn't taken
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xe3 => {
+            // XTHL
+            let l: u8 = state.get_mem(state.sp);
+            let h: u8 = state.get_mem(state.sp.wrapping_add(1));
+            state.set_mem(state.sp, state.l);
+            state.set_mem(state.sp.wrapping_add(1), state.h);
+            state.l = l;
+            state.h = h;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xe4 => {
+            // CPO address
+            if !state.cc.p {
+                // push the address of the next instruction, then jump
+                let ret: u16 = state.pc.wrapping_add(3);
+                state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+                state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+                state.sp = state.sp.wrapping_sub(2);
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xe5 => {
+            // PUSH H
+            state.set_mem(state.sp.wrapping_sub(1), state.h);
+            state.set_mem(state.sp.wrapping_sub(2), state.l);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xe6 => {
+            // ANI byte
+            let and: u16 = (state.a as u16) & (byte_2 as u16);
+
+            state.set_zsp(and);
+            state.cc.cy = false;
+            // the 8080 sets AC from the OR of bit 3 of both operands
+            state.cc.ac = ((state.a | byte_2) & 0x08) != 0;
+
+            state.a = and as u8;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xe7 => {
+            // RST 4
+            let ret: u16 = state.pc.wrapping_add(1);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = 0x20;
+        },
+        0xe8 => {
+            // RPE
+            if state.cc.p {
+                state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+                state.sp = state.sp.wrapping_add(2);
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(1);
+            }
+        },
+        0xe9 => {
+            // PCHL
+            state.pc = state.get_hl();
+        },
+        0xea => {
+            // JPE address
+            if state.cc.p {
+                // take the branch and set the PC accordingly
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+            } else {
+                // skip to the next instruction if the branch isn't taken
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xeb => {
+            // XCHG
+            std::mem::swap(&mut state.h, &mut state.d);
+            std::mem::swap(&mut state.l, &mut state.e);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xec => {
+            // CPE address
+            if state.cc.p {
+                // push the address of the next instruction, then jump
+                let ret: u16 = state.pc.wrapping_add(3);
+                state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+                state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+                state.sp = state.sp.wrapping_sub(2);
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xed => {
+            // - (undocumented, behaves like CALL)
+            // push the address of the next instruction, then jump
+            let ret: u16 = state.pc.wrapping_add(3);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+        },
+        0xee => {
+            // XRI byte
+            let xor: u16 = (state.a as u16) ^ (byte_2 as u16);
+
+            state.set_zsp(xor);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = xor as u8;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xef => {
+            // RST 5
+            let ret: u16 = state.pc.wrapping_add(1);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = 0x28;
+        },
+        0xf0 => {
+            // RP
+            if !state.cc.s {
+                state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+                state.sp = state.sp.wrapping_add(2);
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(1);
+            }
+        },
+        0xf1 => {
+            // POP PSW
+            let psw: u8 = state.get_mem(state.sp);
+            state.set_psw(psw);
+            state.a = state.get_mem(state.sp.wrapping_add(1));
+            state.sp = state.sp.wrapping_add(2);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xf2 => {
+            // JP address
+            if !state.cc.s {
+                // take the branch and set the PC accordingly
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+            } else {
+                // skip to the next instruction if the branch isn't taken
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xf3 => {
+            // DI
+            state.int_enable = false;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xf4 => {
+            // CP address
+            if !state.cc.s {
+                // push the address of the next instruction, then jump
+                let ret: u16 = state.pc.wrapping_add(3);
+                state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+                state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+                state.sp = state.sp.wrapping_sub(2);
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xf5 => {
+            // PUSH PSW
+            state.set_mem(state.sp.wrapping_sub(1), state.a);
+            let psw: u8 = state.get_psw();
+            state.set_mem(state.sp.wrapping_sub(2), psw);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xf6 => {
+            // ORI byte
+            let or: u16 = (state.a as u16) | (byte_2 as u16);
+
+            state.set_zsp(or);
+            state.cc.cy = false;
+            state.cc.ac = false;
+
+            state.a = or as u8;
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xf7 => {
+            // RST 6
+            let ret: u16 = state.pc.wrapping_add(1);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = 0x30;
+        },
+        0xf8 => {
+            // RM
+            if state.cc.s {
+                state.pc = (state.get_mem(state.sp) as u16) | ((state.get_mem(state.sp.wrapping_add(1)) as u16) << 8);
+                state.sp = state.sp.wrapping_add(2);
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(1);
+            }
+        },
+        0xf9 => {
+            // SPHL
+            state.sp = state.get_hl();
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xfa => {
+            // JM address
+            if state.cc.s {
+                // take the branch and set the PC accordingly
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+            } else {
+                // skip to the next instruction if the branch isn't taken
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xfb => {
+            // EI
+            state.int_enable = true;
+            state.pc = state.pc.wrapping_add(1);
+        },
+        0xfc => {
+            // CM address
+            if state.cc.s {
+                // push the address of the next instruction, then jump
+                let ret: u16 = state.pc.wrapping_add(3);
+                state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+                state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+                state.sp = state.sp.wrapping_sub(2);
+                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+                cycles += 6;
+            } else {
+                state.pc = state.pc.wrapping_add(3);
+            }
+        },
+        0xfd => {
+            // - (undocumented, behaves like CALL)
+            // push the address of the next instruction, then jump
+            let ret: u16 = state.pc.wrapping_add(3);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
+        },
+        0xfe => {
+            // CPI byte
+            // a borrow out of the 8 bits shows up as a carry in the u16 difference
+            let diff: u16 = sub(state.a, byte_2);
+
+            state.set_zsp(diff);
+            state.set_carry_flag(diff);
+            state.cc.ac = (state.a & 0xf) >= (byte_2 & 0xf);
+            state.pc = state.pc.wrapping_add(2);
+        },
+        0xff => {
+            // RST 7
+            let ret: u16 = state.pc.wrapping_add(1);
+            state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+            state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+            state.sp = state.sp.wrapping_sub(2);
+            state.pc = 0x38;
+        },
+    }
+    cycles
+}
+
+// adds u8 values, and returns the sum as a u16
+fn add(a: u8, b: u8) -> u16 {
+    (a as u16) + (b as u16)
+}
+
+// subtracts u8 values, and returns the difference as a u16.
+// a borrow wraps around, setting the bits above the low 8 bits.
+fn sub(a: u8, b: u8) -> u16 {
+    (a as u16).wrapping_sub(b as u16)
+}
@@ -0,0 +1,692 @@
+// an interactive monitor for investigating a program: step through it, look at and change the registers and memory,
+// and disassemble the code around the pc.
+// addresses and values are typed in hex (with or without an h suffix or 0x prefix), and counts in decimal.
+// step and continue stop at the breakpoints, watchpoints and port breakpoints that have been set.
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::breakpoints::Breakpoints;
+use crate::breakpoints::Condition;
+use crate::breakpoints::Kind;
+use crate::breakpoints::Stop;
+use crate::cpu::Access;
+use crate::cpu::State8080;
+use crate::disassembler;
+use crate::history::History;
+use crate::invaders;
+use crate::loader::Program;
+use crate::machine;
+use crate::machine::Machine;
+use crate::reverse::Recorder;
+use crate::reverse::Snapshot;
+use crate::savestate;
+use crate::trace::Tracer;
+
+const HELP: &str = "\
+commands:
+  s, step [n]               run n instructions (default 1)
+  c, continue [n]           run until the cpu halts, or for at most n instructions
+  r, regs                   show the registers and flags
+  m, mem <addr> [len]       show len bytes of memory (default 64)
+  w, write <addr> <byte>... change memory
+  d, disasm [addr] [n]      disassemble n instructions (default 10) from addr, or around the pc
+  set <reg> <value>         set a register (a b c d e h l bc de hl sp pc) or flag (s z ac p cy)
+  b, break <addr> [if <condition>]
+                            stop when the pc gets to addr
+  watch <addr>[-<end>] [read|write|access] [if <condition>]
+                            stop after an instruction writes (or reads) memory from addr to end
+  port in|out <port> [if <condition>]
+                            stop before an IN or OUT on the port
+  bl, breaks                list the breakpoints, with the times each has been hit
+  delete <id>               remove a breakpoint
+  enable <id>, disable <id> turn a breakpoint on or off
+  ignore <id> <n>           let the next n hits of a breakpoint pass
+  cond <id> [<condition>]   change or remove the condition of a breakpoint
+  reset                     reload the program and start again
+  save <file>               save the state of the cpu, memory and devices to the file
+  load <file>               carry on from a saved state
+  trace <file>              write a line for each instruction run to the file
+  trace on|off              start or stop writing the trace
+  trace range <addr>-<end>  only trace the instructions between the addresses (all to trace everywhere)
+  trace cycles <from>-<to>  only trace between the cycle counts, given in decimal (all for every cycle)
+  history [n]               show the last n instructions run (default 20)
+  history size <n>          keep the last n instructions (default 10000)
+  history auto on|off       show the backtrace and last instructions whenever the program stops or halts
+  bt, backtrace             show the routines that have been called and haven't returned
+  rs, reverse-step [n]      go back n instructions (default 1)
+  rc, reverse-continue      go back to the last time a breakpoint or write watchpoint was hit
+  rc write <addr>[-<end>]   go back to just before the last instruction that wrote memory from addr to end
+  q, quit                   leave the debugger
+an empty line repeats the last step, continue, mem or disasm.
+conditions compare registers, flags, m (the byte at hl) and [addr] with == != < <= > >=, and combine them
+with && || !, as in: a == 10 && cy. numbers in them are hex, and start with a digit: 0ffh, 0x1f.";
+
+const PROMPT: &str = "(8080) ";
+
+// the most instructions step prints one by one
+const STEP_LISTING: u64 = 20;
+
+pub struct Debugger<M: Machine> {
+    pub state: State8080,
+    pub machine: M,
+    pub breakpoints: Breakpoints,
+    pub tracer: Tracer,
+    pub history: History,
+    pub auto_history: bool, // show the history when the program stops by itself
+    pub recorder: Recorder,
+    pub frame_interrupts: bool, // interrupt the cpu with RST 1 and RST 2 every frame, like the space invaders video
+    initial: State8080, // the state after loading, for reset
+    last: String, // the last command, which an empty line repeats
+}
+
+// reads a number in hex, as 1a3f, 1a3fh or 0x1a3f
+pub fn parse_hex(s: &str) -> Result<u16, String> {
+    let lower = s.to_lowercase();
+    let digits = lower.strip_prefix("0x").or(lower.strip_suffix('h')).unwrap_or(&lower);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("'{}' isn't a hex number", s))
+}
+
+fn parse_count(s: &str) -> Result<u64, String> {
+    s.parse::<u64>().map_err(|_| format!("'{}' isn't a count", s))
+}
+
+impl<M: Machine> Debugger<M> {
+    // loads the program and points the pc at its start
+    pub fn new(program: &Program, machine: M) -> Debugger<M> {
+        let mut state = State8080::new();
+        program.load(&mut state);
+        state.pc = program.origin;
+        Debugger::from_state(state, machine)
+    }
+
+    // debugs a cpu that has already been set up, which reset goes back to
+    pub fn from_state(state: State8080, machine: M) -> Debugger<M> {
+        Debugger {
+            initial: state.clone(),
+            state,
+            machine,
+            breakpoints: Breakpoints::new(),
+            tracer: Tracer::new(),
+            history: History::default(),
+            auto_history: false,
+            recorder: Recorder::default(),
+            frame_interrupts: false,
+            last: String::new(),
+        }
+    }
+
+    // reads commands until quit or the end of the input
+    pub fn run(&mut self, input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{}", self.state.registers())?;
+        self.show_next(out)?;
+        write!(out, "{}", PROMPT)?;
+        out.flush()?;
+        for line in input.lines() {
+            if !self.command(&line?, out)? {
+                break;
+            }
+            write!(out, "{}", PROMPT)?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    // carries out one command, and returns false when it is time to quit
+    pub fn command(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let line = if line.trim().is_empty() { self.last.clone() } else { line.trim().to_string() };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some(command) = words.first() else {
+            return Ok(true);
+        };
+        let args = &words[1..];
+
+        // commands that move on through the program are repeated by an empty line.
+        // mem and disasm set up their own repeat, which carries on from where they ended.
+        self.last = match *command {
+            "s" | "step" | "c" | "continue" => line.clone(),
+            _ => String::new(),
+        };
+
+        let result = match *command {
+            "s" | "step" => self.step(args, out),
+            "c" | "continue" => self.continue_(args, out),
+            "r" | "regs" => writeln!(out, "{}", self.state.registers()).map_err(|e| e.to_string()),
+            "m" | "mem" => self.mem(args, out),
+            "w" | "write" => self.write(args),
+            "d" | "disasm" => self.disasm(args, out),
+            "set" => self.set(args, out),
+            "b" | "break" | "watch" | "port" => self.add_breakpoint(command, args, out),
+            "trace" => self.trace(args, out),
+            "history" => self.history(args, out),
+            "rs" | "reverse-step" => self.reverse_step(args, out),
+            "rc" | "reverse-continue" => self.reverse_continue(args, out),
+            "bt" | "backtrace" => write!(out, "{}", self.history.backtrace(self.state.pc)).map_err(|e| e.to_string()),
+            "bl" | "breaks" => self.list_breakpoints(out),
+            "delete" | "enable" | "disable" | "ignore" | "cond" => self.change_breakpoint(command, args),
+            "save" => self.save(args, out),
+            "load" => self.load(args, out),
+            "reset" => {
+                self.state = self.initial.clone();
+                self.tracer.cycle = 0;
+                self.history.clear();
+                self.recorder.clear();
+                writeln!(out, "{}", self.state.registers()).map_err(|e| e.to_string())
+            },
+            "h" | "help" | "?" => writeln!(out, "{}", HELP).map_err(|e| e.to_string()),
+            "q" | "quit" => return Ok(false),
+            _ => Err(format!("unknown command '{}'; type help for the list", command)),
+        };
+        if let Err(error) = result {
+            writeln!(out, "{}", error)?;
+        }
+        Ok(true)
+    }
+
+    // prints the instruction at the pc, which is the one that runs next
+    fn show_next(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let instruction = disassembler::disassemble(&self.state.memory[..], self.state.pc);
+        writeln!(out, "{}", line(&instruction, true))
+    }
+
+    // runs one instruction, unless a breakpoint stops it, and says why it stopped if one did.
+    // first is set for the first instruction of a step or continue, so that it can carry on from a breakpoint at the pc.
+    pub fn execute(&mut self, first: bool) -> Option<Stop> {
+        if !first {
+            if let Some(stop) = self.breakpoints.before(&self.state) {
+                return Some(stop);
+            }
+        }
+        // memory accesses are only recorded while there are watchpoints to check them against
+        self.state.accesses = if self.breakpoints.watching() { Some(Vec::new()) } else { None };
+        self.history.before(&self.state, self.tracer.cycle);
+        self.recorder.before(&self.state, &self.machine, self.tracer.cycle, &self.history.stack);
+        let opcode = if self.state.halted { 0x76 } else { self.state.memory[self.state.pc as usize] };
+        let before = self.tracer.cycle;
+        if self.tracer.step(&mut self.state, &mut self.machine).is_err() {
+            // a trace that can't be written is switched off, rather than stopping the program
+            self.tracer.enabled = false;
+        }
+        self.history.after(&self.state);
+        self.recorder.after(opcode, &self.state);
+        let accesses = self.state.accesses.take().unwrap_or_default();
+        let stop = self.breakpoints.after(&self.state, &accesses);
+        self.frame_interrupt(before);
+        stop
+    }
+
+    // interrupts the cpu if the instruction that started at the cycle before took it past half a frame: RST 1 halfway
+    // through each frame and RST 2 at its end. they go by the cycle count, so running forward again after going back
+    // interrupts in the same places.
+    fn frame_interrupt(&mut self, before: u64) {
+        let half = invaders::CYCLES_PER_FRAME / 2;
+        let now = self.tracer.cycle / half;
+        if !self.frame_interrupts || before / half == now {
+            return;
+        }
+        let pc = self.state.pc;
+        if machine::interrupt(&mut self.state, if now % 2 == 1 { 1 } else { 2 }) {
+            self.history.interrupted(&self.state, pc);
+        }
+    }
+
+    // whether the cpu has halted with nothing to wake it
+    pub fn stuck(&self) -> bool {
+        self.state.halted && !(self.state.int_enable && self.frame_interrupts)
+    }
+
+    // the registers or memory have been changed by hand, which going back and running forward again couldn't repeat
+    pub fn changed(&mut self) {
+        self.recorder.take(&self.state, &self.machine, self.tracer.cycle, &self.history.stack);
+    }
+
+    // puts the program back to where it was after target instructions, by restoring the last snapshot before then
+    // and running forward to it
+    fn rewind(&mut self, target: u64) {
+        if let Some(snapshot) = self.recorder.snapshot(target).cloned() {
+            self.replay(snapshot, target, &mut |_, _, _| {});
+        }
+    }
+
+    // restores the snapshot and runs forward until target instructions have run. visit is shown the state at the
+    // snapshot and after each instruction, with the count of instructions run and the memory the instruction accessed.
+    fn replay(&mut self, snapshot: Snapshot, target: u64, visit: &mut dyn FnMut(u64, &State8080, &[Access])) {
+        self.state = snapshot.state;
+        // a machine can always load devices it saved itself
+        self.machine.load_devices(&snapshot.devices).ok();
+        self.tracer.cycle = snapshot.cycle;
+        self.history.entries.clear();
+        self.history.stack = snapshot.stack;
+        let mut count = snapshot.count;
+        visit(count, &self.state, &[]);
+        while count < target {
+            self.state.accesses = Some(Vec::new());
+            self.history.before(&self.state, self.tracer.cycle);
+            let before = self.tracer.cycle;
+            let cycles = machine::step(&mut self.state, &mut self.recorder.replay(count, &mut self.machine));
+            self.tracer.cycle += cycles as u64;
+            self.history.after(&self.state);
+            self.frame_interrupt(before);
+            count += 1;
+            let accesses = self.state.accesses.take().unwrap_or_default();
+            visit(count, &self.state, &accesses);
+        }
+        self.recorder.count = target;
+    }
+
+    // goes back n instructions, and returns false if there weren't that many to go back over
+    pub fn step_back(&mut self, n: u64) -> bool {
+        let now = self.recorder.count;
+        self.rewind(now.saturating_sub(n));
+        n <= now
+    }
+
+    // goes back to the last time, before now, that the pc was at one of the addresses or one of the ranges was
+    // written, and says what happened there. writes stop just before the instruction that made them, so that it is
+    // the next one to run. returns None, and stays where it is, if there was no such time.
+    pub fn continue_back(&mut self, addresses: &[u16], ranges: &[(u16, u16)]) -> Option<String> {
+        // search back one snapshot at a time, so that only as much is replayed as has to be
+        let now = self.recorder.count;
+        let starts = self.recorder.snapshots.iter().filter(|s| s.count < now).count();
+        let mut found: Option<(u64, String)> = None;
+        let mut end = now;
+        for i in (0..starts).rev() {
+            let snapshot = self.recorder.snapshots[i].clone();
+            let start = snapshot.count;
+            let mut previous_pc = 0;
+            self.replay(snapshot, end, &mut |count, state, accesses| {
+                if let Some(access) = accesses.iter().find(|a| a.write && ranges.iter().any(|(s, e)| (*s..=*e).contains(&a.addr))) {
+                    let event = format!("{:04x} wrote {:02x} to {:04x}", previous_pc, access.value, access.addr);
+                    found = Some((count - 1, event));
+                }
+                if count < now && addresses.contains(&state.pc) && !state.halted {
+                    found = Some((count, format!("at {:04x}", state.pc)));
+                }
+                previous_pc = state.pc;
+            });
+            if found.is_some() {
+                break;
+            }
+            end = start;
+        }
+
+        match found {
+            Some((count, event)) => {
+                self.rewind(count);
+                Some(format!("went back {} instructions: {}", now - count, event))
+            },
+            None => {
+                self.rewind(now);
+                None
+            },
+        }
+    }
+
+    // the exec breakpoints and the ranges of the write watchpoints, which are what going back stops at
+    pub fn reverse_targets(&self) -> (Vec<u16>, Vec<(u16, u16)>) {
+        let mut addresses: Vec<u16> = Vec::new();
+        let mut ranges: Vec<(u16, u16)> = Vec::new();
+        for breakpoint in self.breakpoints.list.iter().filter(|b| b.enabled) {
+            match breakpoint.kind {
+                Kind::Exec(addr) => addresses.push(addr),
+                Kind::Watch { start, end, write: true, .. } => ranges.push((start, end)),
+                _ => {},
+            }
+        }
+        (addresses, ranges)
+    }
+
+    fn reverse_step(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let n = args.first().map(|s| parse_count(s)).transpose()?.unwrap_or(1);
+        if !self.step_back(n) {
+            writeln!(out, "back at the start").map_err(|e| e.to_string())?;
+        }
+        writeln!(out, "{}", self.state.registers()).map_err(|e| e.to_string())?;
+        self.show_next(out).map_err(|e| e.to_string())
+    }
+
+    fn reverse_continue(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let (addresses, ranges) = match args {
+            [] => self.reverse_targets(),
+            ["write", range] => {
+                let range = match range.split_once('-') {
+                    Some((start, end)) => (parse_hex(start)?, parse_hex(end)?),
+                    None => (parse_hex(range)?, parse_hex(range)?),
+                };
+                (Vec::new(), vec![range])
+            },
+            _ => return Err("reverse-continue takes nothing, or write and an address or range".to_string()),
+        };
+        let reason = self.continue_back(&addresses, &ranges).unwrap_or("nothing to go back to".to_string());
+        writeln!(out, "{}", reason).map_err(|e| e.to_string())?;
+        writeln!(out, "{}", self.state.registers()).map_err(|e| e.to_string())?;
+        self.show_next(out).map_err(|e| e.to_string())
+    }
+
+    fn step(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let n = args.first().map(|s| parse_count(s)).transpose()?.unwrap_or(1);
+        for i in 0..n {
+            if n <= STEP_LISTING && i > 0 {
+                self.show_next(out).map_err(|e| e.to_string())?;
+            }
+            if let Some(stop) = self.execute(i == 0) {
+                writeln!(out, "{}", stop).map_err(|e| e.to_string())?;
+                self.show_history(out)?;
+                break;
+            }
+        }
+        self.tracer.flush().map_err(|e| e.to_string())?;
+        writeln!(out, "{}", self.state.registers()).map_err(|e| e.to_string())?;
+        self.show_next(out).map_err(|e| e.to_string())
+    }
+
+    fn continue_(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let limit = args.first().map(|s| parse_count(s)).transpose()?;
+        let mut count: u64 = 0;
+        // the history is only worth showing when the program stopped by itself, not when the count ran out
+        let (reason, stopped) = loop {
+            if self.stuck() {
+                break ("halted".to_string(), true);
+            }
+            if limit == Some(count) {
+                break (format!("stopped after {} instructions", count), false);
+            }
+            if let Some(stop) = self.execute(count == 0) {
+                break (stop.to_string(), true);
+            }
+            count += 1;
+        };
+        writeln!(out, "{}", reason).map_err(|e| e.to_string())?;
+        if stopped {
+            self.show_history(out)?;
+        }
+        self.tracer.flush().map_err(|e| e.to_string())?;
+        writeln!(out, "{}", self.state.registers()).map_err(|e| e.to_string())?;
+        self.show_next(out).map_err(|e| e.to_string())
+    }
+
+    fn mem(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let Some(addr) = args.first() else {
+            return Err("mem needs an address".to_string());
+        };
+        let addr = parse_hex(addr)?;
+        let len = args.get(1).map(|s| parse_count(s)).transpose()?.unwrap_or(64) as usize;
+        for row in (0..len).step_by(16) {
+            let start = addr.wrapping_add(row as u16);
+            let bytes: Vec<u8> = (0..16.min(len - row)).map(|i| self.state.get_mem(start.wrapping_add(i as u16))).collect();
+            let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            let text: String = bytes.iter().map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' }).collect();
+            writeln!(out, "{:04x}  {:<48} {}", start, hex.join(" "), text).map_err(|e| e.to_string())?;
+        }
+        self.last = format!("mem {:x} {}", addr.wrapping_add(len as u16), len);
+        Ok(())
+    }
+
+    fn write(&mut self, args: &[&str]) -> Result<(), String> {
+        let Some((addr, bytes)) = args.split_first() else {
+            return Err("write needs an address and bytes".to_string());
+        };
+        let addr = parse_hex(addr)?;
+        let mut values: Vec<u8> = Vec::new();
+        for byte in bytes.iter() {
+            let value = parse_hex(byte)?;
+            if value > 0xff {
+                return Err(format!("{} isn't a byte", byte));
+            }
+            values.push(value as u8);
+        }
+        for (i, value) in values.iter().enumerate() {
+            self.state.set_mem(addr.wrapping_add(i as u16), *value);
+        }
+        self.changed();
+        Ok(())
+    }
+
+    fn disasm(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let start = match args.first() {
+            Some(s) => parse_hex(s)?,
+            None => back_from(&self.state.memory[..], self.state.pc),
+        };
+        let n = args.get(1).map(|s| parse_count(s)).transpose()?.unwrap_or(10);
+        let mut addr = start;
+        for _ in 0..n {
+            let instruction = disassembler::disassemble(&self.state.memory[..], addr);
+            writeln!(out, "{}", line(&instruction, addr == self.state.pc)).map_err(|e| e.to_string())?;
+            addr = addr.wrapping_add(instruction.length());
+        }
+        self.last = format!("disasm {:x} {}", addr, n);
+        Ok(())
+    }
+
+    fn set(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let [register, value] = args else {
+            return Err("set needs a register and a value".to_string());
+        };
+        let value = parse_hex(value)?;
+        let byte = || if value > 0xff { Err(format!("{:x} doesn't fit in {}", value, register)) } else { Ok(value as u8) };
+        let state = &mut self.state;
+        match register.to_lowercase().as_str() {
+            "a" => state.a = byte()?,
+            "b" => state.b = byte()?,
+            "c" => state.c = byte()?,
+            "d" => state.d = byte()?,
+            "e" => state.e = byte()?,
+            "h" => state.h = byte()?,
+            "l" => state.l = byte()?,
+            "bc" => (state.b, state.c) = ((value >> 8) as u8, value as u8),
+            "de" => (state.d, state.e) = ((value >> 8) as u8, value as u8),
+            "hl" => (state.h, state.l) = ((value >> 8) as u8, value as u8),
+            "sp" => state.sp = value,
+            "pc" => {
+                state.pc = value;
+                state.halted = false;
+            },
+            "f" | "psw" => state.set_psw(byte()?),
+            "s" => state.cc.s = value != 0,
+            "z" => state.cc.z = value != 0,
+            "ac" => state.cc.ac = value != 0,
+            "p" => state.cc.p = value != 0,
+            "cy" => state.cc.cy = value != 0,
+            _ => return Err(format!("unknown register '{}'", register)),
+        }
+        self.changed();
+        writeln!(out, "{}", self.state.registers()).map_err(|e| e.to_string())
+    }
+
+    fn save(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let [path] = args else {
+            return Err("save needs the name of a file".to_string());
+        };
+        let bytes = savestate::save(&self.state, self.tracer.cycle, &self.machine);
+        std::fs::write(path, bytes).map_err(|e| format!("can't write {}: {}", path, e))?;
+        writeln!(out, "saved at cycle {}", self.tracer.cycle).map_err(|e| e.to_string())
+    }
+
+    fn load(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let [path] = args else {
+            return Err("load needs the name of a file".to_string());
+        };
+        let bytes = std::fs::read(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+        self.load_state(&bytes)?;
+        writeln!(out, "{}", self.state.registers()).map_err(|e| e.to_string())?;
+        self.show_next(out).map_err(|e| e.to_string())
+    }
+
+    // carries on from a save state. what came before it is forgotten, so there is no going back past it.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.tracer.cycle = savestate::load(bytes, &mut self.state, &mut self.machine)?;
+        self.history.clear();
+        self.recorder.clear();
+        Ok(())
+    }
+
+    // the backtrace and the last few instructions, when the program has stopped and auto is on
+    fn show_history(&mut self, out: &mut impl Write) -> Result<(), String> {
+        if !self.auto_history {
+            return Ok(());
+        }
+        write!(out, "{}", self.history.backtrace(self.state.pc)).map_err(|e| e.to_string())?;
+        for entry in self.history.last(10) {
+            writeln!(out, "{}", entry).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn history(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        match args {
+            ["auto", "on"] => self.auto_history = true,
+            ["auto", "off"] => self.auto_history = false,
+            ["size", n] => {
+                let capacity = parse_count(n)? as usize;
+                let history = &mut self.history;
+                while history.entries.len() > capacity {
+                    history.entries.pop_front();
+                }
+                history.capacity = capacity;
+            },
+            _ => {
+                let n = args.first().map(|s| parse_count(s)).transpose()?.unwrap_or(20);
+                for entry in self.history.last(n as usize) {
+                    writeln!(out, "{}", entry).map_err(|e| e.to_string())?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn trace(&mut self, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let tracer = &mut self.tracer;
+        match args {
+            [] => {},
+            ["on"] => tracer.enabled = true,
+            ["off"] => tracer.enabled = false,
+            ["range", "all"] => tracer.addresses = None,
+            ["range", range] => {
+                let (first, last) = range.split_once('-').ok_or("trace range needs <addr>-<end>")?;
+                tracer.addresses = Some((parse_hex(first)?, parse_hex(last)?));
+            },
+            ["cycles", "all"] => tracer.cycles = None,
+            ["cycles", window] => {
+                let (start, end) = window.split_once('-').ok_or("trace cycles needs <from>-<to>")?;
+                tracer.cycles = Some((parse_count(start)?, parse_count(end)?));
+            },
+            [path] => {
+                let file = std::fs::File::create(path).map_err(|e| format!("can't write {}: {}", path, e))?;
+                tracer.set_output(Box::new(io::BufWriter::new(file)));
+            },
+            _ => return Err("trace needs a file, on, off, range or cycles".to_string()),
+        }
+        let range = tracer.addresses.map_or("everywhere".to_string(), |(first, last)| format!("from {:04x} to {:04x}", first, last));
+        let window = tracer.cycles.map_or("any cycle".to_string(), |(start, end)| format!("cycles {} to {}", start, end));
+        let state = if tracer.enabled { "on" } else { "off" };
+        writeln!(out, "tracing is {}, {}, at {}; {} cycles so far", state, range, window, tracer.cycle).map_err(|e| e.to_string())
+    }
+
+    // break, watch and port, each followed by what to stop at and then an optional if and condition
+    fn add_breakpoint(&mut self, command: &str, args: &[&str], out: &mut impl Write) -> Result<(), String> {
+        let (args, condition) = match args.iter().position(|arg| *arg == "if") {
+            Some(i) => (&args[..i], Some(Condition::parse(&args[i + 1..].join(" "))?)),
+            None => (args, None),
+        };
+        let kind = match (command, args) {
+            ("b" | "break", [addr]) => Kind::Exec(parse_hex(addr)?),
+            ("watch", [range, mode @ ..]) if mode.len() <= 1 => {
+                let (start, end) = match range.split_once('-') {
+                    Some((start, end)) => (parse_hex(start)?, parse_hex(end)?),
+                    None => (parse_hex(range)?, parse_hex(range)?),
+                };
+                if end < start {
+                    return Err(format!("{:04x} is before {:04x}", end, start));
+                }
+                let (read, write) = match mode.first().copied().unwrap_or("write") {
+                    "read" => (true, false),
+                    "write" => (false, true),
+                    "access" => (true, true),
+                    other => return Err(format!("'{}' isn't read, write or access", other)),
+                };
+                Kind::Watch { start, end, read, write }
+            },
+            ("port", [direction @ ("in" | "out"), port]) => {
+                let port = parse_hex(port)?;
+                if port > 0xff {
+                    return Err(format!("{:x} isn't a port", port));
+                }
+                Kind::Port { input: *direction == "in", port: port as u8 }
+            },
+            ("b" | "break", _) => return Err("break needs an address".to_string()),
+            ("watch", _) => return Err("watch needs an address or range, and optionally read, write or access".to_string()),
+            _ => return Err("port needs in or out, and a port".to_string()),
+        };
+        let id = self.breakpoints.add(kind, condition);
+        let breakpoint = self.breakpoints.get(id)?;
+        writeln!(out, "{}: {}", id, breakpoint).map_err(|e| e.to_string())
+    }
+
+    fn list_breakpoints(&mut self, out: &mut impl Write) -> Result<(), String> {
+        if self.breakpoints.list.is_empty() {
+            return writeln!(out, "no breakpoints").map_err(|e| e.to_string());
+        }
+        for breakpoint in self.breakpoints.list.iter() {
+            let mut notes = format!("hit {}", breakpoint.hits);
+            if breakpoint.ignore > breakpoint.hits {
+                notes.push_str(&format!(", ignoring {} more", breakpoint.ignore - breakpoint.hits));
+            }
+            if !breakpoint.enabled {
+                notes.push_str(", disabled");
+            }
+            writeln!(out, "{}: {}  ({})", breakpoint.id, breakpoint, notes).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn change_breakpoint(&mut self, command: &str, args: &[&str]) -> Result<(), String> {
+        let Some((id, rest)) = args.split_first() else {
+            return Err(format!("{} needs the id of a breakpoint", command));
+        };
+        let id = id.parse::<usize>().map_err(|_| format!("'{}' isn't a breakpoint id", id))?;
+        if command == "delete" {
+            return self.breakpoints.delete(id);
+        }
+        let breakpoint = self.breakpoints.get(id)?;
+        match command {
+            "enable" => breakpoint.enabled = true,
+            "disable" => breakpoint.enabled = false,
+            "ignore" => {
+                let [n] = rest else {
+                    return Err("ignore needs a breakpoint and a count".to_string());
+                };
+                // the count is of the hits from now on
+                breakpoint.ignore = breakpoint.hits + parse_count(n)?;
+            },
+            _ => {
+                breakpoint.condition = if rest.is_empty() { None } else { Some(Condition::parse(&rest.join(" "))?) };
+            },
+        }
+        Ok(())
+    }
+}
+
+// an instruction as the debugger shows it: its address, bytes and text, with a > for the one at the pc
+fn line(instruction: &disassembler::Instruction, at_pc: bool) -> String {
+    let bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{} {:04x}  {:<8}  {}", if at_pc { ">" } else { " " }, instruction.addr, bytes.join(" "), instruction)
+}
+
+// finds an address a few instructions before the pc to start disassembling from.
+// code can't be read backwards for certain, so this tries each of the bytes before the pc and takes the furthest one
+// that decodes into instructions landing exactly on the pc.
+fn back_from(memory: &[u8], pc: u16) -> u16 {
+    for back in (1..=9u16.min(pc)).rev() {
+        let start = pc.wrapping_sub(back);
+        let mut addr = start;
+        let mut count = 0;
+        while addr != pc && pc.wrapping_sub(addr) <= back && count < 3 {
+            addr = addr.wrapping_add(disassembler::disassemble(memory, addr).length());
+            count += 1;
+        }
+        if addr == pc {
+            return start;
+        }
+    }
+    pc
+}
@@ -0,0 +1,134 @@
+// turns 8080 machine code back into Intel mnemonics, such as LXI H,2400h or JNZ 1a3fh
+use std::fmt;
+
+// the mnemonic and length of each opcode. D8 and D16 stand for immediate data, adr for an address.
+// undocumented opcodes are marked with a * and named after the instruction they behave like.
+const OPCODES: [(&str, u8); 256] = [
+    ("NOP", 1), ("LXI B,D16", 3), ("STAX B", 1), ("INX B", 1), ("INR B", 1), ("DCR B", 1), ("MVI B,D8", 2), ("RLC", 1), // 0x00..0x07
+    ("*NOP", 1), ("DAD B", 1), ("LDAX B", 1), ("DCX B", 1), ("INR C", 1), ("DCR C", 1), ("MVI C,D8", 2), ("RRC", 1), // 0x08..0x0f
+    ("*NOP", 1), ("LXI D,D16", 3), ("STAX D", 1), ("INX D", 1), ("INR D", 1), ("DCR D", 1), ("MVI D,D8", 2), ("RAL", 1), // 0x10..0x17
+    ("*NOP", 1), ("DAD D", 1), ("LDAX D", 1), ("DCX D", 1), ("INR E", 1), ("DCR E", 1), ("MVI E,D8", 2), ("RAR", 1), // 0x18..0x1f
+    ("*NOP", 1), ("LXI H,D16", 3), ("SHLD adr", 3), ("INX H", 1), ("INR H", 1), ("DCR H", 1), ("MVI H,D8", 2), ("DAA", 1), // 0x20..0x27
+    ("*NOP", 1), ("DAD H", 1), ("LHLD adr", 3), ("DCX H", 1), ("INR L", 1), ("DCR L", 1), ("MVI L,D8", 2), ("CMA", 1), // 0x28..0x2f
+    ("*NOP", 1), ("LXI SP,D16", 3), ("STA adr", 3), ("INX SP", 1), ("INR M", 1), ("DCR M", 1), ("MVI M,D8", 2), ("STC", 1), // 0x30..0x37
+    ("*NOP", 1), ("DAD SP", 1), ("LDA adr", 3), ("DCX SP", 1), ("INR A", 1), ("DCR A", 1), ("MVI A,D8", 2), ("CMC", 1), // 0x38..0x3f
+    ("MOV B,B", 1), ("MOV B,C", 1), ("MOV B,D", 1), ("MOV B,E", 1), ("MOV B,H", 1), ("MOV B,L", 1), ("MOV B,M", 1), ("MOV B,A", 1), // 0x40..0x47
+    ("MOV C,B", 1), ("MOV C,C", 1), ("MOV C,D", 1), ("MOV C,E", 1), ("MOV C,H", 1), ("MOV C,L", 1), ("MOV C,M", 1), ("MOV C,A", 1), // 0x48..0x4f
+    ("MOV D,B", 1), ("MOV D,C", 1), ("MOV D,D", 1), ("MOV D,E", 1), ("MOV D,H", 1), ("MOV D,L", 1), ("MOV D,M", 1), ("MOV D,A", 1), // 0x50..0x57
+    ("MOV E,B", 1), ("MOV E,C", 1), ("MOV E,D", 1), ("MOV E,E", 1), ("MOV E,H", 1), ("MOV E,L", 1), ("MOV E,M", 1), ("MOV E,A", 1), // 0x58..0x5f
+    ("MOV H,B", 1), ("MOV H,C", 1), ("MOV H,D", 1), ("MOV H,E", 1), ("MOV H,H", 1), ("MOV H,L", 1), ("MOV H,M", 1), ("MOV H,A", 1), // 0x60..0x67
+    ("MOV L,B", 1), ("MOV L,C", 1), ("MOV L,D", 1), ("MOV L,E", 1), ("MOV L,H", 1), ("MOV L,L", 1), ("MOV L,M", 1), ("MOV L,A", 1), // 0x68..0x6f
+    ("MOV M,B", 1), ("MOV M,C", 1), ("MOV M,D", 1), ("MOV M,E", 1), ("MOV M,H", 1), ("MOV M,L", 1), ("HLT", 1), ("MOV M,A", 1), // 0x70..0x77
+    ("MOV A,B", 1), ("MOV A,C", 1), ("MOV A,D", 1), ("MOV A,E", 1), ("MOV A,H", 1), ("MOV A,L", 1), ("MOV A,M", 1), ("MOV A,A", 1), // 0x78..0x7f
+    ("ADD B", 1), ("ADD C", 1), ("ADD D", 1), ("ADD E", 1), ("ADD H", 1), ("ADD L", 1), ("ADD M", 1), ("ADD A", 1), // 0x80..0x87
+    ("ADC B", 1), ("ADC C", 1), ("ADC D", 1), ("ADC E", 1), ("ADC H", 1), ("ADC L", 1), ("ADC M", 1), ("ADC A", 1), // 0x88..0x8f
+    ("SUB B", 1), ("SUB C", 1), ("SUB D", 1), ("SUB E", 1), ("SUB H", 1), ("SUB L", 1), ("SUB M", 1), ("SUB A", 1), // 0x90..0x97
+    ("SBB B", 1), ("SBB C", 1), ("SBB D", 1), ("SBB E", 1), ("SBB H", 1), ("SBB L", 1), ("SBB M", 1), ("SBB A", 1), // 0x98..0x9f
+    ("ANA B", 1), ("ANA C", 1), ("ANA D", 1), ("ANA E", 1), ("ANA H", 1), ("ANA L", 1), ("ANA M", 1), ("ANA A", 1), // 0xa0..0xa7
+    ("XRA B", 1), ("XRA C", 1), ("XRA D", 1), ("XRA E", 1), ("XRA H", 1), ("XRA L", 1), ("XRA M", 1), ("XRA A", 1), // 0xa8..0xaf
+    ("ORA B", 1), ("ORA C", 1), ("ORA D", 1), ("ORA E", 1), ("ORA H", 1), ("ORA L", 1), ("ORA M", 1), ("ORA A", 1), // 0xb0..0xb7
+    ("CMP B", 1), ("CMP C", 1), ("CMP D", 1), ("CMP E", 1), ("CMP H", 1), ("CMP L", 1), ("CMP M", 1), ("CMP A", 1), // 0xb8..0xbf
+    ("RNZ", 1), ("POP B", 1), ("JNZ adr", 3), ("JMP adr", 3), ("CNZ adr", 3), ("PUSH B", 1), ("ADI D8", 2), ("RST 0", 1), // 0xc0..0xc7
+    ("RZ", 1), ("RET", 1), ("JZ adr", 3), ("*JMP adr", 3), ("CZ adr", 3), ("CALL adr", 3), ("ACI D8", 2), ("RST 1", 1), // 0xc8..0xcf
+    ("RNC", 1), ("POP D", 1), ("JNC adr", 3), ("OUT D8", 2), ("CNC adr", 3), ("PUSH D", 1), ("SUI D8", 2), ("RST 2", 1), // 0xd0..0xd7
+    ("RC", 1), ("*RET", 1), ("JC adr", 3), ("IN D8", 2), ("CC adr", 3), ("*CALL adr", 3), ("SBI D8", 2), ("RST 3", 1), // 0xd8..0xdf
+    ("RPO", 1), ("POP H", 1), ("JPO adr", 3), ("XTHL", 1), ("CPO adr", 3), ("PUSH H", 1), ("ANI D8", 2), ("RST 4", 1), // 0xe0..0xe7
+    ("RPE", 1), ("PCHL", 1), ("JPE adr", 3), ("XCHG", 1), ("CPE adr", 3), ("*CALL adr", 3), ("XRI D8", 2), ("RST 5", 1), // 0xe8..0xef
+    ("RP", 1), ("POP PSW", 1), ("JP adr", 3), ("DI", 1), ("CP adr", 3), ("PUSH PSW", 1), ("ORI D8", 2), ("RST 6", 1), // 0xf0..0xf7
+    ("RM", 1), ("SPHL", 1), ("JM adr", 3), ("EI", 1), ("CM adr", 3), ("*CALL adr", 3), ("CPI D8", 2), ("RST 7", 1), // 0xf8..0xff
+];
+
+// one decoded instruction
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub addr: u16,
+    pub bytes: Vec<u8>, // the opcode, then any data or address
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    pub fn length(&self) -> u16 {
+        self.bytes.len() as u16
+    }
+
+    // the mnemonic with its operand placeholder, e.g. "JNZ adr"
+    pub fn template(&self) -> &'static str {
+        template(self.opcode())
+    }
+
+    // the immediate data or address, if the instruction has one
+    pub fn operand(&self) -> Option<u16> {
+        match self.bytes.len() {
+            2 => Some(self.bytes[1] as u16),
+            3 => Some(((self.bytes[2] as u16) << 8) | (self.bytes[1] as u16)),
+            _ => None,
+        }
+    }
+
+    pub fn documented(&self) -> bool {
+        !self.template().starts_with('*')
+    }
+
+    // the instruction's text, with its operand written by the function passed in instead of as a number
+    pub fn text_with(&self, operand: impl Fn(u16) -> String) -> String {
+        let template = self.template();
+        match self.operand() {
+            Some(value) => template.replace("D16", "#").replace("D8", "#").replace("adr", "#").replace('#', &operand(value)),
+            None => template.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = if self.bytes.len() == 2 {
+            self.text_with(|value| hex(value, 2))
+        } else {
+            self.text_with(|value| hex(value, 4))
+        };
+        f.write_str(&text)
+    }
+}
+
+// writes a number in Intel hex, e.g. 2400h. a leading 0 is added when the number starts with a letter,
+// so that it can't be mistaken for a name.
+pub fn hex(value: u16, digits: usize) -> String {
+    let s = format!("{:0width$x}h", value, width = digits);
+    if s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        format!("0{}", s)
+    } else {
+        s
+    }
+}
+
+// returns the mnemonic of an opcode, with its operand placeholder
+pub fn template(opcode: u8) -> &'static str {
+    OPCODES[opcode as usize].0
+}
+
+// returns the length in bytes of the instruction that starts with the opcode passed-in
+pub fn length(opcode: u8) -> u16 {
+    OPCODES[opcode as usize].1 as u16
+}
+
+// decodes the instruction at addr. memory is the 64K address space, so operands wrap around past 0xffff.
+pub fn disassemble(memory: &[u8], addr: u16) -> Instruction {
+    let get = |offset: u16| memory.get(addr.wrapping_add(offset) as usize).copied().unwrap_or(0);
+    let bytes = (0..length(get(0))).map(get).collect();
+    Instruction { addr, bytes }
+}
+
+// decodes the instructions from start up to end (inclusive), one after another
+pub fn disassemble_range(memory: &[u8], start: u16, end: u16) -> Vec<Instruction> {
+    let mut instructions: Vec<Instruction> = Vec::new();
+    let mut addr = start as u32;
+    while addr <= end as u32 {
+        let instruction = disassemble(memory, addr as u16);
+        addr += instruction.length() as u32;
+        instructions.push(instruction);
+    }
+    instructions
+}
@@ -0,0 +1,357 @@
+// a stub for the gdb remote serial protocol, so a debugger front end can attach to the emulator over tcp.
+// gdb has no 8080 target of its own, so the stub describes its registers in a target description (target.xml).
+// the registers are numbered like this, each 16 bits and sent little-endian, as gdb expects:
+//   0 psw (a in the high byte, the flags in the low byte), 1 bc, 2 de, 3 hl, 4 sp, 5 pc
+// breakpoints (Z0 and Z1) and watchpoints (Z2 write, Z3 read, Z4 access) are the debugger's own breakpoints,
+// so they count hits and show up in its breakpoint list like any other.
+// reverse-step and reverse-continue (bs and bc) use the debugger's recording of the run to go backwards.
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+
+use crate::breakpoints::Kind;
+use crate::breakpoints::Stop;
+use crate::debugger::Debugger;
+use crate::machine::Machine;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.8080.core">
+    <reg name="psw" bitsize="16" type="int" regnum="0"/>
+    <reg name="bc" bitsize="16" type="int"/>
+    <reg name="de" bitsize="16" type="int"/>
+    <reg name="hl" bitsize="16" type="data_ptr"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+const REGISTERS: usize = 6;
+
+// how many instructions a continue runs between looking for an interrupt (ctrl-c) from gdb
+const POLL_INTERVAL: u64 = 4096;
+
+pub struct Stub<'a, M: Machine> {
+    pub debugger: &'a mut Debugger<M>,
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok())).collect()
+}
+
+fn parse_number(hex: &str) -> Option<u32> {
+    u32::from_str_radix(hex, 16).ok()
+}
+
+// splits addr,len into numbers, where addr has to fit in the 8080's 64K
+fn address_and_length(text: &str) -> Option<(u16, u32)> {
+    let (addr, len) = text.split_once(',')?;
+    let addr = parse_number(addr)?;
+    if addr > 0xffff {
+        return None;
+    }
+    Some((addr as u16, parse_number(len)?))
+}
+
+// the stop reply for a breakpoint: watchpoints give the address that was accessed
+fn stop_reply(stop: &Stop) -> String {
+    match (&stop.kind, stop.access) {
+        (Kind::Watch { read, write, .. }, Some(access)) => {
+            let name = match (read, write) {
+                (true, true) => "awatch",
+                (true, false) => "rwatch",
+                _ => "watch",
+            };
+            format!("T05{}:{:04x};", name, access.addr)
+        },
+        _ => "S05".to_string(),
+    }
+}
+
+impl<M: Machine> Stub<'_, M> {
+    fn register(&self, n: usize) -> u16 {
+        let state = &self.debugger.state;
+        let pair = |high: u8, low: u8| ((high as u16) << 8) | low as u16;
+        match n {
+            0 => pair(state.a, state.get_psw()),
+            1 => pair(state.b, state.c),
+            2 => pair(state.d, state.e),
+            3 => pair(state.h, state.l),
+            4 => state.sp,
+            _ => state.pc,
+        }
+    }
+
+    fn set_register(&mut self, n: usize, value: u16) {
+        let state = &mut self.debugger.state;
+        let (high, low) = ((value >> 8) as u8, value as u8);
+        match n {
+            0 => {
+                state.a = high;
+                state.set_psw(low);
+            },
+            1 => (state.b, state.c) = (high, low),
+            2 => (state.d, state.e) = (high, low),
+            3 => (state.h, state.l) = (high, low),
+            4 => state.sp = value,
+            _ => {
+                state.pc = value;
+                state.halted = false;
+            },
+        }
+    }
+
+    // the watchpoint or breakpoint a Z or z packet is about
+    fn breakpoint_kind(text: &str) -> Option<Kind> {
+        let mut fields = text.split(',');
+        let kind = fields.next()?;
+        let addr = parse_number(fields.next()?)?;
+        let len = parse_number(fields.next()?)?.max(1);
+        if addr as u64 + len as u64 - 1 > 0xffff {
+            return None;
+        }
+        let (start, end) = (addr as u16, (addr + len - 1) as u16);
+        Some(match kind {
+            "0" | "1" => Kind::Exec(start),
+            "2" => Kind::Watch { start, end, read: false, write: true },
+            "3" => Kind::Watch { start, end, read: true, write: false },
+            "4" => Kind::Watch { start, end, read: true, write: true },
+            _ => return None,
+        })
+    }
+
+    // runs until a breakpoint, the cpu halting, or gdb interrupting, and returns the stop reply
+    fn resume(&mut self, single: bool, interrupted: &mut dyn FnMut() -> bool) -> String {
+        let mut count: u64 = 0;
+        loop {
+            if let Some(stop) = self.debugger.execute(count == 0) {
+                return stop_reply(&stop);
+            }
+            count += 1;
+            if single || self.debugger.stuck() {
+                return "S05".to_string();
+            }
+            if count.is_multiple_of(POLL_INTERVAL) && interrupted() {
+                return "S02".to_string();
+            }
+        }
+    }
+
+    // answers a packet. the only packet with no answer is k.
+    // interrupted is asked now and then during a continue whether gdb wants it to stop.
+    pub fn handle(&mut self, packet: &str, interrupted: &mut dyn FnMut() -> bool) -> Option<String> {
+        let error = "E01".to_string();
+        let (command, rest) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+        let reply = match command {
+            "?" => "S05".to_string(),
+            "g" => {
+                let bytes: Vec<u8> = (0..REGISTERS).flat_map(|n| self.register(n).to_le_bytes()).collect();
+                hex_bytes(&bytes)
+            },
+            "G" => match parse_bytes(rest) {
+                Some(bytes) if bytes.len() == REGISTERS * 2 => {
+                    for n in 0..REGISTERS {
+                        self.set_register(n, u16::from_le_bytes([bytes[n * 2], bytes[n * 2 + 1]]));
+                    }
+                    self.debugger.changed();
+                    "OK".to_string()
+                },
+                _ => error,
+            },
+            "p" => match parse_number(rest) {
+                Some(n) if (n as usize) < REGISTERS => hex_bytes(&self.register(n as usize).to_le_bytes()),
+                _ => error,
+            },
+            "P" => {
+                let value = rest.split_once('=').and_then(|(n, value)| Some((parse_number(n)?, parse_bytes(value)?)));
+                match value {
+                    Some((n, bytes)) if (n as usize) < REGISTERS && bytes.len() == 2 => {
+                        self.set_register(n as usize, u16::from_le_bytes([bytes[0], bytes[1]]));
+                        self.debugger.changed();
+                        "OK".to_string()
+                    },
+                    _ => error,
+                }
+            },
+            "m" => match address_and_length(rest) {
+                Some((addr, len)) => {
+                    let memory = &self.debugger.state.memory;
+                    let bytes: Vec<u8> = (0..len.min(0x10000)).map(|i| memory[addr.wrapping_add(i as u16) as usize]).collect();
+                    hex_bytes(&bytes)
+                },
+                None => error,
+            },
+            "M" => {
+                let write = rest.split_once(':').and_then(|(range, data)| Some((address_and_length(range)?, parse_bytes(data)?)));
+                match write {
+                    Some(((addr, len), bytes)) if bytes.len() == len as usize => {
+                        for (i, byte) in bytes.iter().enumerate() {
+                            self.debugger.state.memory[addr.wrapping_add(i as u16) as usize] = *byte;
+                        }
+                        self.debugger.changed();
+                        "OK".to_string()
+                    },
+                    _ => error,
+                }
+            },
+            // s and c can give the address to go on from
+            "s" | "c" => {
+                if !rest.is_empty() {
+                    match parse_number(rest) {
+                        Some(addr) if addr <= 0xffff => self.set_register(5, addr as u16),
+                        _ => return Some(error),
+                    }
+                }
+                self.resume(command == "s", interrupted)
+            },
+            // bs and bc step and continue backwards
+            "b" if rest == "s" => {
+                if self.debugger.step_back(1) { "S05".to_string() } else { "T05replaylog:begin;".to_string() }
+            },
+            "b" if rest == "c" => {
+                let (addresses, ranges) = self.debugger.reverse_targets();
+                match self.debugger.continue_back(&addresses, &ranges) {
+                    Some(_) => "S05".to_string(),
+                    None => {
+                        self.debugger.step_back(u64::MAX);
+                        "T05replaylog:begin;".to_string()
+                    },
+                }
+            },
+            "Z" => match Self::breakpoint_kind(rest) {
+                Some(kind) => {
+                    if self.debugger.breakpoints.find(&kind).is_none() {
+                        self.debugger.breakpoints.add(kind, None);
+                    }
+                    "OK".to_string()
+                },
+                // an empty reply tells gdb this kind of breakpoint isn't supported
+                None => String::new(),
+            },
+            "z" => match Self::breakpoint_kind(rest) {
+                Some(kind) => {
+                    if let Some(id) = self.debugger.breakpoints.find(&kind) {
+                        self.debugger.breakpoints.delete(id).ok();
+                    }
+                    "OK".to_string()
+                },
+                None => String::new(),
+            },
+            // there is only ever one thread
+            "H" => "OK".to_string(),
+            "T" => "OK".to_string(),
+            "q" if rest.starts_with("Supported") => "PacketSize=1000;qXfer:features:read+;ReverseStep+;ReverseContinue+".to_string(),
+            "q" if rest == "Attached" => "1".to_string(),
+            "q" if rest == "C" => "QC1".to_string(),
+            "q" if rest == "fThreadInfo" => "m1".to_string(),
+            "q" if rest == "sThreadInfo" => "l".to_string(),
+            "q" if rest.starts_with("Xfer:features:read:target.xml:") => {
+                let range = &rest["Xfer:features:read:target.xml:".len()..];
+                match address_and_length(range) {
+                    Some((offset, len)) => {
+                        let start = (offset as usize).min(TARGET_XML.len());
+                        let end = (start + len as usize).min(TARGET_XML.len());
+                        let more = if end < TARGET_XML.len() { "m" } else { "l" };
+                        format!("{}{}", more, &TARGET_XML[start..end])
+                    },
+                    None => error,
+                }
+            },
+            "D" => "OK".to_string(),
+            // gdb doesn't wait for a reply to kill
+            "k" => return None,
+            // anything else isn't supported
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+}
+
+// wraps a reply in $...#checksum
+fn frame(data: &str) -> String {
+    let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+    format!("${}#{:02x}", data, sum)
+}
+
+// reads the next packet from gdb, acknowledging it. returns None when the connection closes.
+fn read_packet(stream: &mut TcpStream) -> io::Result<Option<String>> {
+    let mut byte = [0u8];
+    loop {
+        // everything before the $ is acks, or a ctrl-c that came too late to matter
+        loop {
+            if stream.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] == b'$' {
+                break;
+            }
+        }
+        let mut data: Vec<u8> = Vec::new();
+        loop {
+            if stream.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] == b'#' {
+                break;
+            }
+            data.push(byte[0]);
+        }
+        let mut checksum = [0u8; 2];
+        stream.read_exact(&mut checksum)?;
+        let sum = data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        let expected = std::str::from_utf8(&checksum).ok().and_then(|c| u8::from_str_radix(c, 16).ok());
+        if expected == Some(sum) {
+            stream.write_all(b"+")?;
+            return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+        }
+        // ask for it again
+        stream.write_all(b"-")?;
+    }
+}
+
+// whether gdb has sent a ctrl-c, without waiting for one
+fn interrupt_waiting(stream: &mut TcpStream) -> bool {
+    let mut byte = [0u8];
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let interrupted = matches!(stream.peek(&mut byte), Ok(1) if byte[0] == 0x03);
+    if interrupted {
+        stream.read_exact(&mut byte).ok();
+    }
+    stream.set_nonblocking(false).ok();
+    interrupted
+}
+
+// serves one gdb connection until it detaches, kills the program, or goes away
+pub fn serve<M: Machine>(debugger: &mut Debugger<M>, mut stream: TcpStream) -> io::Result<()> {
+    let mut interrupt_stream = stream.try_clone()?;
+    let mut stub = Stub { debugger };
+    while let Some(packet) = read_packet(&mut stream)? {
+        if let Some(reply) = stub.handle(&packet, &mut || interrupt_waiting(&mut interrupt_stream)) {
+            stream.write_all(frame(&reply).as_bytes())?;
+        }
+        if packet.starts_with('D') || packet.starts_with('k') {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// waits on the address (like 127.0.0.1:1234) for gdb to connect, then serves it
+pub fn listen<M: Machine>(debugger: &mut Debugger<M>, address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let (stream, _) = listener.accept()?;
+    serve(debugger, stream)
+}
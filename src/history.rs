@@ -0,0 +1,163 @@
+// the recent past of a running program: the last instructions it ran, kept in a ring buffer, and the call stack
+// rebuilt from the CALLs, RSTs and interrupts it went through and the RETs that came back from them.
+// the call stack follows the stack pointer rather than matching each RET to its CALL, so a routine that drops its
+// return address (with POP or LXI SP) drops its frame too.
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::cpu::State8080;
+use crate::disassembler;
+use crate::disassembler::Instruction;
+
+// the number of instructions kept unless asked for otherwise
+pub const DEFAULT_CAPACITY: usize = 10000;
+
+// an instruction about to run, and the registers it ran with
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub pc: u16,
+    pub bytes: Vec<u8>,
+    pub af: u16,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+    pub sp: u16,
+    pub cycle: u64,
+}
+
+impl Entry {
+    pub fn new(state: &State8080, cycle: u64) -> Entry {
+        let pair = |high: u8, low: u8| ((high as u16) << 8) | low as u16;
+        let instruction = disassembler::disassemble(&state.memory[..], state.pc);
+        Entry {
+            pc: state.pc,
+            bytes: instruction.bytes,
+            af: pair(state.a, state.get_psw()),
+            bc: pair(state.b, state.c),
+            de: pair(state.d, state.e),
+            hl: pair(state.h, state.l),
+            sp: state.sp,
+            cycle,
+        }
+    }
+
+    pub fn opcode(&self) -> u8 {
+        self.bytes[0]
+    }
+}
+
+// the same line as the execution trace writes
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let instruction = Instruction { addr: self.pc, bytes: self.bytes.clone() };
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        write!(
+            f,
+            "PC={:04x} AF={:04x} BC={:04x} DE={:04x} HL={:04x} SP={:04x} CYC={}  {:<8}  {}",
+            self.pc, self.af, self.bc, self.de, self.hl, self.sp, self.cycle, bytes.join(" "), instruction,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Via {
+    Call,
+    Restart,
+    Interrupt,
+}
+
+// a routine that was called and hasn't returned yet
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub via: Via,
+    pub from: u16, // the address of the CALL or RST, or the instruction that was interrupted
+    pub target: u16, // the routine it went to
+    pub ret: u16, // the return address it pushed
+    pub sp: u16, // the stack pointer just after the push, which points at the return address
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let via = match self.via {
+            Via::Call => "CALL",
+            Via::Restart => "RST",
+            Via::Interrupt => "interrupt",
+        };
+        write!(f, "{:04x} from {:04x} by {}, returns to {:04x} (sp {:04x})", self.target, self.from, via, self.ret, self.sp)
+    }
+}
+
+pub struct History {
+    pub entries: VecDeque<Entry>,
+    pub capacity: usize,
+    pub stack: Vec<Frame>, // outermost first
+}
+
+impl Default for History {
+    fn default() -> History {
+        History::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History { entries: VecDeque::with_capacity(capacity.min(DEFAULT_CAPACITY)), capacity, stack: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.stack.clear();
+    }
+
+    // records the instruction at the pc, before it runs
+    pub fn before(&mut self, state: &State8080, cycle: u64) {
+        if state.halted || self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry::new(state, cycle));
+    }
+
+    // updates the call stack once the recorded instruction has run
+    pub fn after(&mut self, state: &State8080) {
+        // anything above the stack pointer has been returned from or thrown away
+        self.stack.retain(|frame| frame.sp >= state.sp);
+
+        let Some(entry) = self.entries.back() else {
+            return;
+        };
+        let opcode = entry.opcode();
+        let ret = entry.pc.wrapping_add(entry.bytes.len() as u16);
+        // a call that was taken has pushed the return address
+        let pushed = state.sp == entry.sp.wrapping_sub(2);
+        let via = match opcode {
+            0xcd | 0xdd | 0xed | 0xfd => Via::Call,
+            _ if opcode & 0xc7 == 0xc4 && pushed => Via::Call, // CNZ, CZ and the other conditional calls
+            _ if opcode & 0xc7 == 0xc7 => Via::Restart,
+            _ => return,
+        };
+        self.stack.push(Frame { via, from: entry.pc, target: state.pc, ret, sp: state.sp });
+    }
+
+    // records an interrupt, once the cpu has taken it and pushed the pc
+    pub fn interrupted(&mut self, state: &State8080, from: u16) {
+        self.stack.retain(|frame| frame.sp >= state.sp);
+        self.stack.push(Frame { via: Via::Interrupt, from, target: state.pc, ret: from, sp: state.sp });
+    }
+
+    // the last n instructions, oldest first
+    pub fn last(&self, n: usize) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().skip(self.entries.len().saturating_sub(n))
+    }
+
+    // the call stack, innermost first, starting from where the pc is now
+    pub fn backtrace(&self, pc: u16) -> String {
+        let mut out = format!("#0  {:04x}\n", pc);
+        for (i, frame) in self.stack.iter().rev().enumerate() {
+            out.push_str(&format!("#{}  {}\n", i + 1, frame));
+        }
+        out
+    }
+}
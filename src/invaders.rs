@@ -0,0 +1,164 @@
+// the i/o hardware of the space invaders arcade board, without the screen or the sound.
+// the board has two input ports of switches and buttons, a shift register that the game uses to move its sprites a
+// few pixels at a time, two ports of sound triggers and a watchdog:
+//   IN 1  buttons: coin, the start buttons, and player 1's fire, left and right
+//   IN 2  the dip switches, the tilt switch, and player 2's fire, left and right
+//   IN 3  the shift register, shifted left by the offset
+//   OUT 2 the shift offset
+//   OUT 3 sounds
+//   OUT 4 shifts a byte into the top of the shift register
+//   OUT 5 more sounds
+//   OUT 6 the watchdog, which the game resets every frame
+// the video hardware interrupts the cpu twice a frame, with RST 1 when the beam is halfway down the screen and RST 2
+// when it reaches the bottom.
+use crate::cpu::State8080;
+use crate::machine;
+use crate::machine::Machine;
+use crate::scheduler::Scheduler;
+use crate::throttle::CLOCK_HZ;
+
+// the cpu runs at 1.9968MHz, and the screen at 60 frames a second
+pub const CYCLES_PER_FRAME: u64 = CLOCK_HZ / 60;
+
+// the bits of port 1
+pub const COIN: u8 = 0x01;
+pub const P2_START: u8 = 0x02;
+pub const P1_START: u8 = 0x04;
+pub const P1_FIRE: u8 = 0x10;
+pub const P1_LEFT: u8 = 0x20;
+pub const P1_RIGHT: u8 = 0x40;
+
+// the bits of port 2
+pub const TILT: u8 = 0x04;
+pub const P2_FIRE: u8 = 0x10;
+pub const P2_LEFT: u8 = 0x20;
+pub const P2_RIGHT: u8 = 0x40;
+
+// bit 3 of port 1 always reads as 1
+const PORT1_ALWAYS: u8 = 0x08;
+
+// the settings on the dip switches, which the game reads from port 2
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dips {
+    pub lives: u8, // 3 to 6
+    pub bonus_at_1000: bool, // an extra life at 1000 points, rather than 1500
+    pub coin_info: bool, // show the coin information on the demo screen
+}
+
+impl Default for Dips {
+    fn default() -> Dips {
+        Dips { lives: 3, bonus_at_1000: false, coin_info: true }
+    }
+}
+
+impl Dips {
+    // the bits the switches set in port 2
+    pub fn bits(&self) -> u8 {
+        let mut bits = self.lives.clamp(3, 6) - 3;
+        if self.bonus_at_1000 {
+            bits |= 0x08;
+        }
+        // the switch is on when the bit is clear
+        if !self.coin_info {
+            bits |= 0x80;
+        }
+        bits
+    }
+
+    pub fn from_bits(bits: u8) -> Dips {
+        Dips { lives: (bits & 0x03) + 3, bonus_at_1000: bits & 0x08 != 0, coin_info: bits & 0x80 == 0 }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Invaders {
+    pub port1: u8, // the buttons held down on port 1, as the bits above
+    pub port2: u8, // the buttons held down on port 2, as the bits above
+    pub dips: Dips,
+    pub shift: u16, // the last two bytes written to port 4, the latest in the high byte
+    pub shift_offset: u8,
+    pub sound1: u8, // the last value written to port 3
+    pub sound2: u8, // the last value written to port 5
+    pub watchdog: u64, // the number of times the watchdog has been reset
+}
+
+impl Invaders {
+    pub fn new(dips: Dips) -> Invaders {
+        Invaders { dips, ..Invaders::default() }
+    }
+
+    // runs a frame, with the interrupts halfway through and at the end, and returns the number of cycles it took.
+    // the last instruction of each half can run a few cycles over, so a frame is never quite the same length.
+    pub fn frame(&mut self, state: &mut State8080) -> u64 {
+        let mut scheduler = Scheduler::new();
+        schedule_interrupts(&mut scheduler, 0);
+        scheduler.run_until_cycle(state, self, CYCLES_PER_FRAME)
+    }
+}
+
+// schedules the video's interrupts for every frame from the cycle on: RST 1 halfway through each, and RST 2 at its end
+pub fn schedule_interrupts<M: Machine + ?Sized>(scheduler: &mut Scheduler<M>, start: u64) {
+    scheduler.every(start + CYCLES_PER_FRAME / 2, CYCLES_PER_FRAME, |state, _| {
+        machine::interrupt(state, 1);
+    });
+    scheduler.every(start + CYCLES_PER_FRAME, CYCLES_PER_FRAME, |state, _| {
+        machine::interrupt(state, 2);
+    });
+}
+
+// the length of the saved device state
+const SAVED_LENGTH: usize = 16;
+
+impl Machine for Invaders {
+    fn input(&mut self, port: u8) -> u8 {
+        match port {
+            // port 0 isn't used by the game, but reads as these bits on the board
+            0 => 0x0e,
+            1 => self.port1 | PORT1_ALWAYS,
+            2 => (self.port2 & (TILT | P2_FIRE | P2_LEFT | P2_RIGHT)) | self.dips.bits(),
+            3 => (self.shift >> (8 - self.shift_offset)) as u8,
+            _ => 0,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            2 => self.shift_offset = value & 0x07,
+            3 => self.sound1 = value,
+            4 => self.shift = ((value as u16) << 8) | (self.shift >> 8),
+            5 => self.sound2 = value,
+            6 => self.watchdog += 1,
+            _ => {},
+        }
+    }
+
+    fn save_devices(&self) -> Vec<u8> {
+        let mut data = vec![
+            self.port1,
+            self.port2,
+            self.dips.bits(),
+            self.shift as u8,
+            (self.shift >> 8) as u8,
+            self.shift_offset,
+            self.sound1,
+            self.sound2,
+        ];
+        data.extend_from_slice(&self.watchdog.to_le_bytes());
+        data
+    }
+
+    fn load_devices(&mut self, data: &[u8]) -> Result<(), String> {
+        if data.len() != SAVED_LENGTH {
+            return Err(format!("the space invaders devices are {} bytes, not {}", SAVED_LENGTH, data.len()));
+        }
+        self.port1 = data[0];
+        self.port2 = data[1];
+        self.dips = Dips::from_bits(data[2]);
+        self.shift = u16::from_le_bytes([data[3], data[4]]);
+        self.shift_offset = data[5] & 0x07;
+        self.sound1 = data[6];
+        self.sound2 = data[7];
+        self.watchdog = u64::from_le_bytes(data[8..16].try_into().unwrap());
+        Ok(())
+    }
+}
@@ -0,0 +1,667 @@
+// a basic-block JIT for x86-64 hosts, behind the jit feature.
+// a block is a run of instructions that only move data around and do arithmetic, from where the pc is up to the first
+// instruction that does anything else. each block is translated to native code the first time the pc gets to it, and
+// the native code runs it from then on. everything else (calls, returns, the stack, IN and OUT, EI, DI, HLT, DAA)
+// runs one instruction at a time through machine::step, so the machine still sees every port access, and interrupts
+// still only arrive between instructions.
+// the flags come from the host's own: x86 keeps S, Z, AC, P and CY in the same bits of LAHF's byte as the 8080 keeps
+// them in its flags byte. where the two disagree (AC after a subtraction or a logical instruction) the code fixes
+// them up.
+// memory written by a block, by an instruction run through step or by an interrupt is checked against the bytes that
+// have been translated, and a write to one throws that code away, so self-modifying programs still work. it is kept
+// byte by byte rather than by page, since 8080 programs often keep their variables right next to their code.
+// anything else that writes memory (a loader, a debugger, a save state) must call invalidate or flush afterwards.
+// blocks don't record their accesses in state.accesses, so a debugger watching memory should run the interpreter.
+use std::mem;
+
+use dynasmrt::dynasm;
+use dynasmrt::x64::Assembler;
+use dynasmrt::AssemblyOffset;
+use dynasmrt::DynamicLabel;
+use dynasmrt::DynasmApi;
+use dynasmrt::DynasmLabelApi;
+use dynasmrt::Executor;
+
+use crate::cpu;
+use crate::cpu::State8080;
+use crate::cpu::MEMORY_SIZE;
+use crate::disassembler;
+use crate::machine;
+use crate::machine::Machine;
+
+// the most instructions in a block
+const MAX_INSTRUCTIONS: u64 = 64;
+
+// when the code buffer grows past this, everything is thrown away and translated again as it runs
+const MAX_CODE: usize = 16 << 20;
+
+// what the native code works on. the registers are kept in the order of the register field of an opcode
+// (B C D E H L M A), with the flags where M would be, so an opcode's field is also its register's offset.
+#[repr(C)]
+struct Context {
+    registers: [u8; 8],
+    sp: u16,
+    pc: u16,
+    stopped: u16, // set when the block stopped after a write to translated code
+    written: u16, // the address that was written
+    memory: *mut u8,
+    code: *const u8, // nonzero for each byte of translated code
+    cycles: u64,
+    instructions: u64,
+}
+
+const F: i32 = 6;
+const A: i32 = 7;
+const SP: i32 = 8;
+const PC: i32 = 10;
+const STOPPED: i32 = 12;
+const WRITTEN: i32 = 14;
+const MEMORY: i32 = 16;
+const CODE: i32 = 24;
+const CYCLES: i32 = 32;
+const INSTRUCTIONS: i32 = 40;
+
+const _: () = {
+    assert!(mem::offset_of!(Context, sp) == SP as usize);
+    assert!(mem::offset_of!(Context, pc) == PC as usize);
+    assert!(mem::offset_of!(Context, stopped) == STOPPED as usize);
+    assert!(mem::offset_of!(Context, written) == WRITTEN as usize);
+    assert!(mem::offset_of!(Context, memory) == MEMORY as usize);
+    assert!(mem::offset_of!(Context, code) == CODE as usize);
+    assert!(mem::offset_of!(Context, cycles) == CYCLES as usize);
+    assert!(mem::offset_of!(Context, instructions) == INSTRUCTIONS as usize);
+};
+
+// a block starting at some address. a block whose first instruction can't be translated has no code, and only
+// remembers that, so it isn't tried again every time.
+#[derive(Clone, Copy)]
+struct Block {
+    entry: Option<AssemblyOffset>,
+    end: usize, // one past its last byte
+    cycles: u64,
+}
+
+// the way out of a block after a store, taken when the store hit translated code
+struct Stop {
+    label: DynamicLabel,
+    pc: u16, // the instruction after the store
+    cycles: u64, // the cycles and instructions up to and including the store
+    instructions: u64,
+}
+
+pub struct Jit {
+    ops: Assembler,
+    reader: Executor,
+    blocks: Vec<Option<Block>>, // by the address they start at
+    code: Box<[u8; MEMORY_SIZE]>, // nonzero for each byte in a block
+    cover: Vec<u16>, // the number of blocks each byte is in
+    page_blocks: Vec<Vec<u16>>, // the blocks starting in each page, by their start. some may be gone already.
+    writes: Vec<cpu::Access>, // the accesses of an instruction that step runs
+    pub translated: u64, // the instructions run as native code
+    pub interpreted: u64, // the instructions run through step
+}
+
+impl Jit {
+    pub fn new() -> std::io::Result<Jit> {
+        let ops = Assembler::new()?;
+        let reader = ops.reader();
+        Ok(Jit {
+            ops,
+            reader,
+            blocks: vec![None; MEMORY_SIZE],
+            code: cpu::new_memory(),
+            cover: vec![0; MEMORY_SIZE],
+            page_blocks: vec![Vec::new(); 256],
+            writes: Vec::new(),
+            translated: 0,
+            interpreted: 0,
+        })
+    }
+
+    // throws away all the translated code
+    pub fn flush(&mut self) {
+        self.blocks.iter_mut().for_each(|block| *block = None);
+        self.code.fill(0);
+        self.cover.fill(0);
+        self.page_blocks.iter_mut().for_each(Vec::clear);
+        if self.ops.offset().0 > MAX_CODE {
+            if let Ok(ops) = Assembler::new() {
+                self.reader = ops.reader();
+                self.ops = ops;
+            }
+        }
+    }
+
+    // throws away the translated code with the address in it, for when something else has written there
+    pub fn invalidate(&mut self, addr: u16) {
+        let addr = addr as usize;
+        if self.code[addr] == 0 {
+            return;
+        }
+        // a block is shorter than a page, so any block with the address in it starts in its page or the one before
+        for page in (addr >> 8).saturating_sub(1)..=addr >> 8 {
+            let mut starts = mem::take(&mut self.page_blocks[page]);
+            starts.retain(|&start| {
+                let start = start as usize;
+                match self.blocks[start] {
+                    Some(block) if start <= addr && addr < block.end => {
+                        self.remove(start, block.end);
+                        false
+                    },
+                    block => block.is_some(),
+                }
+            });
+            self.page_blocks[page] = starts;
+        }
+    }
+
+    fn remove(&mut self, start: usize, end: usize) {
+        self.blocks[start] = None;
+        for addr in start..end {
+            self.cover[addr] -= 1;
+            if self.cover[addr] == 0 {
+                self.code[addr] = 0;
+            }
+        }
+    }
+
+    // runs instructions until at least the number of cycles has passed, and returns the cycles it took.
+    // it stops at the same instruction the interpreter would: a block only runs if it fits in the cycles left.
+    pub fn run<M: Machine + ?Sized>(&mut self, state: &mut State8080, machine: &mut M, cycles: u64) -> u64 {
+        let mut run = 0;
+        while run < cycles {
+            if !state.halted {
+                let block = self.block(state);
+                if block.entry.is_some() && block.cycles <= cycles - run {
+                    run += self.execute(state, cycles - run);
+                    continue;
+                }
+            }
+            run += self.step(state, machine);
+        }
+        run
+    }
+
+    // interrupts the cpu with RST n, like machine::interrupt
+    pub fn interrupt(&mut self, state: &mut State8080, n: u8) -> bool {
+        let taken = machine::interrupt(state, n);
+        if taken {
+            self.invalidate(state.sp);
+            self.invalidate(state.sp.wrapping_add(1));
+        }
+        taken
+    }
+
+    // the block starting at the pc, translating it if it hasn't been
+    fn block(&mut self, state: &State8080) -> Block {
+        let start = state.pc as usize;
+        if let Some(block) = self.blocks[start] {
+            return block;
+        }
+        if self.ops.offset().0 > MAX_CODE {
+            self.flush();
+        }
+        let block = translate(&mut self.ops, &state.memory, start);
+        self.ops.commit().expect("the translated code refers to nothing outside itself");
+        for addr in start..block.end {
+            self.cover[addr] += 1;
+            self.code[addr] = 1;
+        }
+        self.page_blocks[start >> 8].push(start as u16);
+        self.blocks[start] = Some(block);
+        block
+    }
+
+    // runs the translated block at the pc, and the ones after it for as long as they have been translated and fit in
+    // the cycles, and returns the cycles they took
+    fn execute(&mut self, state: &mut State8080, cycles: u64) -> u64 {
+        let mut context = Context {
+            registers: [state.b, state.c, state.d, state.e, state.h, state.l, state.get_psw(), state.a],
+            sp: state.sp,
+            pc: state.pc,
+            stopped: 0,
+            written: 0,
+            memory: state.memory.as_mut_ptr(),
+            code: self.code.as_ptr(),
+            cycles: 0,
+            instructions: 0,
+        };
+        {
+            let code = self.reader.lock();
+            while let Some(Block { entry: Some(entry), cycles: block_cycles, .. }) = self.blocks[context.pc as usize] {
+                if context.stopped != 0 || context.cycles + block_cycles > cycles {
+                    break;
+                }
+                // SAFETY: the code at entry is a whole block, translated to take a context in rdi and return once the
+                // block is done. it only reads and writes the context, the 64K of memory and the code map it points to.
+                let block: extern "sysv64" fn(*mut Context) = unsafe { mem::transmute(code.ptr(entry)) };
+                block(&mut context);
+            }
+        }
+        let [b, c, d, e, h, l, psw, a] = context.registers;
+        (state.a, state.b, state.c, state.d, state.e, state.h, state.l) = (a, b, c, d, e, h, l);
+        state.set_psw(psw);
+        state.sp = context.sp;
+        state.pc = context.pc;
+        if context.stopped != 0 {
+            self.invalidate(context.written);
+        }
+        self.translated += context.instructions;
+        context.cycles
+    }
+
+    // runs one instruction through the interpreter, watching what it writes
+    fn step<M: Machine + ?Sized>(&mut self, state: &mut State8080, machine: &mut M) -> u64 {
+        // if something is already recording accesses, its record is left as it was, with this instruction's added
+        let recording = state.accesses.is_some();
+        if !recording {
+            state.accesses = Some(mem::take(&mut self.writes));
+        }
+        let from = state.accesses.as_ref().map_or(0, Vec::len);
+        let cycles = machine::step(state, machine);
+        self.interpreted += 1;
+        if let Some(accesses) = &state.accesses {
+            for access in &accesses[from..] {
+                if access.write && self.code[access.addr as usize] != 0 {
+                    self.invalidate(access.addr);
+                }
+            }
+        }
+        if !recording {
+            self.writes = state.accesses.take().unwrap_or_default();
+            self.writes.clear();
+        }
+        cycles as u64
+    }
+}
+
+// the pair register field of an opcode (B, D, H or SP), as the offset of its high byte
+fn pair(opcode: u8) -> i32 {
+    ((opcode >> 4) & 3) as i32 * 2
+}
+
+// ecx = hl
+fn hl(ops: &mut Assembler) {
+    dynasm!(ops
+        ; .arch x64
+        ; movzx ecx, WORD [rdi + 4]
+        ; rol cx, 8
+    );
+}
+
+// ecx = the pair (B, D or H), which is kept high byte first
+fn load_pair(ops: &mut Assembler, offset: i32) {
+    dynasm!(ops
+        ; .arch x64
+        ; movzx ecx, WORD [rdi + offset]
+        ; rol cx, 8
+    );
+}
+
+// al = the register (or the byte at hl for M)
+fn load(ops: &mut Assembler, r: u8) {
+    if r == 6 {
+        hl(ops);
+        dynasm!(ops
+            ; .arch x64
+            ; mov rdx, QWORD [rdi + MEMORY]
+            ; mov al, BYTE [rdx + rcx]
+        );
+    } else {
+        dynasm!(ops
+            ; .arch x64
+            ; mov al, BYTE [rdi + r as i32]
+        );
+    }
+}
+
+// the byte at ecx = al, leaving the block if it was translated code
+fn store(ops: &mut Assembler, stops: &mut Vec<Stop>, pc: usize, cycles: u64, instructions: u64) {
+    let label = ops.new_dynamic_label();
+    dynasm!(ops
+        ; .arch x64
+        ; mov rdx, QWORD [rdi + MEMORY]
+        ; mov BYTE [rdx + rcx], al
+        ; mov rdx, QWORD [rdi + CODE]
+        ; cmp BYTE [rdx + rcx], 0
+        ; jne =>label
+    );
+    stops.push(Stop { label, pc: pc as u16, cycles, instructions });
+}
+
+// the register (or the byte at hl for M) = al
+fn store_register(ops: &mut Assembler, stops: &mut Vec<Stop>, r: u8, pc: usize, cycles: u64, instructions: u64) {
+    if r == 6 {
+        hl(ops);
+        store(ops, stops, pc, cycles, instructions);
+    } else {
+        dynasm!(ops
+            ; .arch x64
+            ; mov BYTE [rdi + r as i32], al
+        );
+    }
+}
+
+// cy = dl (0 or 1), leaving the other flags alone
+fn set_carry(ops: &mut Assembler) {
+    dynasm!(ops
+        ; .arch x64
+        ; and BYTE [rdi + F], -2
+        ; or BYTE [rdi + F], dl
+    );
+}
+
+// the flags = ah, the flags LAHF gave, with the bits that are fixed on the 8080 fixed
+fn set_flags(ops: &mut Assembler) {
+    dynasm!(ops
+        ; .arch x64
+        ; and ah, 0xd5u8 as i8
+        ; or ah, 2
+        ; mov BYTE [rdi + F], ah
+    );
+}
+
+// leaves the block with the pc at the address
+fn leave(ops: &mut Assembler, pc: usize, cycles: u64, instructions: u64) {
+    dynasm!(ops
+        ; .arch x64
+        ; mov WORD [rdi + PC], pc as u16 as i16
+        ; add QWORD [rdi + CYCLES], cycles as i32
+        ; add QWORD [rdi + INSTRUCTIONS], instructions as i32
+        ; ret
+    );
+}
+
+// translates the instructions from start, up to the first one that can't be
+fn translate(ops: &mut Assembler, memory: &[u8; MEMORY_SIZE], start: usize) -> Block {
+    let entry = ops.offset();
+    let mut stops: Vec<Stop> = Vec::new();
+    let mut pc = start;
+    let mut cycles: u64 = 0;
+    let mut instructions: u64 = 0;
+    let mut branched = false;
+    // a block stops short of the top of memory rather than wrapping around to 0
+    while instructions < MAX_INSTRUCTIONS && pc < MEMORY_SIZE {
+        let opcode = memory[pc];
+        let next = pc + disassembler::length(opcode) as usize;
+        if next > MEMORY_SIZE {
+            break;
+        }
+        let byte_2 = memory.get(pc + 1).copied().unwrap_or(0);
+        let word = u16::from_le_bytes([byte_2, memory.get(pc + 2).copied().unwrap_or(0)]);
+        let through = (cycles + cpu::CYCLES[opcode as usize] as u64, instructions + 1);
+        let translated = instruction(ops, &mut stops, opcode, byte_2, word, next, through);
+        if translated == Translated::No {
+            break;
+        }
+        (cycles, instructions) = through;
+        pc = next;
+        if translated == Translated::Branch {
+            branched = true;
+            break;
+        }
+    }
+    if instructions == 0 {
+        return Block { entry: None, end: start + 1, cycles: 0 };
+    }
+    if !branched {
+        leave(ops, pc, cycles, instructions);
+    }
+    for stop in stops {
+        dynasm!(ops
+            ; .arch x64
+            ; =>stop.label
+            ; mov WORD [rdi + STOPPED], 1
+            ; mov WORD [rdi + WRITTEN], cx
+        );
+        leave(ops, stop.pc as usize, stop.cycles, stop.instructions);
+    }
+    Block { entry: Some(entry), end: pc, cycles }
+}
+
+#[derive(PartialEq)]
+enum Translated {
+    No, // the instruction can't be translated, and nothing was emitted
+    Yes,
+    Branch, // a jump, which has left the block itself
+}
+
+// a = a op cl, for the ALU field of an opcode (ADD ADC SUB SBB ANA XRA ORA CMP), setting the flags as the 8080 does
+fn alu(ops: &mut Assembler, op: u8) {
+    dynasm!(ops
+        ; .arch x64
+        ; mov al, BYTE [rdi + A]
+    );
+    match op {
+        0 => dynasm!(ops ; .arch x64 ; add al, cl ; lahf),
+        1 => dynasm!(ops ; .arch x64 ; mov dl, BYTE [rdi + F] ; shr dl, 1 ; adc al, cl ; lahf),
+        // the 8080's AC after a subtraction is set when there was no borrow out of bit 3, the other way around to x86's
+        2 => dynasm!(ops ; .arch x64 ; sub al, cl ; lahf ; xor ah, 0x10),
+        3 => dynasm!(ops ; .arch x64 ; mov dl, BYTE [rdi + F] ; shr dl, 1 ; sbb al, cl ; lahf ; xor ah, 0x10),
+        // ANA sets AC from the OR of bit 3 of both operands
+        4 => dynasm!(ops
+            ; .arch x64
+            ; mov dl, al
+            ; or dl, cl
+            ; and dl, 8
+            ; shl dl, 1
+            ; and al, cl
+            ; lahf
+            ; and ah, 0xc4u8 as i8
+            ; or ah, dl
+        ),
+        5 => dynasm!(ops ; .arch x64 ; xor al, cl ; lahf ; and ah, 0xc4u8 as i8),
+        6 => dynasm!(ops ; .arch x64 ; or al, cl ; lahf ; and ah, 0xc4u8 as i8),
+        _ => dynasm!(ops ; .arch x64 ; cmp al, cl ; lahf ; xor ah, 0x10),
+    }
+    set_flags(ops);
+    if op != 7 {
+        dynasm!(ops
+            ; .arch x64
+            ; mov BYTE [rdi + A], al
+        );
+    }
+}
+
+// emits the native code for one instruction. next is the address of the instruction after it, and through the
+// cycles and instructions of the block up to and including it.
+fn instruction(
+    ops: &mut Assembler,
+    stops: &mut Vec<Stop>,
+    opcode: u8,
+    byte_2: u8,
+    word: u16,
+    next: usize,
+    through: (u64, u64),
+) -> Translated {
+    let (cycles, instructions) = through;
+    let r = (opcode >> 3) & 7;
+    match opcode {
+        // NOP, and the undocumented ones
+        0x00 | 0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => {},
+        // MOV
+        0x40..=0x75 | 0x77..=0x7f => {
+            load(ops, opcode & 7);
+            store_register(ops, stops, r, next, cycles, instructions);
+        },
+        // MVI
+        _ if opcode & 0xc7 == 0x06 => {
+            if r == 6 {
+                dynasm!(ops ; .arch x64 ; mov al, byte_2 as i8);
+                store_register(ops, stops, r, next, cycles, instructions);
+            } else {
+                dynasm!(ops ; .arch x64 ; mov BYTE [rdi + r as i32], byte_2 as i8);
+            }
+        },
+        // INR and DCR, which leave CY alone
+        _ if opcode & 0xc6 == 0x04 => {
+            load(ops, r);
+            if opcode & 1 == 0 {
+                dynasm!(ops ; .arch x64 ; inc al ; lahf);
+            } else {
+                dynasm!(ops ; .arch x64 ; dec al ; lahf ; xor ah, 0x10);
+            }
+            dynasm!(ops
+                ; .arch x64
+                ; and ah, 0xd4u8 as i8
+                ; mov dl, BYTE [rdi + F]
+                ; and dl, 1
+                ; or ah, dl
+            );
+            set_flags(ops);
+            store_register(ops, stops, r, next, cycles, instructions);
+        },
+        // the ALU instructions on a register or M
+        0x80..=0xbf => {
+            let src = opcode & 7;
+            if src == 6 {
+                hl(ops);
+                dynasm!(ops
+                    ; .arch x64
+                    ; mov rdx, QWORD [rdi + MEMORY]
+                    ; mov cl, BYTE [rdx + rcx]
+                );
+            } else {
+                dynasm!(ops ; .arch x64 ; mov cl, BYTE [rdi + src as i32]);
+            }
+            alu(ops, r);
+        },
+        // ADI, ACI, SUI, SBI, ANI, XRI, ORI and CPI
+        _ if opcode & 0xc7 == 0xc6 => {
+            dynasm!(ops ; .arch x64 ; mov cl, byte_2 as i8);
+            alu(ops, r);
+        },
+        // LXI
+        0x01 | 0x11 | 0x21 => {
+            let offset = pair(opcode);
+            dynasm!(ops
+                ; .arch x64
+                ; mov WORD [rdi + offset], word.swap_bytes() as i16
+            );
+        },
+        0x31 => dynasm!(ops ; .arch x64 ; mov WORD [rdi + SP], word as i16),
+        // INX and DCX
+        0x03 | 0x13 | 0x23 | 0x0b | 0x1b | 0x2b => {
+            let offset = pair(opcode);
+            load_pair(ops, offset);
+            if opcode & 8 == 0 {
+                dynasm!(ops ; .arch x64 ; add cx, 1);
+            } else {
+                dynasm!(ops ; .arch x64 ; sub cx, 1);
+            }
+            dynasm!(ops
+                ; .arch x64
+                ; rol cx, 8
+                ; mov WORD [rdi + offset], cx
+            );
+        },
+        0x33 => dynasm!(ops ; .arch x64 ; add WORD [rdi + SP], 1),
+        0x3b => dynasm!(ops ; .arch x64 ; sub WORD [rdi + SP], 1),
+        // DAD, which only sets CY
+        0x09 | 0x19 | 0x29 | 0x39 => {
+            if opcode == 0x39 {
+                dynasm!(ops ; .arch x64 ; movzx ecx, WORD [rdi + SP]);
+            } else {
+                load_pair(ops, pair(opcode));
+            }
+            dynasm!(ops
+                ; .arch x64
+                ; movzx eax, WORD [rdi + 4]
+                ; rol ax, 8
+                ; add ax, cx
+                ; setc dl
+                ; rol ax, 8
+                ; mov WORD [rdi + 4], ax
+            );
+            set_carry(ops);
+        },
+        // STAX and LDAX
+        0x02 | 0x12 => {
+            load_pair(ops, pair(opcode));
+            dynasm!(ops ; .arch x64 ; mov al, BYTE [rdi + A]);
+            store(ops, stops, next, cycles, instructions);
+        },
+        0x0a | 0x1a => {
+            load_pair(ops, pair(opcode));
+            dynasm!(ops
+                ; .arch x64
+                ; mov rdx, QWORD [rdi + MEMORY]
+                ; mov al, BYTE [rdx + rcx]
+                ; mov BYTE [rdi + A], al
+            );
+        },
+        // STA, LDA and LHLD
+        0x32 => {
+            dynasm!(ops
+                ; .arch x64
+                ; mov ecx, word as i32
+                ; mov al, BYTE [rdi + A]
+            );
+            store(ops, stops, next, cycles, instructions);
+        },
+        0x3a => dynasm!(ops
+            ; .arch x64
+            ; mov rdx, QWORD [rdi + MEMORY]
+            ; mov al, BYTE [rdx + word as i32]
+            ; mov BYTE [rdi + A], al
+        ),
+        0x2a => dynasm!(ops
+            ; .arch x64
+            ; mov rdx, QWORD [rdi + MEMORY]
+            ; mov al, BYTE [rdx + word as i32]
+            ; mov BYTE [rdi + 5], al
+            ; mov al, BYTE [rdx + word.wrapping_add(1) as i32]
+            ; mov BYTE [rdi + 4], al
+        ),
+        // XCHG
+        0xeb => dynasm!(ops
+            ; .arch x64
+            ; mov ax, WORD [rdi + 2]
+            ; mov cx, WORD [rdi + 4]
+            ; mov WORD [rdi + 2], cx
+            ; mov WORD [rdi + 4], ax
+        ),
+        // STC, CMC and CMA
+        0x37 => dynasm!(ops ; .arch x64 ; or BYTE [rdi + F], 1),
+        0x3f => dynasm!(ops ; .arch x64 ; xor BYTE [rdi + F], 1),
+        0x2f => dynasm!(ops ; .arch x64 ; not BYTE [rdi + A]),
+        // RLC, RRC, RAL and RAR, which only set CY
+        0x07 | 0x0f | 0x17 | 0x1f => {
+            dynasm!(ops ; .arch x64 ; mov al, BYTE [rdi + A]);
+            match opcode {
+                0x07 => dynasm!(ops ; .arch x64 ; rol al, 1),
+                0x0f => dynasm!(ops ; .arch x64 ; ror al, 1),
+                0x17 => dynasm!(ops ; .arch x64 ; mov dl, BYTE [rdi + F] ; shr dl, 1 ; rcl al, 1),
+                _ => dynasm!(ops ; .arch x64 ; mov dl, BYTE [rdi + F] ; shr dl, 1 ; rcr al, 1),
+            }
+            dynasm!(ops
+                ; .arch x64
+                ; setc dl
+                ; mov BYTE [rdi + A], al
+            );
+            set_carry(ops);
+        },
+        // JMP, and the undocumented one
+        0xc3 | 0xcb => {
+            leave(ops, word as usize, cycles, instructions);
+            return Translated::Branch;
+        },
+        // the conditional jumps: NZ Z NC C PO PE P M, each testing a flag for clear then set
+        0xc2 | 0xca | 0xd2 | 0xda | 0xe2 | 0xea | 0xf2 | 0xfa => {
+            let flag = [0x40, 0x40, 0x01, 0x01, 0x04, 0x04, 0x80u8, 0x80u8][r as usize];
+            let taken = ops.new_dynamic_label();
+            dynasm!(ops ; .arch x64 ; test BYTE [rdi + F], flag as i8);
+            if r & 1 == 0 {
+                dynasm!(ops ; .arch x64 ; jz =>taken);
+            } else {
+                dynasm!(ops ; .arch x64 ; jnz =>taken);
+            }
+            leave(ops, next, cycles, instructions);
+            dynasm!(ops ; .arch x64 ; =>taken);
+            leave(ops, word as usize, cycles, instructions);
+            return Translated::Branch;
+        },
+        _ => return Translated::No,
+    }
+    Translated::Yes
+}
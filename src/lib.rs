@@ -0,0 +1,24 @@
+// emulates the 8080
+// written following this guide: http://www.emulator101.com/
+pub mod assembler;
+pub mod breakpoints;
+pub mod cpm;
+pub mod cpu;
+pub mod debugger;
+pub mod disassembler;
+pub mod gdb;
+pub mod history;
+pub mod invaders;
+#[cfg(all(feature = "jit", target_arch = "x86_64"))]
+pub mod jit;
+pub mod listing;
+pub mod loader;
+pub mod machine;
+pub mod movie;
+pub mod reverse;
+pub mod rewind;
+pub mod run;
+pub mod savestate;
+pub mod scheduler;
+pub mod throttle;
+pub mod trace;
@@ -0,0 +1,204 @@
+// disassembles a whole ROM into an .asm listing that assembles back into the same bytes.
+// instead of reading the ROM from start to end, it follows the code from its entry points through every jump, call
+// and restart, so the tables and text in between come out as data rather than as nonsense instructions.
+use std::collections::BTreeSet;
+
+use crate::disassembler;
+use crate::disassembler::Instruction;
+use crate::loader::Program;
+
+// what each byte of the program turned out to be
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Byte {
+    Data,
+    Opcode, // the first byte of an instruction
+    Operand, // the data or address that follows an opcode
+}
+
+// where the code goes after an instruction
+enum Flow {
+    Next, // on to the next instruction
+    Branch(u16), // to the address, and possibly on to the next instruction
+    Jump(u16), // only to the address
+    Stop, // somewhere that can't be known by looking at the code, like a RET or PCHL
+}
+
+fn flow(instruction: &Instruction) -> Flow {
+    let opcode = instruction.opcode();
+    let target = instruction.operand().unwrap_or(0);
+    match opcode {
+        0xc3 | 0xcb => Flow::Jump(target), // JMP
+        0xc9 | 0xd9 | 0xe9 => Flow::Stop, // RET, PCHL
+        0xcd | 0xdd | 0xed | 0xfd => Flow::Branch(target), // CALL
+        // RST n calls 8 * n
+        _ if opcode & 0xc7 == 0xc7 => Flow::Branch((opcode & 0x38) as u16),
+        // the conditional jumps and calls
+        _ if opcode & 0xc7 == 0xc2 => Flow::Branch(target),
+        _ if opcode & 0xc7 == 0xc4 => Flow::Branch(target),
+        _ => Flow::Next,
+    }
+}
+
+pub struct Disassembly<'a> {
+    program: &'a Program,
+    pub bytes: Vec<Byte>,
+    pub labels: BTreeSet<u16>, // the branch targets inside the program
+}
+
+impl Disassembly<'_> {
+    fn contains(&self, addr: u16) -> bool {
+        addr >= self.program.origin && ((addr - self.program.origin) as usize) < self.program.bytes.len()
+    }
+
+    fn byte(&self, addr: u16) -> Byte {
+        self.bytes[(addr - self.program.origin) as usize]
+    }
+
+    fn decode(&self, addr: u16) -> Option<Instruction> {
+        let offset = (addr - self.program.origin) as usize;
+        let length = disassembler::length(self.program.bytes[offset]) as usize;
+        let bytes = self.program.bytes.get(offset..offset + length)?;
+        Some(Instruction { addr, bytes: bytes.to_vec() })
+    }
+
+    // follows the code from the entry point, marking each instruction it reaches
+    fn trace(&mut self, entry: u16) {
+        let mut pending: Vec<u16> = vec![entry];
+        while let Some(mut addr) = pending.pop() {
+            loop {
+                if !self.contains(addr) || self.byte(addr) != Byte::Data {
+                    // left the program, or got to code that has already been traced
+                    break;
+                }
+                // an instruction that runs off the end of the program, or into code, is really data
+                let Some(instruction) = self.decode(addr) else { break };
+                if (1..instruction.length()).any(|i| !self.contains(addr.wrapping_add(i)) || self.byte(addr.wrapping_add(i)) != Byte::Data) {
+                    break;
+                }
+
+                let offset = (addr - self.program.origin) as usize;
+                self.bytes[offset] = Byte::Opcode;
+                for i in 1..instruction.length() as usize {
+                    self.bytes[offset + i] = Byte::Operand;
+                }
+
+                match flow(&instruction) {
+                    Flow::Next => {},
+                    Flow::Branch(target) => {
+                        self.labels.insert(target);
+                        pending.push(target);
+                    },
+                    Flow::Jump(target) => {
+                        self.labels.insert(target);
+                        pending.push(target);
+                        break;
+                    },
+                    Flow::Stop => break,
+                }
+                addr = addr.wrapping_add(instruction.length());
+            }
+        }
+    }
+}
+
+// traces the program from each of the entry points
+pub fn disassemble<'a>(program: &'a Program, entries: &[u16]) -> Disassembly<'a> {
+    let mut disassembly = Disassembly {
+        program,
+        bytes: vec![Byte::Data; program.bytes.len()],
+        labels: BTreeSet::new(),
+    };
+    for entry in entries.iter() {
+        disassembly.labels.insert(*entry);
+        disassembly.trace(*entry);
+    }
+    // a label is only useful where it can be put: at the start of an instruction or in the data
+    let labels: Vec<u16> = disassembly.labels.iter().copied().collect();
+    for label in labels {
+        if !disassembly.contains(label) || disassembly.byte(label) == Byte::Operand {
+            disassembly.labels.remove(&label);
+        }
+    }
+    disassembly
+}
+
+// the entry points of a ROM at the origin: the origin itself, and the interrupt vectors if the ROM covers them
+pub fn default_entries(program: &Program) -> Vec<u16> {
+    let mut entries = vec![program.origin];
+    if program.origin == 0 {
+        // RST 0 is the reset vector, which is the origin
+        entries.extend((1..8).map(|n| n * 8).filter(|addr| (*addr as usize) < program.bytes.len()));
+    }
+    entries
+}
+
+fn label(addr: u16) -> String {
+    format!("L{:04x}", addr)
+}
+
+// the label for an address, or the address as a number if it has none
+fn operand(labels: &BTreeSet<u16>, value: u16) -> String {
+    if labels.contains(&value) {
+        label(value)
+    } else {
+        disassembler::hex(value, 4)
+    }
+}
+
+fn data(bytes: &[u8]) -> String {
+    let bytes: Vec<String> = bytes.iter().map(|b| disassembler::hex(*b as u16, 2)).collect();
+    format!("DB {}", bytes.join(","))
+}
+
+// writes the listing. each line has room for a label, then the instruction or data, then a comment with the address.
+pub fn listing(program: &Program, entries: &[u16]) -> String {
+    let disassembly = disassemble(program, entries);
+    let labels = &disassembly.labels;
+
+    let mut out = String::new();
+    let entry_names: Vec<String> = entries.iter().map(|entry| disassembler::hex(*entry, 4)).collect();
+    out.push_str(&format!("; {} bytes, traced from {}\n", program.bytes.len(), entry_names.join(", ")));
+    out.push_str(&format!("{:<8}ORG {}\n\n", "", disassembler::hex(program.origin, 4)));
+
+    let end = program.origin as usize + program.bytes.len();
+    let mut addr = program.origin as usize;
+    while addr < end {
+        let offset = addr - program.origin as usize;
+        let prefix = if labels.contains(&(addr as u16)) { format!("{}:", label(addr as u16)) } else { String::new() };
+
+        let (text, comment, length) = if disassembly.bytes[offset] == Byte::Opcode {
+            let instruction = disassembly.decode(addr as u16).unwrap();
+            let length = instruction.length() as usize;
+            let bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            if instruction.documented() {
+                let text = if instruction.template().contains("adr") {
+                    instruction.text_with(|value| operand(labels, value))
+                } else {
+                    instruction.to_string()
+                };
+                (text, bytes.join(" "), length)
+            } else {
+                // the assembler only knows the documented names, so the undocumented opcodes are written as bytes
+                (data(&instruction.bytes), instruction.to_string(), length)
+            }
+        } else {
+            // a run of data, up to 8 bytes, ending before the next label or instruction
+            let mut length = 1;
+            while length < 8 && addr + length < end {
+                if labels.contains(&((addr + length) as u16)) || disassembly.bytes[offset + length] != Byte::Data {
+                    break;
+                }
+                length += 1;
+            }
+            (data(&program.bytes[offset..offset + length]), String::new(), length)
+        };
+
+        let line = format!("{:<8}{:<24}; {:04x}  {}", prefix, text, addr, comment);
+        out.push_str(line.trim_end());
+        out.push('\n');
+        addr += length;
+    }
+
+    out.push_str(&format!("\n{:<8}END\n", ""));
+    out
+}
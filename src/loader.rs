@@ -0,0 +1,130 @@
+// reads programs into memory from the formats they come in: hexdumps, Intel HEX files and raw binaries.
+// every loader checks its input, so a bad file gives an error instead of a panic partway through loading.
+use crate::cpu::State8080;
+
+const MEMORY_SIZE: usize = 0x10000;
+
+// a block of bytes, and the address it is loaded at
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+impl Program {
+    // copies the program into memory at its origin
+    pub fn load(&self, state: &mut State8080) {
+        let start = self.origin as usize;
+        state.memory[start..start + self.bytes.len()].copy_from_slice(&self.bytes);
+    }
+}
+
+// makes a program, if it fits in memory above its origin
+fn program(origin: u16, bytes: Vec<u8>) -> Result<Program, String> {
+    if origin as usize + bytes.len() > MEMORY_SIZE {
+        return Err(format!("{} bytes at {:04x}h don't fit in memory", bytes.len(), origin));
+    }
+    Ok(Program { origin, bytes })
+}
+
+// reads the output of hexdump, loaded at address 0: each line is an offset followed by 16-bit words,
+// and each word holds two bytes of the program, the leftmost first
+pub fn hexdump(text: &str) -> Result<Program, String> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        // the first string on each line is just the offset
+        for word in line.split_whitespace().skip(1) {
+            let int_rep = u16::from_str_radix(word, 16).map_err(|_| format!("line {}: '{}' isn't a hex word", n + 1, word))?;
+            bytes.push((int_rep >> 8) as u8);
+            bytes.push(int_rep as u8);
+        }
+    }
+    program(0, bytes)
+}
+
+// reads an Intel HEX file. the data records may be in any order and have gaps between them, which are filled
+// with zeros, so the program starts at the lowest address in the file and ends at the highest.
+pub fn intel_hex(text: &str) -> Result<Program, String> {
+    let mut records: Vec<(u16, Vec<u8>)> = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| format!("line {}: {}", n + 1, message);
+
+        let Some(hex) = line.strip_prefix(':') else {
+            return Err(error("records start with ':'"));
+        };
+        if !hex.len().is_multiple_of(2) || hex.len() < 10 {
+            return Err(error("record is too short"));
+        }
+        let mut record: Vec<u8> = Vec::new();
+        for i in (0..hex.len()).step_by(2) {
+            let byte = hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok());
+            record.push(byte.ok_or(error("not a hex digit"))?);
+        }
+
+        // the bytes of a record, including its checksum, add up to 0
+        if record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(error("bad checksum"));
+        }
+        let count = record[0] as usize;
+        if record.len() != count + 5 {
+            return Err(error("byte count doesn't match the record"));
+        }
+        let addr = ((record[1] as u16) << 8) | (record[2] as u16);
+        let data = &record[4..4 + count];
+        match record[3] {
+            0x00 => {
+                if addr as usize + count > MEMORY_SIZE {
+                    return Err(error("data runs past the end of memory"));
+                }
+                records.push((addr, data.to_vec()));
+            },
+            0x01 => break,
+            // a segment or upper address of 0 is still inside the 8080's 64K; anything else isn't
+            0x02 | 0x04 => {
+                if data.iter().any(|byte| *byte != 0) {
+                    return Err(error("address is beyond 64K"));
+                }
+            },
+            // start addresses are for x86 programs
+            0x03 | 0x05 => {},
+            other => return Err(error(&format!("unknown record type {:02x}", other))),
+        }
+    }
+
+    let Some(origin) = records.iter().map(|(addr, _)| *addr).min() else {
+        return program(0, Vec::new());
+    };
+    let end = records.iter().map(|(addr, data)| *addr as usize + data.len()).max().unwrap();
+    let mut bytes = vec![0; end - origin as usize];
+    for (addr, data) in records.iter() {
+        let start = (*addr - origin) as usize;
+        bytes[start..start + data.len()].copy_from_slice(data);
+    }
+    program(origin, bytes)
+}
+
+// writes a program as an Intel HEX file, 16 bytes to a record
+pub fn write_intel_hex(program: &Program) -> String {
+    let mut text = String::new();
+    for (i, chunk) in program.bytes.chunks(16).enumerate() {
+        let addr = program.origin as usize + i * 16;
+        let mut record = vec![chunk.len() as u8, (addr >> 8) as u8, addr as u8, 0x00];
+        record.extend_from_slice(chunk);
+        let sum = record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        record.push(sum.wrapping_neg());
+        text.push(':');
+        text.extend(record.iter().map(|byte| format!("{:02X}", byte)));
+        text.push('\n');
+    }
+    text.push_str(":00000001FF\n");
+    text
+}
+
+// takes the bytes of a binary file as they are, loaded at the given address
+pub fn binary(bytes: &[u8], origin: u16) -> Result<Program, String> {
+    program(origin, bytes.to_vec())
+}
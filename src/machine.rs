@@ -0,0 +1,69 @@
+// the hardware around the cpu. the cpu leaves IN and OUT to the machine it is built into,
+// so machines run instructions with step, which does the port access before emulating the instruction.
+use crate::cpu::emulate;
+use crate::cpu::State8080;
+
+pub trait Machine {
+    // returns the value IN reads from a port
+    fn input(&mut self, port: u8) -> u8;
+
+    // takes the value OUT writes to a port
+    fn output(&mut self, port: u8, value: u8);
+
+    // the state of the machine's devices, for a save state. a machine with nothing to save saves nothing.
+    fn save_devices(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    // puts back the state of the devices from a save state
+    fn load_devices(&mut self, data: &[u8]) -> Result<(), String> {
+        if data.is_empty() {
+            Ok(())
+        } else {
+            Err("this machine has no devices to load".to_string())
+        }
+    }
+}
+
+// a machine with nothing connected to its ports: IN reads 0, and OUT is ignored
+pub struct Bare;
+
+impl Machine for Bare {
+    fn input(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn output(&mut self, _port: u8, _value: u8) {}
+}
+
+// emulates one instruction in the machine, and returns the number of clock cycles it took
+pub fn step<M: Machine + ?Sized>(state: &mut State8080, machine: &mut M) -> u8 {
+    if !state.halted {
+        let pc = state.pc;
+        // like the instruction fetch in emulate, this reads memory directly so it doesn't show up as an access
+        let port = state.memory[pc.wrapping_add(1) as usize];
+        match state.memory[pc as usize] {
+            0xdb => state.a = machine.input(port), // IN
+            0xd3 => machine.output(port, state.a), // OUT
+            _ => {},
+        }
+    }
+    emulate(state)
+}
+
+// interrupts the cpu with RST n, which it only takes if interrupts are enabled.
+// returns true if the interrupt was taken.
+pub fn interrupt(state: &mut State8080, n: u8) -> bool {
+    if !state.int_enable {
+        return false;
+    }
+    // an interrupt disables further interrupts until the handler enables them again with EI
+    state.int_enable = false;
+    state.halted = false;
+    let ret = state.pc;
+    state.set_mem(state.sp.wrapping_sub(1), (ret >> 8) as u8);
+    state.set_mem(state.sp.wrapping_sub(2), ret as u8);
+    state.sp = state.sp.wrapping_sub(2);
+    state.pc = (n as u16 & 7) * 8;
+    true
+}
@@ -1,2306 +1,839 @@
 // emulates the 8080
 // written following this guide: http://www.emulator101.com/
 use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+
+use emulator_8080::assembler;
+use emulator_8080::cpm;
+use emulator_8080::cpu::State8080;
+use emulator_8080::debugger;
+use emulator_8080::debugger::Debugger;
+use emulator_8080::disassembler;
+use emulator_8080::gdb;
+use emulator_8080::invaders::Invaders;
+use emulator_8080::listing;
+use emulator_8080::loader;
+use emulator_8080::loader::Program;
+use emulator_8080::machine::Bare;
+use emulator_8080::machine::Machine;
+use emulator_8080::movie;
+use emulator_8080::movie::Movie;
+use emulator_8080::rewind::Rewind;
+use emulator_8080::run::Exit;
+use emulator_8080::run::Runner;
+use emulator_8080::run::Until;
+use emulator_8080::savestate;
+
+// an option a command takes, with the name of its value if it has one
+struct Opt {
+    name: &'static str,
+    value: Option<&'static str>,
+    help: &'static str,
+}
 
-use std::fs::File;
-use std::io::BufReader;
-use std::io::BufRead;
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
+const fn opt(name: &'static str, value: Option<&'static str>, help: &'static str) -> Opt {
+    Opt { name, value, help }
+}
 
-    if args.len() != 2 {
-        println!("Improper usage. Please pass the name of the hexdump file to emulate as an argument.");
-        return;
-    }
+// the options of every command that loads a program
+const LOAD_OPTIONS: &[Opt] = &[
+    opt("--format", Some("<format>"), "auto (the default, by the file's extension), bin, hex, hexdump, asm or com"),
+    opt("--origin", Some("<address>"), "where a binary file is loaded (0, or 0100h for a .com file)"),
+    opt("--entry", Some("<address>"), "where to start running, if not at the origin"),
+    opt("--machine", Some("<machine>"), "bare (the default), invaders, or cpm (the default for .com files)"),
+];
+
+struct Command {
+    name: &'static str,
+    args: &'static str,
+    summary: &'static str, // for the list of commands
+    help: &'static str,
+    loads: bool, // takes the LOAD_OPTIONS
+    options: &'static [Opt],
+}
 
-    // read the hex into a vector of strings, ignoring the line numbers
-    let mut hex_strings: Vec<&str> = Vec::new();
-    let reader = BufReader::new(File::open(&args[1]).expect("Cannot open file."));
-    let lines: Vec<String> = reader.lines().collect::<Result<_, _>>().unwrap();
-    for line in lines.iter() {
-        let strings: Vec<&str> = line.split_whitespace().collect();
-        for j in 0..strings.len() {
-            // every 9 strings is just the line number
-            if j % 9 != 0 {
-                hex_strings.push(strings[j]);
-            }
-        }
-    }
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "run",
+        summary: "run a program headless until a condition or limit, and summarise it as JSON",
+        args: "<file>",
+        help: "runs a program without the debugger until it stops at a condition or reaches a limit, and writes a JSON\n\
+               summary of how it ended. it passes (and exits with 0) if it stops at one of the conditions, or wasn't\n\
+               waiting for any; otherwise it exits with 1.",
+        loads: true,
+        options: &[
+            opt("--cycles", Some("<n>"), "stop after n cycles"),
+            opt("--frames", Some("<n>"), "stop after n frames, at 60 frames a second"),
+            opt("--until-pc", Some("<address>"), "stop when the pc gets to the address"),
+            opt("--until-mem", Some("<address>=<byte>"), "stop when the byte at the address has the value"),
+            opt("--until-halt", None, "stop when the cpu halts"),
+            opt("--until-output", Some("<text>"), "stop when the program's output ends with the text"),
+            opt("--console", Some("<port>"), "take the output of a program that isn't a CP/M one from OUTs to the port"),
+            opt("--trace", Some("<file>"), "write a line for each instruction run to the file"),
+            opt("--state", Some("<file>"), "start from a save state"),
+            opt("--save", Some("<file>"), "write a save state when the run ends"),
+            opt("--rewind", Some("<n>"), "when the run ends, go back n frames before writing the summary and save state"),
+            opt("--summary", Some("<file>"), "write the summary to the file rather than stdout"),
+            opt("--speed", Some("<x>"), "run at x times the speed of the real 1.9968MHz 8080, or max (the default)"),
+            opt("--show-speed", None, "write the speed it runs at to stderr each second, as a percentage of the real one"),
+        ],
+    },
+    Command {
+        name: "debug",
+        summary: "step through a program in the debugger",
+        args: "<file>",
+        help: "steps through a program in the debugger, which reads its commands from stdin (type help for them).",
+        loads: true,
+        options: &[
+            opt("--trace", Some("<file>"), "write a line for each instruction run to the file"),
+            opt("--state", Some("<file>"), "carry on from a save state"),
+        ],
+    },
+    Command {
+        name: "disasm",
+        summary: "list the instructions in a ROM",
+        args: "<file> [<start> [<end>]]",
+        help: "lists the instructions in a ROM, from start to end, or over the whole ROM if they aren't given.",
+        loads: false,
+        options: &[
+            opt("--format", Some("<format>"), "auto (the default, by the file's extension), bin, hex, hexdump, asm or com"),
+            opt("--origin", Some("<address>"), "where a binary file is loaded (0, or 0100h for a .com file)"),
+            opt("--trace", None, "follow the code from the entry points instead, and write an .asm listing of the whole ROM"),
+            opt("--entry", Some("<address>"), "an entry point to follow the code from, which can be given more than once"),
+        ],
+    },
+    Command {
+        name: "asm",
+        summary: "assemble a program",
+        args: "<source>",
+        help: "assembles a program. a listing (.lst) and symbols (.sym) are written beside the output.",
+        loads: false,
+        options: &[
+            opt("-o", Some("<output>"), "the output file: Intel HEX if it ends in .hex, or else binary (<source>.bin by default)"),
+            opt("--cpm", None, "run the program as a CP/M .COM file once it's assembled"),
+        ],
+    },
+    Command {
+        name: "test",
+        summary: "run cpu test programs as CP/M programs, and say which passed",
+        args: "<file>...",
+        help: "runs cpu test programs, such as TST8080, CPUTEST, 8080PRE and 8080EXM, as CP/M programs, and says which\n\
+               passed. a program passes if it ends and says it is operational, complete or ok without an error, or prints\n\
+               the --expect text. exits with 1 if any fail.",
+        loads: false,
+        options: &[
+            opt("--expect", Some("<text>"), "the output that means a program passed"),
+            opt("--cycles", Some("<n>"), "fail a program that runs for more than n cycles"),
+            opt("--trace", Some("<file>"), "write a line for each instruction run to the file"),
+            opt("--verbose", None, "show the output of the programs that pass too"),
+        ],
+    },
+    Command {
+        name: "info",
+        summary: "describe a program, save state or movie",
+        args: "<file>",
+        help: "describes a program, save state or movie: where a program loads and starts, its size and checksums.",
+        loads: true,
+        options: &[],
+    },
+    Command {
+        name: "gdb",
+        summary: "debug a program from gdb",
+        args: "<file>",
+        help: "loads a program and waits for gdb to connect to it on localhost.",
+        loads: true,
+        options: &[opt("--port", Some("<port>"), "the port to listen on (1234 by default)")],
+    },
+    Command {
+        name: "record",
+        summary: "record a space invaders movie from an input script",
+        args: "<space invaders ROM> <input script>",
+        help: "plays space invaders from power on, holding down the buttons the input script gives for each frame, and\n\
+               records it as a movie. each line of the script is a number of frames and the buttons held down through\n\
+               them: coin, p1-start, p2-start, p1-fire, p1-left, p1-right, p2-fire, p2-left, p2-right or tilt.\n\
+               # starts a comment.",
+        loads: false,
+        options: &[opt("-o", Some("<movie>"), "the movie to write (<input script>.movie by default)")],
+    },
+    Command {
+        name: "replay",
+        summary: "play a space invaders movie again, and check it goes the same way",
+        args: "<space invaders ROM> <movie>",
+        help: "plays a recorded space invaders game again, and checks that it goes the same way.",
+        loads: false,
+        options: &[],
+    },
+    Command {
+        name: "cpm",
+        summary: "boot CP/M from disk images",
+        args: "<disk image>...",
+        help: "boots CP/M from the disk images, which are given in drive order starting with A.",
+        loads: false,
+        options: &[
+            opt("--system", Some("<image>"), "the CCP+BDOS image to boot, rather than the one on drive A"),
+            opt("--dpb", Some("<format>"), "the format of the next disk image, by name or fields (IBM 3740 by default)"),
+            opt("--host", Some("<directory>"), "a drive backed by the files in a directory on the host"),
+        ],
+    },
+];
+
+fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
 
-    // convert the hex strings into unsigned 16-bit integers
-    let mut rom: Vec<u16> = Vec::new();
-    for h in hex_strings.iter() {
-        let int_rep = u16::from_str_radix(h, 16).unwrap();
-        rom.push(int_rep);
+fn usage() -> String {
+    let mut s = String::from("usage: emulator-8080 <command> [<options>] <file>\n\ncommands:\n");
+    for command in COMMANDS {
+        s.push_str(&format!("  {:<8}{}\n", command.name, command.summary));
     }
-
-    emulate_all(rom);
-
+    s.push_str("  help    show the options of a command\n\n");
+    s.push_str("run emulator-8080 help <command> for the options of a command.\n");
+    s.push_str("a hexdump file given on its own is run until it halts, or for 120 million cycles, like\n");
+    s.push_str("run --format hexdump --until-halt --cycles 120000000.\n");
+    s
 }
 
-
-fn emulate_all(hex_dump: Vec<u16>) {
-    let cc = ConditionCodes {
-        z: false,
-        s: false,
-        p: false,
-        cy: false,
-        ac: false,
-    };
-
-    let state = &mut State8080 {
-        a: 0,
-        b: 0,
-        c: 0,
-        d: 0,
-        e: 0,
-        h: 0,
-        l: 0,
-        sp: 0,
-        pc: 0,
-        memory: vec![0; 65536], // size of the memory for the space invaders arcade machine
-        cc: cc,
-    };
-
-    // loads the rom into memory
-    let mut i = 0;
-    for x in hex_dump {
-        // memory is in bytes, but our hexdump is in format xxxx xxxx ...
-        // so we need to load in the leftmost 8 bits of each value, then the rightmost 8 bits.
-        state.memory[i] = (x >> 8) as u8;
-        i += 1;
-        state.memory[i] = x as u8;
-        i += 1;
+fn command_usage(command: &Command) -> String {
+    let mut s = format!("usage: emulator-8080 {} [<options>] {}\n\n{}\n\noptions:\n", command.name, command.args, command.help);
+    let options = command.options.iter().chain(if command.loads { LOAD_OPTIONS } else { &[] });
+    for option in options {
+        let name = format!("{} {}", option.name, option.value.unwrap_or(""));
+        s.push_str(&format!("  {:<30}{}\n", name.trim_end(), option.help));
     }
-
-    //loop {
-    for i in 0..11 {
-        emulate(state);
-        println!("state is: {}", state.clone().dump_state());
-
-        // break when the program counter reaches the end (of the memory)
-        // TODO: I think check that pc < memory.len()
-        //if state.pc
+    if command.options.is_empty() && !command.loads {
+        s.push_str("  (none)\n");
     }
+    s
 }
 
-
-// flags used for arithmetic operations
-#[derive(Clone)]
-struct ConditionCodes {
-    z: bool, // true when result is 0
-    s: bool, // true when MSB (bit 7) is 1
-    p: bool, // true when result has even parity
-    cy: bool, // true when instruction caused a carry out to a higher bit
-    ac: bool, // TODO (not used by space invaders)
-}
-
-#[derive(Clone)]
-struct State8080 {
-    a: u8,
-    b: u8,
-    c: u8,
-    d: u8,
-    e: u8,
-    h: u8,
-    l: u8,
-    sp: u16,
-    pc: u16,
-    memory: Vec<u8>, // in the original code this is an integer pointer, but here we use a vector because integers cannot be indexed in rust
-    // TODO: change 'memory' to be a fixed size array once the necessary size is known
-    cc: ConditionCodes,
+// the options and files given to a command
+struct Parsed {
+    values: Vec<(&'static str, String)>,
+    flags: Vec<&'static str>,
+    files: Vec<String>,
 }
 
-impl State8080 {
-    // for debugging. converts the fields to strings for printing.
-    fn dump_state(self) -> String {
-        let mut s = "a:".to_string();
-        s.push_str(&self.a.to_string());
-        s.push_str(" b:");
-        s.push_str(&self.b.to_string());
-        s.push_str(" c:");
-        s.push_str(&self.c.to_string());
-        s.push_str(" d:");
-        s.push_str(&self.d.to_string());
-        s.push_str(" e:");
-        s.push_str(&self.e.to_string());
-        s.push_str(" h:");
-        s.push_str(&self.h.to_string());
-        s.push_str(" l:");
-        s.push_str(&self.l.to_string());
-        s.push_str(" sp:");
-        s.push_str(&self.sp.to_string());
-        s.push_str(" pc:");
-        s.push_str(&self.pc.to_string());
-        s.push_str(" memory size:");
-        s.push_str(&self.memory.len().to_string());
-
-        return s;
+impl Parsed {
+    // the last value given for the option
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.iter().rev().find(|(option, _)| *option == name).map(|(_, value)| value.as_str())
     }
 
-    // sets the zero (z) condition code
-    fn set_zero_flag(&mut self, result: u16) {
-        if result == 0 {
-            self.cc.z = true;
-        } else {
-            self.cc.z = false;
-        }
+    fn all(&self, name: &str) -> Vec<&str> {
+        self.values.iter().filter(|(option, _)| *option == name).map(|(_, value)| value.as_str()).collect()
     }
 
-    // sets the sign (s) condition code
-    fn set_sign_flag(&mut self, result: u16) {
-        if result & 0b10000000 != 0 {
-            self.cc.s = true;
-        } else {
-            self.cc.s = false;
-        }
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(&name)
     }
 
-    // sets the carry (cy) condition code (for u16)
-    fn set_carry_flag(&mut self, result: u16) {
-        if result > 0xff {
-            self.cc.cy = true;
-        } else {
-            self.cc.cy = false;
+    // the only file, which the command needs
+    fn file(&self, command: &Command) -> Result<&str, String> {
+        match self.files.as_slice() {
+            [file] => Ok(file),
+            [] => Err(format!("{} needs the name of a file", command.name)),
+            _ => Err(format!("{} takes one file, but was given {}", command.name, self.files.join(", "))),
         }
     }
+}
 
-    // sets the carry (cy) condition code (for u32)
-    fn set_carry_flag_double(&mut self, result: u32) {
-        if result > 0xffff {
-            self.cc.cy = true;
+fn parse_args(command: &Command, args: &[String]) -> Result<Parsed, String> {
+    let mut parsed = Parsed { values: Vec::new(), flags: Vec::new(), files: Vec::new() };
+    let options: Vec<&Opt> = command.options.iter().chain(if command.loads { LOAD_OPTIONS } else { &[] }).collect();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg.starts_with('-') && arg.len() > 1 {
+            let Some(option) = options.iter().find(|option| option.name == arg) else {
+                return Err(format!("{} doesn't have an option {}", command.name, arg));
+            };
+            if let Some(value_name) = option.value {
+                let Some(value) = args.get(i + 1) else {
+                    return Err(format!("{} needs a value: {} {}", arg, arg, value_name));
+                };
+                parsed.values.push((option.name, value.clone()));
+                i += 1;
+            } else {
+                parsed.flags.push(option.name);
+            }
         } else {
-            self.cc.cy = false;
+            parsed.files.push(arg.clone());
         }
+        i += 1;
     }
+    Ok(parsed)
+}
 
-    // concatenates h and l register values, and returns hl
-    fn get_hl(&mut self) -> u16 {
-        let hl: u16 = (self.h as u16) << 8 | (self.l as u16);
-        return hl;
-    }
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let Some(name) = args.get(1) else {
+        print!("{}", usage());
+        std::process::exit(2);
+    };
 
-    // returns the byte at the 16-bit address passed-in
-    fn get_mem(&mut self, addr: u16) -> u8 {
-        return self.memory[addr as usize];
-    }
+    let result = match name.as_str() {
+        "help" | "--help" | "-h" => help(args.get(2)),
+        // the older ways of running CP/M programs
+        "--cpm" if args.len() == 3 => run_com(&args[2]),
+        "--cpm-system" => run_cpm_system(&args[2..]),
+        _ if args.len() == 2 && find_command(name).is_none() && Path::new(name).is_file() => emulate_all(name),
+        _ => match find_command(name) {
+            Some(command) => parse_args(command, &args[2..]).and_then(|parsed| match command.name {
+                "run" => run(command, &parsed),
+                "debug" => debug(command, &parsed),
+                "disasm" => disasm(command, &parsed),
+                "asm" => asm(command, &parsed),
+                "test" => test(command, &parsed),
+                "info" => info(command, &parsed),
+                "gdb" => gdb_server(command, &parsed),
+                "record" => record(command, &parsed),
+                "replay" => replay(command, &parsed),
+                // the order of the disks and their formats matters, so cpm reads its own arguments
+                _ => run_cpm_system(&args[2..]),
+            }),
+            None => Err(format!("there is no command '{}'", name)),
+        },
+    };
 
-    // sets the byte at the 16-bit address passed-in
-    fn set_mem(&mut self, addr: u16, val: u8) {
-        self.memory[addr as usize] = val;
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        match find_command(name) {
+            Some(command) => eprintln!("run emulator-8080 help {} for its options.", command.name),
+            None => eprintln!("run emulator-8080 help for the commands."),
+        }
+        std::process::exit(2);
     }
 }
 
-
-// emulates one 8080 instruction
-fn emulate(state: &mut State8080) {
-    let opcode: u8 = state.get_mem(state.pc); // only needs 4 bytes, but rust doesn't have that...
-    let byte_2: u8 = state.get_mem(state.pc + 1);
-    let byte_3: u8 = state.get_mem(state.pc + 2);
-    println!("opcode: {:x}", opcode);
-    println!("byte 2 is: {:x}", byte_2);
-    println!("byte 3 is: {:x}", byte_3);
-    match opcode {
-        0x00 => {
-            // NOP
-            // (do nothing)
-            state.pc += 1;
-        },
-        0x01 => {
-            // LXI B,word
-            state.c = byte_2;
-            state.b = byte_3;
-            state.pc += 3;
+fn help(name: Option<&String>) -> Result<(), String> {
+    match name {
+        None => print!("{}", usage()),
+        Some(name) => match find_command(name) {
+            Some(command) => print!("{}", command_usage(command)),
+            None => return Err(format!("there is no command '{}'", name)),
         },
-        0x02 => {
-            // STAX B
-            let bc: u16 = ((state.b as u16) << 8) | (state.c as u16);
-            state.set_mem(bc, state.a);
-            state.pc += 1;
-        },
-        0x03 => {
-            // INX B
-            let bc: u16 = ((state.b as u16) << 8) | (state.c as u16);
-            let bc_inc: u16 = bc + 1;
+    }
+    Ok(())
+}
 
-            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
-            state.b = (bc_inc >> 8) as u8;
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("cannot open {}: {}", path, e))
+}
 
-            // truncate the leftmost 8 bits
-            state.c = bc_inc as u8;
-            state.pc += 1;
-        },
-        0x04 => {
-            // INR B
-            let sum: u16 = (state.b as u16) + 1;
-            state.b = sum as u8;
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-            state.pc += 1;
-        },
-        0x05 => {
-            // DCR B
-            let diff: u16 = (state.b as u16).wrapping_sub(1);
-            state.b = diff as u8;
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-            state.pc += 1;
-        },
-        0x06 => {
-            // MVI B,D8
-            state.b = byte_2;
-            state.pc += 2;
-        },
-        0x07 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-        },
-        0x08 => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-        },
-        0x09 => {
-            // DAD B
-            let hl: u16 = ((state.h as u16) << 8) | (state.l as u16);
-            let bc: u16 = ((state.b as u16) << 8) | (state.c as u16);
+fn read_text(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("cannot open {}: {}", path, e))
+}
 
-            let sum: u32 = (hl as u32) + (bc as u32);
+fn write(path: &str, data: impl AsRef<[u8]>) -> Result<(), String> {
+    std::fs::write(path, data).map_err(|e| format!("cannot write {}: {}", path, e))
+}
 
-            // h stores the leftmost 8 bits. l stores the rightmost 8 bits.
-            // if we cast sum from u16 to u8, then the leftmost 8 bits are dropped.
-            state.l = sum as u8;
-            state.h = (sum >> 8) as u8;
+// reads an address written in hex, as 1a3f, 1a3fh or 0x1a3f
+fn parse_address(s: &str) -> Result<u16, String> {
+    debugger::parse_hex(s).map_err(|_| format!("'{}' isn't an address", s))
+}
 
-            state.set_carry_flag_double(sum);
-            state.pc += 1;
-        },
-        0x0a => {
-            // LDAX B
-            let bc = (state.b as u16) << 8 | state.c as u16;
-            let bc_mem = state.get_mem(bc);
-            state.a = bc_mem;
-            state.pc += 1;
-        },
-        0x0b => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x0c => {
-            // INR C
-            let sum: u16 = (state.c as u16) + 1;
-            state.c = sum as u8;
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-            state.pc += 1;
-        },
-        0x0d => {
-            // DCR C
-            let diff: u16 = (state.c as u16).wrapping_sub(1);
-            state.c = diff as u8;
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-            state.pc += 1;
-        },
-        0x0e => {
-            // MVI C,D8
-            state.c = byte_2;
-            state.pc += 2;
-        },
-        0x0f => {
-            // RRC
-            let x: u8 = state.a;
-            state.a = ((x & 1) << 7) | (x >> 1);
-            state.cc.cy = 1 == (x & 1);
-            state.pc += 1;
-        },
-        0x10 => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x11 => {
-            // LXI D,D16
-            state.d = byte_3;
-            state.e = byte_2;
-            state.pc += 3;
-        },
-        0x12 => {
-            // STAX D
-            let de: u16 = ((state.d as u16) << 8) | (state.e as u16);
-            state.set_mem(de, state.a);
-            state.pc += 1;
-        },
-        0x13 => {
-            // INX B
-            let de: u16 = ((state.d as u16) << 8) | (state.e as u16);
-            let de_inc: u16 = de + 1;
+// reads a byte or port number, written in hex
+fn parse_byte(s: &str) -> Result<u8, String> {
+    debugger::parse_hex(s).ok().and_then(|b| u8::try_from(b).ok()).ok_or(format!("'{}' isn't a byte", s))
+}
 
-            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
-            state.d = (de_inc >> 8) as u8;
+// reads a count, written in decimal
+fn parse_count(s: &str) -> Result<u64, String> {
+    s.parse().map_err(|_| format!("'{}' isn't a count", s))
+}
 
-            // truncate the leftmost 8 bits
-            state.e = de_inc as u8;
-            state.pc += 1;
-        },
-        0x14 => {
-            // INR D
-            let sum: u16 = (state.d as u16) + 1;
-            state.d = sum as u8;
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-            state.pc += 1;
-        },
-        0x15 => {
-            // DCR D
-            let diff: u16 = (state.d as u16).wrapping_sub(1);
-            state.d = diff as u8;
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-            state.pc += 1;
-        },
-        0x16 => {
-            // MVI D,D8
-            state.d = byte_2;
-            state.pc += 2;
+// reads a speed, as a multiple of the real one, or max for no limit
+fn parse_speed(s: &str) -> Result<Option<f64>, String> {
+    match s {
+        "max" => Ok(None),
+        _ => match s.parse::<f64>() {
+            Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(Some(speed)),
+            _ => Err(format!("'{}' isn't a speed: give a multiple of the real speed, such as 1 or 0.5, or max", s)),
         },
-        0x17 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x18 => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x19 => {
-            // DAD D
-            let hl: u16 = ((state.h as u16) << 8) | (state.l as u16);
-            let de: u16 = ((state.d as u16) << 8) | (state.e as u16);
-
-            let sum: u32 = (hl as u32) + (de as u32);
-
-            // h stores the leftmost 8 bits. l stores the rightmost 8 bits.
-            // if we cast sum from u16 to u8, then the leftmost 8 bits are dropped.
-            state.l = sum as u8;
-            state.h = (sum >> 8) as u8;
-
-            state.set_carry_flag_double(sum);
+    }
+}
 
-            state.pc += 1;
-        },
-        0x1a => {
-            // LDAX D
-            let de = (state.d as u16) << 8 | state.e as u16;
-            let de_mem = state.get_mem(de);
-            state.a = de_mem;
-            state.pc += 1;
-        },
-        0x1b => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x1c => {
-            // INR E
-            let sum: u16 = (state.e as u16) + 1;
-            state.e = sum as u8;
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-            state.pc += 1;
-        },
-        0x1d => {
-            // DCR E
-            let diff: u16 = (state.e as u16).wrapping_sub(1);
-            state.e = diff as u8;
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-            state.pc += 1;
-        },
-        0x1e => {
-            // MVI E,D8
-            state.e = byte_2;
-            state.pc += 2;
-        },
-        0x1f => {
-            // RAR
-            let x: u8 = state.a;
-            state.a = ((state.cc.cy as u8) << 7) | (x >> 1);
-            state.cc.cy = 1 == (x & 1);
-            state.pc += 1;
-        },
-        0x20 => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x21 => {
-            // LXI H,D16
-            state.h = byte_3;
-            state.l = byte_2;
-            state.pc += 3;
-        },
-        0x22 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x23 => {
-            // INX H
-            let hl: u16 = state.get_hl();
-            let hl_inc: u16 = hl + 1;
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Binary,
+    IntelHex,
+    Hexdump,
+    Asm,
+    Com,
+}
 
-            // shift out the rightmost 8 bits, and truncate to use only the remaining 8 bits.
-            state.h = (hl_inc >> 8) as u8;
+// the format given, or else the one the file's extension says
+fn format(parsed: &Parsed, path: &str) -> Result<Format, String> {
+    let given = parsed.value("--format").unwrap_or("auto");
+    let extension = Path::new(path).extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let name = if given == "auto" { extension.as_str() } else { given };
+    Ok(match name {
+        "asm" => Format::Asm,
+        "hex" => Format::IntelHex,
+        "hexdump" => Format::Hexdump,
+        "com" => Format::Com,
+        "bin" => Format::Binary,
+        _ if given == "auto" => Format::Binary,
+        _ => return Err(format!("'{}' isn't a format: the formats are auto, bin, hex, hexdump, asm and com", given)),
+    })
+}
 
-            // truncate the leftmost 8 bits
-            state.l = hl_inc as u8;
-            state.pc += 1;
-        },
-        0x24 => {
-            // INR H
-            let sum: u16 = (state.h as u16) + 1;
-            state.h = sum as u8;
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-            state.pc += 1;
-        },
-        0x25 => {
-            // DCR H
-            let diff: u16 = (state.h as u16).wrapping_sub(1);
-            state.h = diff as u8;
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-            state.pc += 1;
-        },
-        0x26 => {
-            // MVI H,D8
-            state.h = byte_2;
-            state.pc += 2;
-        },
-        0x27 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x28 => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x29 => {
-            // DAD H
-            let mut hl = state.get_hl() as u32;
-            hl <<= 1;
-            state.set_carry_flag_double(hl);
-            state.h = (hl >> 8) as u8;
-            state.l = hl as u8;
-            state.pc += 1;
-        },
-        0x2a => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x2b => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x2c => {
-            // INR L
-            let sum: u16 = (state.l as u16) + 1;
-            state.l = sum as u8;
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-            state.pc += 1;
-        },
-        0x2d => {
-            // DCR L
-            let diff: u16 = (state.l as u16).wrapping_sub(1);
-            state.l = diff as u8;
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-            state.pc += 1;
-        },
-        0x2e => {
-            // MVI L,D8
-            state.l = byte_2;
-            state.pc += 2;
-        },
-        0x2f => {
-            // CMA (not)
-            state.a = !state.a;
-            state.pc += 1;
-        },
-        0x30 => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x31 => {
-            // LXI SP,D16
-            state.sp = ((byte_3 as u16) << 8) | (byte_2 as u16);
-            state.pc += 3;
-        },
-        0x32 => {
-            // STA adr
-            // required for space invaders
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            //state.set_mem((byte_2 as u16) << 8 | (byte_3 as u16), state.a);
-        },
-        0x33 => {
-            // INX SP
-            state.sp += 1;
-            state.pc += 1;
-        },
-        0x34 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x35 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x36 => {
-            // MVI M,D8
-            let hl = state.get_hl();
-            state.set_mem(hl, byte_2);
-            state.pc += 2;
-        },
-        0x37 => {
-            // STC
-            state.cc.cy = true;
-            state.pc += 1;
-        },
-        0x38 => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x39 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x3a => {
-            // LDA adr
-            let addr = (byte_2 as u16) << 8 | (byte_3 as u16);
-            let mem = state.get_mem(addr);
-            state.a = mem;
-            state.pc += 3;
-        },
-        0x3b => {
-            // DCX SP
-            state.sp -= 1;
-            state.pc += 1;
-        },
-        0x3c => {
-            // INR A
-            let sum: u16 = (state.a as u16) + 1;
-            state.a = sum as u8;
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            // TODO: handle AC cc
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-            state.pc += 1;
+// assembles a source file, or returns its errors, each with the file's name
+fn assemble_file(path: &str) -> Result<assembler::Assembly, String> {
+    assembler::assemble(&read_text(path)?).map_err(|errors| format!("{}: {}", path, errors.replace('\n', &format!("\n{}: ", path))))
+}
 
-        },
-        0x3d => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x3e => {
-            // MVI A,D8
-            state.a = byte_2;
-            state.pc += 2;
-        },
-        0x3f => {
-            // CMC
-            state.cc.cy = !state.cc.cy;
-            state.pc += 1;
-        },
-        0x40 => {
-            // MOV B,B
-            // TODO: does this actually do anything?
-            state.b = state.b;
-            state.pc += 1;
-        },
-        0x41 => {
-            // MOV B,C
-            state.b = state.c;
-            state.pc += 1;
-        },
-        0x42 => {
-            // MOV B,D
-            state.b = state.d;
-            state.pc += 1;
-        },
-        0x43 => {
-            // MOV B,E
-            state.b = state.e;
-            state.pc += 1;
-        },
-        0x44 => {
-            // MOV B,B
-            state.b = state.h;
-            state.pc += 1;
-        },
-        0x45 => {
-            // MOV B,B
-            state.b = state.l;
-            state.pc += 1;
-        },
-        0x46 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x47 => {
-            // MOV B,A
-            state.b = state.a;
-            state.pc += 1;
-        },
-        0x48 => {
-            // MOV C,B
-            state.c = state.b;
-            state.pc += 1;
-        },
-        0x49 => {
-            // MOV C,C
-            state.c = state.c;
-            state.pc += 1;
-        },
-        0x4a => {
-            // MOV C,D
-            state.c = state.d;
-            state.pc += 1;
-        },
-        0x4b => {
-            // MOV C,E
-            state.c = state.e;
-            state.pc += 1;
-        },
-        0x4c => {
-            // MOV C,H
-            state.c = state.h;
-            state.pc += 1;
-        },
-        0x4d => {
-            // MOV C,L
-            state.c = state.l;
-            state.pc += 1;
-        },
-        0x4e => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x4f => {
-            // MOV C,A
-            state.c = state.a;
-            state.pc += 1;
-        },
-        0x50 => {
-            // MOV D,B
-            state.d = state.b;
-            state.pc += 1;
-        },
-        0x51 => {
-            // MOV D,C
-            state.d = state.c;
-            state.pc += 1;
-        },
-        0x52 => {
-            // MOV D,D
-            state.d = state.d;
-            state.pc += 1;
-        },
-        0x53 => {
-            // MOV D,E
-            state.d = state.e;
-            state.pc += 1;
-        },
-        0x54 => {
-            // MOV D,H
-            state.d = state.h;
-            state.pc += 1;
-        },
-        0x55 => {
-            // MOV D,L
-            state.d = state.l;
-            state.pc += 1;
-        },
-        0x56 => {
-            // MOV D,M
-            let hl = state.get_hl();
-            let m = state.get_mem(hl);
-            state.d = m;
-            state.pc += 1;
-        },
-        0x57 => {
-            // MOV D,A
-            state.d = state.a;
-            state.pc += 1;
-        },
-        0x58 => {
-            // MOV E,B
-            state.e = state.b;
-            state.pc += 1;
-        },
-        0x59 => {
-            // MOV E,C
-            state.e = state.c;
-            state.pc += 1;
-        },
-        0x5a => {
-            // MOV E,D
-            state.e = state.d;
-            state.pc += 1;
-        },
-        0x5b => {
-            // MOV E,E
-            state.e = state.e;
-            state.pc += 1;
-        },
-        0x5c => {
-            // MOV E,H
-            state.e = state.h;
-            state.pc += 1;
-        },
-        0x5d => {
-            // MOV E,L
-            state.e = state.l;
-            state.pc += 1;
-        },
-        0x5e => {
-            // MOV E,M
-            let hl = state.get_hl();
-            let m = state.get_mem(hl);
-            state.e = m;
-            state.pc += 1;
-        },
-        0x5f => {
-            // MOV E,A
-            state.e = state.a;
-            state.pc += 1;
-        },
-        0x60 => {
-            // MOV H,B
-            state.h = state.b;
-            state.pc += 1;
-        },
-        0x61 => {
-            // MOV H,C
-            state.h = state.c;
-            state.pc += 1;
-        },
-        0x62 => {
-            // MOV H,D
-            state.h = state.d;
-            state.pc += 1;
-        },
-        0x63 => {
-            // MOV H,E
-            state.h = state.e;
-            state.pc += 1;
-        },
-        0x64 => {
-            // MOV H,H
-            state.h = state.h;
-            state.pc += 1;
-        },
-        0x65 => {
-            // MOV H,L
-            state.h = state.l;
-            state.pc += 1;
-        },
-        0x66 => {
-            // MOV H,M
-            let hl = state.get_hl();
-            let m = state.get_mem(hl);
-            state.h = m;
-            state.pc += 1;
-        },
-        0x67 => {
-            // MOV H,A
-            state.h = state.a;
-            state.pc += 1;
-        },
-        0x68 => {
-            // MOV L,B
-            state.l = state.b;
-            state.pc += 1;
-        },
-        0x69 => {
-            // MOV L,C
-            state.l = state.c;
-            state.pc += 1;
-        },
-        0x6a => {
-            // MOV L,D
-            state.l = state.d;
-            state.pc += 1;
-        },
-        0x6b => {
-            // MOV L,E
-            state.l = state.e;
-            state.pc += 1;
-        },
-        0x6c => {
-            // MOV L,H
-            state.l = state.h;
-            state.pc += 1;
-        },
-        0x6d => {
-            // MOV L,L
-            state.l = state.l;
-            state.pc += 1;
-        },
-        0x6e => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x6f => {
-            // MOV L,A
-            state.l = state.a;
-            state.pc += 1;
-        },
-        0x70 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x71 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x72 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x73 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x74 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x75 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x76 => {
-            // HLT
-            return;
-        },
-        0x77 => {
-            // MOV M,A
-            let hl = state.get_hl();
-            state.set_mem(hl, state.a);
-            state.pc += 1;
-        },
-        0x78 => {
-            // MOV A,B
-            state.a = state.b;
-            state.pc += 1;
-        },
-        0x79 => {
-            // MOV A,C
-            state.a = state.c;
-            state.pc += 1;
-        },
-        0x7a => {
-            // MOV A,D
-            state.a = state.d;
-            state.pc += 1;
-        },
-        0x7b => {
-            // MOV A,E
-            state.a = state.e;
-            state.pc += 1;
-        },
-        0x7c => {
-            // MOV A,H
-            state.a = state.h;
-            state.pc += 1;
-        },
-        0x7d => {
-            // MOV A,L
-            state.a = state.l;
-            state.pc += 1;
-        },
-        0x7e => {
-            // MOV A,M
-            let hl = state.get_hl();
-            state.set_mem(hl, state.a);
-            state.pc += 1;
-        },
-        0x7f => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x80 => {
-            // ADD B
+fn load_program(path: &str, format: Format, origin: Option<u16>) -> Result<Program, String> {
+    let program = match format {
+        Format::Asm => return Ok(assemble_file(path)?.program),
+        Format::IntelHex => loader::intel_hex(&read_text(path)?),
+        Format::Hexdump => loader::hexdump(&read_text(path)?),
+        Format::Binary => loader::binary(&read(path)?, origin.unwrap_or(0)),
+        Format::Com => loader::binary(&read(path)?, origin.unwrap_or(0x0100)),
+    };
+    program.map_err(|e| format!("cannot load {}: {}", path, e))
+}
 
-            // a and b are u8, but we need to capture the carry-out, so we use u16
-            let sum: u16 = add(state.a, state.b);
+#[derive(Clone, Copy, PartialEq)]
+enum MachineType {
+    Bare,
+    Invaders,
+    Cpm,
+}
 
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
+// a program loaded into a cpu, ready to run from its entry point
+struct Loaded {
+    program: Program,
+    state: State8080,
+    machine: MachineType,
+}
 
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
+// loads the program with the LOAD_OPTIONS. a CP/M program is set up to run as a .COM file.
+fn load(command: &Command, parsed: &Parsed) -> Result<Loaded, String> {
+    let path = parsed.file(command)?;
+    let format = format(parsed, path)?;
+    let origin = parsed.value("--origin").map(parse_address).transpose()?;
+    let program = load_program(path, format, origin)?;
+    let machine = match parsed.value("--machine") {
+        Some("bare") => MachineType::Bare,
+        Some("invaders") => MachineType::Invaders,
+        Some("cpm") => MachineType::Cpm,
+        Some(other) => return Err(format!("'{}' isn't a machine: the machines are bare, invaders and cpm", other)),
+        None if format == Format::Com => MachineType::Cpm,
+        None => MachineType::Bare,
+    };
 
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x81 => {
-            // ADD C
+    let mut state = State8080::new();
+    if machine == MachineType::Cpm {
+        if program.origin != 0x0100 {
+            return Err(format!("a CP/M program has to start at 0100h, not {:04x}h", program.origin));
+        }
+        cpm::load_com(&mut state, &program.bytes)?;
+    } else {
+        program.load(&mut state);
+        state.pc = program.origin;
+    }
+    if let Some(entry) = parsed.value("--entry") {
+        state.pc = parse_address(entry)?;
+    }
+    Ok(Loaded { program, state, machine })
+}
 
-            let sum: u16 = add(state.a, state.c);
+fn trace_file(path: &str) -> Result<Box<dyn std::io::Write>, String> {
+    let file = std::fs::File::create(path).map_err(|e| format!("cannot create {}: {}", path, e))?;
+    Ok(Box::new(std::io::BufWriter::new(file)))
+}
 
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
+// runs a CP/M .COM program, such as one of the cpu diagnostics
+fn run_com(path: &str) -> Result<(), String> {
+    let program = read(path)?;
+    let stdout = std::io::stdout();
+    cpm::run_com(&program, &mut stdout.lock())
+}
 
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
+// boots CP/M from the disk images, which are given in drive order starting with A.
+// each image is an 8-inch IBM 3740 disk, unless it is preceded by --dpb with the name or fields of another format.
+// --host followed by a directory adds a drive backed by the files in that directory.
+fn run_cpm_system(args: &[String]) -> Result<(), String> {
+    let mut drives: Vec<cpm::system::Drive> = Vec::new();
+    let mut system: Option<Vec<u8>> = None;
+    let mut params = cpm::disk::DiskParams::ibm_3740();
 
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x82 => {
-            // ADD D
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--system" if i + 1 < args.len() => {
+                system = Some(read(&args[i + 1])?);
+                i += 1;
+            },
+            "--dpb" if i + 1 < args.len() => {
+                params = cpm::disk::DiskParams::parse(&args[i + 1]).map_err(|e| format!("invalid disk format: {}", e))?;
+                i += 1;
+            },
+            "--host" if i + 1 < args.len() => {
+                let host = cpm::hostdir::HostDrive::new(&args[i + 1]).map_err(|e| format!("cannot use {}: {}", args[i + 1], e))?;
+                drives.push(cpm::system::Drive::Host(host));
+                i += 1;
+            },
+            path => {
+                let disk = cpm::disk::Disk::open(path, params).map_err(|e| format!("cannot open {}: {}", path, e))?;
+                drives.push(cpm::system::Drive::Image(disk));
+                params = cpm::disk::DiskParams::ibm_3740();
+            },
+        }
+        i += 1;
+    }
+    if drives.is_empty() {
+        return Err("cpm needs a disk image, or a --host directory".to_string());
+    }
 
-            let sum: u16 = add(state.a, state.d);
+    let mut cpm = cpm::system::CpmSystem::new(drives, system).map_err(|e| format!("cannot start CP/M: {}", e))?;
+    cpm.run().map_err(|e| format!("cannot write to stdout: {}", e))
+}
 
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
+// assembles a source file into a binary or Intel HEX file, with a listing and symbol file beside it
+fn asm(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    let source = parsed.file(command)?;
+    let assembly = assemble_file(source)?;
+    let output = parsed.value("-o").map(PathBuf::from).unwrap_or_else(|| Path::new(source).with_extension("bin"));
+    let output_name = output.display().to_string();
+    if output.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("hex")) {
+        write(&output_name, loader::write_intel_hex(&assembly.program))?;
+    } else {
+        write(&output_name, &assembly.program.bytes)?;
+    }
+    write(&output.with_extension("lst").display().to_string(), &assembly.listing)?;
+    write(&output.with_extension("sym").display().to_string(), assembly.symbol_file())?;
 
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
+    if parsed.flag("--cpm") {
+        if assembly.program.origin != 0x0100 {
+            return Err(format!("a CP/M program has to start at 0100h, not {:04x}h", assembly.program.origin));
+        }
+        let stdout = std::io::stdout();
+        cpm::run_com(&assembly.program.bytes, &mut stdout.lock())?;
+    }
+    Ok(())
+}
 
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x83 => {
-            // ADD E
+// loads a program into the debugger, and reads its commands from stdin
+fn debug(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    let loaded = load(command, parsed)?;
+    match loaded.machine {
+        MachineType::Bare => debug_in(Debugger::from_state(loaded.state, Bare), parsed),
+        MachineType::Invaders => {
+            let mut debugger = Debugger::from_state(loaded.state, Invaders::default());
+            debugger.frame_interrupts = true;
+            debug_in(debugger, parsed)
+        },
+        MachineType::Cpm => Err("the debugger can't do CP/M's BDOS calls, so CP/M programs can only be run with run or test".to_string()),
+    }
+}
 
-            let sum: u16 = add(state.a, state.e);
+fn debug_in<M: Machine>(mut debugger: Debugger<M>, parsed: &Parsed) -> Result<(), String> {
+    if let Some(path) = parsed.value("--state") {
+        debugger.load_state(&read(path)?).map_err(|e| format!("cannot load {}: {}", path, e))?;
+    }
+    if let Some(path) = parsed.value("--trace") {
+        debugger.tracer.set_output(trace_file(path)?);
+    }
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    debugger.run(stdin.lock(), &mut stdout.lock()).map_err(|e| format!("cannot write to stdout: {}", e))
+}
 
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
+// loads a program and waits for gdb to connect to it on localhost
+fn gdb_server(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    let port = match parsed.value("--port") {
+        Some(port) => port.parse::<u16>().map_err(|_| format!("'{}' isn't a port", port))?,
+        None => 1234,
+    };
+    let loaded = load(command, parsed)?;
+    let address = format!("127.0.0.1:{}", port);
+    println!("Waiting for gdb on localhost:{}.", port);
+    let result = match loaded.machine {
+        MachineType::Bare => gdb::listen(&mut Debugger::from_state(loaded.state, Bare), &address),
+        MachineType::Invaders => {
+            let mut debugger = Debugger::from_state(loaded.state, Invaders::default());
+            debugger.frame_interrupts = true;
+            gdb::listen(&mut debugger, &address)
+        },
+        MachineType::Cpm => return Err("gdb can't debug CP/M programs, whose BDOS calls aren't done in the debugger".to_string()),
+    };
+    result.map_err(|e| format!("cannot talk to gdb: {}", e))
+}
 
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
+// lists the instructions in a ROM, from start to end, or over the whole ROM if they aren't given.
+// with --trace, it instead follows the code from the entry points and writes an .asm listing of the whole ROM.
+fn disasm(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    let Some(path) = parsed.files.first() else {
+        return Err(format!("{} needs the name of a ROM file", command.name));
+    };
+    if parsed.files.len() > 3 {
+        return Err(format!("disasm takes a file and at most a start and end address, but was given {}", parsed.files.join(" ")));
+    }
+    let origin = parsed.value("--origin").map(parse_address).transpose()?;
+    let program = load_program(path, format(parsed, path)?, origin)?;
+    if parsed.flag("--trace") {
+        let mut entries: Vec<u16> = parsed.all("--entry").into_iter().map(parse_address).collect::<Result<_, _>>()?;
+        if entries.is_empty() {
+            entries = listing::default_entries(&program);
+        }
+        print!("{}", listing::listing(&program, &entries));
+        return Ok(());
+    }
+    let state = &mut State8080::new();
+    program.load(state);
+
+    let first = parsed.files.get(1).map(|s| parse_address(s)).transpose()?.unwrap_or(program.origin);
+    let end = (program.origin as usize + program.bytes.len()).saturating_sub(1) as u16;
+    let last = parsed.files.get(2).map(|s| parse_address(s)).transpose()?.unwrap_or(end);
+    for instruction in disassembler::disassemble_range(&state.memory[..], first, last) {
+        let bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        println!("{:04x}  {:<8}  {}", instruction.addr, bytes.join(" "), instruction);
+    }
+    Ok(())
+}
 
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x84 => {
-            // ADD H
+// plays the input script on the ROM, and writes the movie of the game it made
+fn record(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    let [rom, path] = parsed.files.as_slice() else {
+        return Err(format!("{} needs the names of a ROM and an input script", command.name));
+    };
+    let script = movie::script(&read_text(path)?).map_err(|e| format!("{}: {}", path, e))?;
+    let output = parsed.value("-o").map(PathBuf::from).unwrap_or_else(|| Path::new(path).with_extension("movie"));
+    let output_name = output.display().to_string();
+    let program = load_program(rom, Format::Binary, None)?;
+    let state = &mut State8080::new();
+    program.load(state);
+    let mut machine = Invaders::default();
+    let mut movie = Movie::new(&program.bytes, machine.dips);
+    movie.record_script(&script, state, &mut machine);
+    write(&output_name, movie.to_bytes())?;
+    println!("Recorded {} frames to {}.", movie.frames.len(), output_name);
+    Ok(())
+}
 
-            let sum: u16 = add(state.a, state.h);
+// plays a movie of a space invaders game on the ROM, and says whether it went the way it did when it was recorded
+fn replay(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    let [rom, path] = parsed.files.as_slice() else {
+        return Err(format!("{} needs the names of a ROM and a movie", command.name));
+    };
+    let movie = Movie::read(&read(path)?).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let state = &mut State8080::new();
+    load_program(rom, Format::Binary, None)?.load(state);
+    let mut machine = Invaders::default();
+    match movie.replay(state, &mut machine) {
+        Ok(None) => println!("The replay matches the recording for all {} frames.", movie.frames.len()),
+        Ok(Some(frame)) => {
+            println!("The replay diverges from the recording at frame {}.", frame);
+            std::process::exit(1);
+        },
+        Err(e) => return Err(format!("cannot replay {}: {}", path, e)),
+    }
+    Ok(())
+}
 
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
+// the most cycles a hexdump given on its own runs for, about a minute of the real cpu, so that one that never halts
+// (a game, waiting for interrupts that never come) still ends. run takes a limit of its own. the help says it too.
+const HEXDUMP_CYCLES: u64 = 120_000_000;
+
+// runs a hexdump program until it halts, or for HEXDUMP_CYCLES, and prints how it ended
+fn emulate_all(path: &str) -> Result<(), String> {
+    let text = read_text(path)?;
+    let program = loader::hexdump(&text).map_err(|e| format!("{} isn't a hexdump: {}", path, e))?;
+    let state = &mut State8080::new();
+    program.load(state);
+    let mut runner = Runner::new();
+    runner.until.push(Until::Halt);
+    runner.cycles = Some(HEXDUMP_CYCLES);
+    finish(&mut runner, state, &mut Bare, None, None, None)
+}
 
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
+// runs a program with no one watching until it meets a condition or reaches a limit, then writes a summary of the run
+// as JSON, and exits with 0 if it passed or 1 if it didn't
+fn run(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    let mut runner = Runner::new();
+    runner.cycles = parsed.value("--cycles").map(parse_count).transpose()?;
+    runner.frames = parsed.value("--frames").map(parse_count).transpose()?;
+    for addr in parsed.all("--until-pc") {
+        runner.until.push(Until::Pc(parse_address(addr)?));
+    }
+    for condition in parsed.all("--until-mem") {
+        let Some((addr, byte)) = condition.split_once('=') else {
+            return Err(format!("'{}' isn't a memory condition, which is written <address>=<byte>", condition));
+        };
+        runner.until.push(Until::Byte(parse_address(addr)?, parse_byte(byte)?));
+    }
+    if parsed.flag("--until-halt") {
+        runner.until.push(Until::Halt);
+    }
+    for text in parsed.all("--until-output") {
+        runner.until.push(Until::Output(text.to_string()));
+    }
+    runner.console = parsed.value("--console").map(parse_byte).transpose()?;
+    if let Some(path) = parsed.value("--trace") {
+        runner.tracer.set_output(trace_file(path)?);
+    }
+    if let Some(speed) = parsed.value("--speed") {
+        runner.throttle.set_speed(parse_speed(speed)?);
+    }
+    if parsed.flag("--show-speed") {
+        runner.speed_log = Some(Box::new(std::io::stderr()));
+    }
+    let rewind = parsed.value("--rewind").map(parse_count).transpose()?;
+    if let Some(frames) = rewind {
+        // and one for the end of the frame the run ends in
+        runner.rewind = Some(Rewind::new(frames as usize + 1, 1));
+    }
 
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x85 => {
-            // ADD L
+    let mut loaded = load(command, parsed)?;
+    runner.cpm = loaded.machine == MachineType::Cpm;
+    runner.frame_interrupts = loaded.machine == MachineType::Invaders;
+    if loaded.machine == MachineType::Invaders {
+        start(&mut runner, &mut loaded.state, &mut Invaders::default(), parsed, rewind)
+    } else {
+        start(&mut runner, &mut loaded.state, &mut Bare, parsed, rewind)
+    }
+}
 
-            let sum: u16 = add(state.a, state.l);
+// carries on from the save state, if there is one, then runs
+fn start<M: Machine>(runner: &mut Runner, state: &mut State8080, machine: &mut M, parsed: &Parsed, rewind: Option<u64>) -> Result<(), String> {
+    if let Some(path) = parsed.value("--state") {
+        runner.tracer.cycle = savestate::load(&read(path)?, state, machine).map_err(|e| format!("cannot load {}: {}", path, e))?;
+    }
+    finish(runner, state, machine, parsed.value("--summary"), parsed.value("--save"), rewind)
+}
 
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
+// runs the program, goes back the frames to rewind, writes the summary to the file (or else stdout), and exits with
+// whether it passed
+fn finish<M: Machine>(
+    runner: &mut Runner,
+    state: &mut State8080,
+    machine: &mut M,
+    summary: Option<&str>,
+    save: Option<&str>,
+    rewind: Option<u64>,
+) -> Result<(), String> {
+    // the program's output goes to stdout as it runs, unless the summary is going there
+    if summary.is_some() {
+        runner.echo = Some(Box::new(std::io::stdout()));
+    }
+    let exit = runner.run(state, machine).map_err(|e| format!("cannot write the trace: {}", e))?;
+    if let Some(frames) = rewind {
+        runner.back(state, machine, frames);
+    }
+    if let Some(path) = save {
+        write(path, savestate::save(state, runner.tracer.cycle, machine))?;
+    }
+    let json = runner.summary(state, &exit);
+    match summary {
+        Some(path) => write(path, json)?,
+        None => print!("{}", json),
+    }
+    if !runner.passed(&exit) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
 
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
+// whether the output of a cpu test program says it passed
+fn test_passed(output: &str, expect: Option<&str>) -> bool {
+    if let Some(expect) = expect {
+        return output.contains(expect);
+    }
+    let output = output.to_uppercase();
+    let passed = output.contains("OPERATIONAL") || output.contains("COMPLETE") || output.contains("TESTS OK");
+    passed && !output.contains("ERROR") && !output.contains("FAIL")
+}
 
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x86 => {
-            // ADD M
-            let hl: u16 = state.get_hl();
-            let m: u8 = state.get_mem(hl);
-            let sum: u16 = add(state.a, m);
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x87 => {
-            // ADD A
-
-            let sum: u16 = add(state.a, state.a);
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x88 => {
-            // ADC B
-
-            let sum: u16 = add(state.a, state.b) + state.cc.cy as u16;
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x89 => {
-            // ADC C
-
-            let sum: u16 = add(state.a, state.c) + state.cc.cy as u16;
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x8a => {
-            // ADC D
-
-            let sum: u16 = add(state.a, state.d) + state.cc.cy as u16;
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x8b => {
-            // ADC E
-
-            let sum: u16 = add(state.a, state.e) + state.cc.cy as u16;
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x8c => {
-            // ADC H
-
-            let sum: u16 = add(state.a, state.h) + state.cc.cy as u16;
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x8d => {
-            // ADC L
-
-            let sum: u16 = add(state.a, state.l) + state.cc.cy as u16;
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x8e => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x8f => {
-            // ADC A
-
-            let sum: u16 = add(state.a, state.a) + state.cc.cy as u16;
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x90 => {
-            // SUB B
-
-            let sum: u16 = add(state.a, 0 - state.b);
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x91 => {
-            // SUB C
-
-            let sum: u16 = add(state.a, 0 - state.c);
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x92 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x93 => {
-            // SUB E
-
-            let sum: u16 = add(state.a, 0 - state.e);
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x94 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x95 => {
-            // SUB L
-
-            let sum: u16 = add(state.a, 0 - state.l);
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x96 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x97 => {
-            // SUB A
-            // TODO: optimize this
-            let diff = (state.a as u16) - (state.a as u16);
-
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            state.a = (diff as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x98 => {
-            // SBB B
-            let diff = (state.a as u16) - (state.b as u16) - (state.cc.cy as u16);
-
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            state.a = (diff as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x99 => {
-            // SBB C
-            let diff = (state.a as u16) - (state.c as u16) - (state.cc.cy as u16);
-
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            state.a = (diff as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x9a => {
-            // SBB D
-            let diff = (state.a as u16) - (state.d as u16) - (state.cc.cy as u16);
-
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            state.a = (diff as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x9b => {
-            // SBB E
-            let diff = (state.a as u16) - (state.e as u16) - (state.cc.cy as u16);
-
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            state.a = (diff as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x9c => {
-            // SBB H
-            let diff = (state.a as u16) - (state.h as u16) - (state.cc.cy as u16);
-
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            state.a = (diff as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x9d => {
-            // SBB L
-            let diff = (state.a as u16) - (state.l as u16) - (state.cc.cy as u16);
-
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            state.a = (diff as u8) & 0xff;
-            state.pc += 1;
-        },
-        0x9e => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0x9f => {
-            // SBB A
-            // TODO: optimize this
-            let diff = (state.a as u16) - (state.c as u16) - (state.cc.cy as u16);
-
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            state.a = (diff as u8) & 0xff;
-            state.pc += 1;
-        },
-        0xa0 => {
-            // ANA B
-            let and = (state.a as u16) & (state.b as u16);
-            state.set_zero_flag(and);
-            state.set_sign_flag(and);
-            state.set_carry_flag(and);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(and & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = and as u8;
-            state.pc += 1;
-        },
-        0xa1 => {
-            // ANA C
-            let and = (state.a as u16) & (state.c as u16);
-            state.set_zero_flag(and);
-            state.set_sign_flag(and);
-            state.set_carry_flag(and);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(and & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = and as u8;
-            state.pc += 1;
-        },
-        0xa2 => {
-            // ANA D
-            let and = (state.a as u16) & (state.d as u16);
-            state.set_zero_flag(and);
-            state.set_sign_flag(and);
-            state.set_carry_flag(and);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(and & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = and as u8;
-            state.pc += 1;
-        },
-        0xa3 => {
-            // ANA E
-            let and = (state.a as u16) & (state.e as u16);
-            state.set_zero_flag(and);
-            state.set_sign_flag(and);
-            state.set_carry_flag(and);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(and & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = and as u8;
-            state.pc += 1;
-        },
-        0xa4 => {
-            // ANA H
-            let and = (state.a as u16) & (state.h as u16);
-            state.set_zero_flag(and);
-            state.set_sign_flag(and);
-            state.set_carry_flag(and);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(and & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = and as u8;
-            state.pc += 1;
-        },
-        0xa5 => {
-            // ANA L
-            let and = (state.a as u16) & (state.l as u16);
-            state.set_zero_flag(and);
-            state.set_sign_flag(and);
-            state.set_carry_flag(and);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(and & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = and as u8;
-            state.pc += 1;
-        },
-        0xa6 => {
-            // ANA M
-            let hl = state.get_hl();
-            let m = state.get_mem(hl);
-            let and = (state.a as u16) & (m as u16);
-            state.set_zero_flag(and);
-            state.set_sign_flag(and);
-            state.set_carry_flag(and);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(and & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = and as u8;
-            state.pc += 1;
-        },
-        0xa7 => {
-            // ANA A
-            // TODO: optimize this?
-            let and = (state.a as u16) & (state.a as u16);
-            state.set_zero_flag(and);
-            state.set_sign_flag(and);
-            state.set_carry_flag(and);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(and & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = and as u8;
-            state.pc += 1;
-        },
-        0xa8 => {
-            // XRA B
-            // a and b are u8, but we need to capture the carry-out, so we use u16
-            let xor: u16 = (state.a as u16) ^ (state.b as u16);
-
-            state.set_zero_flag(xor);
-            state.set_sign_flag(xor);
-            state.set_carry_flag(xor);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(xor & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = xor as u8;
-            state.pc += 1;
-        },
-        0xa9 => {
-            // XRA C
-            // a and c are u8, but we need to capture the carry-out, so we use u16
-            let xor: u16 = (state.a as u16) ^ (state.c as u16);
-
-            state.set_zero_flag(xor);
-            state.set_sign_flag(xor);
-            state.set_carry_flag(xor);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(xor & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = xor as u8;
-            state.pc += 1;
-        },
-        0xaa => {
-            // XRA D
-
-            let xor: u16 = (state.a as u16) ^ (state.d as u16);
-
-            state.set_zero_flag(xor);
-            state.set_sign_flag(xor);
-            state.set_carry_flag(xor);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(xor & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = xor as u8;
-            state.pc += 1;
-        },
-        0xab => {
-            // XRA E
-
-            let xor: u16 = (state.a as u16) ^ (state.e as u16);
-
-            state.set_zero_flag(xor);
-            state.set_sign_flag(xor);
-            state.set_carry_flag(xor);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(xor & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = xor as u8;
-            state.pc += 1;
-        },
-        0xac => {
-            // XRA H
-
-            let xor: u16 = (state.a as u16) ^ (state.h as u16);
-
-            state.set_zero_flag(xor);
-            state.set_sign_flag(xor);
-            state.set_carry_flag(xor);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(xor & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = xor as u8;
-            state.pc += 1;
-        },
-        0xad => {
-            // XRA L
-
-            let xor: u16 = (state.a as u16) ^ (state.l as u16);
-
-            state.set_zero_flag(xor);
-            state.set_sign_flag(xor);
-            state.set_carry_flag(xor);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(xor & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = xor as u8;
-            state.pc += 1;
-        },
-        0xae => {
-            // XRA M
-            let hl: u16 = state.get_hl();
-            let m: u8 = state.get_mem(hl);
-
-            let xor: u16 = (state.a as u16) ^ (m as u16);
-
-            state.set_zero_flag(xor);
-            state.set_sign_flag(xor);
-            state.set_carry_flag(xor);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(xor & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = xor as u8;
-            state.pc += 1;
-        },
-        0xaf => {
-            // XRA A
-            // TODO: can we just optimize this to set a to 0, and set flags?
-            let xor: u16 = (state.a as u16) ^ (state.a as u16);
-
-            state.set_zero_flag(xor);
-            state.set_sign_flag(xor);
-            state.set_carry_flag(xor);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(xor & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = xor as u8;
-            state.pc += 1;
-        },
-        0xb0 => {
-            // ORA B
-            let or: u16 = (state.a as u16) | (state.b as u16);
-            state.set_zero_flag(or);
-            state.set_sign_flag(or);
-            state.set_carry_flag(or);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(or & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = or as u8;
-            state.pc += 1;
-        },
-        0xb1 => {
-            // ORA C
-            let or: u16 = (state.a as u16) | (state.c as u16);
-            state.set_zero_flag(or);
-            state.set_sign_flag(or);
-            state.set_carry_flag(or);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(or & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = or as u8;
-            state.pc += 1;
-        },
-        0xb2 => {
-            // ORA D
-            let or: u16 = (state.a as u16) | (state.d as u16);
-            state.set_zero_flag(or);
-            state.set_sign_flag(or);
-            state.set_carry_flag(or);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(or & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = or as u8;
-            state.pc += 1;
-        },
-        0xb3 => {
-            // ORA E
-            let or: u16 = (state.a as u16) | (state.e as u16);
-            state.set_zero_flag(or);
-            state.set_sign_flag(or);
-            state.set_carry_flag(or);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(or & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = or as u8;
-            state.pc += 1;
-        },
-        0xb4 => {
-            // ORA H
-            let or: u16 = (state.a as u16) | (state.h as u16);
-            state.set_zero_flag(or);
-            state.set_sign_flag(or);
-            state.set_carry_flag(or);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(or & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = or as u8;
-            state.pc += 1;
-        },
-        0xb5 => {
-            // ORA L
-            let or: u16 = (state.a as u16) | (state.l as u16);
-            state.set_zero_flag(or);
-            state.set_sign_flag(or);
-            state.set_carry_flag(or);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(or & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = or as u8;
-            state.pc += 1;
-        },
-        0xb6 => {
-            // ORA M
-            let hl = state.get_hl();
-            let m = state.get_mem(hl);
-
-            let or: u16 = (state.a as u16) | (m as u16);
-            state.set_zero_flag(or);
-            state.set_sign_flag(or);
-            state.set_carry_flag(or);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(or & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = or as u8;
-            state.pc += 1;
-        },
-        0xb7 => {
-            // ORA A
-            // TODO: can this be optimized to just not modify a and set the flags?
-            let or: u16 = (state.a as u16) | (state.a as u16);
-            state.set_zero_flag(or);
-            state.set_sign_flag(or);
-            state.set_carry_flag(or);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(or & 0xff);
-
-            // TODO: handle AC cc
-
-            state.a = or as u8;
-            state.pc += 1;
-        },
-        0xb8 => {
-            // CMP B
-            let diff: u16 = (state.a as u16) - (state.b as u16);
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            // TODO: handle AC cc
-            state.pc += 1;
-        },
-        0xb9 => {
-            // CMP C
-            let diff: u16 = (state.a as u16) - (state.c as u16);
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            // TODO: handle AC cc
-            state.pc += 1;
-        },
-        0xba => {
-            // CMP D
-            let diff: u16 = (state.a as u16) - (state.d as u16);
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            // TODO: handle AC cc
-            state.pc += 1;
-        },
-        0xbb => {
-            // CMP E
-            let diff: u16 = (state.a as u16) - (state.e as u16);
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            // TODO: handle AC cc
-            state.pc += 1;
-        },
-        0xbc => {
-            // CMP H
-            let diff: u16 = (state.a as u16) - (state.h as u16);
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            // TODO: handle AC cc
-            state.pc += 1;
-        },
-        0xbd => {
-            // CMP L
-            let diff: u16 = (state.a as u16) - (state.l as u16);
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            // TODO: handle AC cc
-            state.pc += 1;
-        },
-        0xbe => {
-            // CMP M
-            let hl = state.get_hl();
-            let m = state.get_mem(hl);
-
-            let diff: u16 = (state.a as u16) - (m as u16);
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            // TODO: handle AC cc
-            state.pc += 1;
-        },
-        0xbf => {
-            // CMP A
-            // TODO: optimize this?
-            let diff: u16 = (state.a as u16) - (state.a as u16);
-            state.set_zero_flag(diff);
-            state.set_sign_flag(diff);
-            state.set_carry_flag(diff);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(diff & 0xff);
-
-            // TODO: handle AC cc
-            state.pc += 1;
-        },
-        0xc0 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xc1 => {
-            // POP B
-            state.c = state.get_mem(state.sp);
-            state.b = state.get_mem(state.sp + 1);
-            state.sp += 2;
-            state.pc += 1;
-        },
-        0xc2 => {
-            // JNZ address
-            if state.cc.z == false {
-                // take the branch and set the PC accordingly
-                state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
-            } else {
-                // skip to the next instruction if the branch isn't taken
-                state.pc += 3;
-            }
-        },
-        0xc3 => {
-            // JMP address
-            state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
-        },
-        0xc4 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 3;
-        },
-        0xc5 => {
-            // PUSH B
-            state.set_mem(state.sp - 1, state.b);
-            state.set_mem(state.sp - 2, state.c);
-            state.sp -= 2;
-            state.pc += 1;
-        },
-        0xc6 => {
-            // ADI byte
-
-            // a and b are u8, but we need to capture the carry-out, so we use u16
-            let sum: u16 = add(state.a, byte_2);
-
-            state.set_zero_flag(sum);
-            state.set_sign_flag(sum);
-            state.set_carry_flag(sum);
-
-            // parity flag
-            state.cc.p = false; // (dummy parity)(sum & 0xff);
-
-            state.a = (sum as u8) & 0xff;
-            state.pc += 2;
-        },
-        0xc7 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xc8 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xc9 => {
-            // RET
-            state.pc = state.get_mem(state.pc) as u16 | (state.get_mem(state.sp + 1) as u16) << 8;
-            state.sp += 2;
-        },
-        0xca => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xcb => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xcc => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xcd => {
-            // CALL address
-            state.set_mem(state.sp - 1, (state.pc >> 8) as u8);
-            state.set_mem(state.sp - 2, state.pc as u8);
-            state.sp -= 2;
-            state.pc = ((byte_3 as u16) << 8) | byte_2 as u16;
-        },
-        0xce => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xcf => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xd0 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xd1 => {
-            // POP D
-            state.e = state.get_mem(state.sp);
-            state.d = state.get_mem(state.sp + 1);
-            state.sp += 2;
-            state.pc += 1;
-        },
-        0xd2 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xd3 => {
-            // TODO: necessary for space invaders
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xd4 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xd5 => {
-            // PUSH D
-            state.set_mem(state.sp - 1, state.d);
-            state.set_mem(state.sp - 2, state.e);
-            state.sp -= 2;
-            state.pc += 1;
-        },
-        0xd6 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xd7 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xd8 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xd9 => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xda => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xdb => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xdc => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xdd => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xde => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xdf => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xe0 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xe1 => {
-            // POP H
-            state.l = state.get_mem(state.sp);
-            state.h = state.get_mem(state.sp + 1);
-            state.sp += 2;
-            state.pc += 1;
-        },
-        0xe2 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xe3 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xe4 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xe5 => {
-            // PUSH H
-            state.set_mem(state.sp - 2, state.l);
-            state.set_mem(state.sp - 1, state.h);
-            state.sp -= 2;
-            state.pc += 1;
-        },
-        0xe6 => {
-            // ANI byte
-            let x: u8 = state.a & byte_2;
-            state.cc.z = x == 0;
-            state.cc.s = 0x80 == (x & 0x80);
-            state.cc.p = false; // (dummy parity)(x as u16);
-            state.cc.cy = false;
-            state.a = x;
-            state.pc += 2;
-        },
-        0xe7 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xe8 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xe9 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xea => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xeb => {
-            // XCHG
-            let temp = state.h;
-            state.h = state.d;
-            state.d = temp;
-
-            let temp = state.l;
-            state.l = state.e;
-            state.e = temp;
-            state.pc += 1;
-        },
-        0xec => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xed => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xee => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xef => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xf0 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xf1 => {
-            // POP PSW
-            state.a = state.get_mem(state.sp + 1);
-            let psw: u8 = state.get_mem(state.sp);
-            state.cc.z = 0x01 == psw & 0x01;
-            state.cc.s = 0x02 == psw & 0x02;
-            state.cc.p = 0x04 == psw & 0x04;
-            state.cc.cy = 0x05 == psw & 0x08;
-            state.cc.ac = 0x10 == psw & 0x10;
-            state.sp += 2;
-            state.pc += 1;
-        },
-        0xf2 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xf3 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xf4 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xf5 => {
-            // PUSH PSW
-            state.set_mem(state.sp - 1, state.a);
-            let psw: u8 = state.cc.z as u8 |
-                          (state.cc.s as u8) << 1 |
-                          (state.cc.p as u8) << 2 |
-                          (state.cc.cy as u8) << 3 |
-                          (state.cc.ac as u8) << 4;
-            state.set_mem(state.sp - 2, psw);
-            state.sp -= 2;
-            state.pc += 1;
-        },
-        0xf6 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xf7 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xf8 => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xf9 => {
-            // SPHL
-            state.sp = state.get_hl();
-            state.pc += 1;
-        },
-        0xfa => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xfb => {
-            // TODO: necessary for space invaders
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xfc => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xfd => {
-            // -
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
-        0xfe => {
-            let x: u8 = state.a - byte_2;
-            state.cc.z = x == 0;
-            state.cc.s = 0x80 == (x & 0x80);
-            state.cc.p = false; // (dummy parity)(x as u16);
-            state.cc.cy = state.a < byte_2;
-            state.pc += 2;
-        },
-        0xff => {
-            println!("unimplemented instruction: {:x}", opcode);
-            return;
-            state.pc += 1;
-        },
+// runs each cpu test program as a CP/M program, and says whether it passed
+fn test(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    if parsed.files.is_empty() {
+        return Err(format!("{} needs the names of the test programs", command.name));
     }
-}
-
-
-fn parity(bitstring: u16) -> bool {
-    let mut counter: u8 = 0;
-    let mut one: u16 = 1;
-    println!("bitstring is {}", bitstring);
-
-    while one <= 0b1000000000000000 {
-        if one & bitstring != 0 {
-            counter += 1;
+    let cycles = parsed.value("--cycles").map(parse_count).transpose()?;
+    let mut tracer = parsed.value("--trace").map(trace_file).transpose()?;
+    let mut failed = 0;
+    for path in &parsed.files {
+        let program = read(path)?;
+        let mut state = State8080::new();
+        cpm::load_com(&mut state, &program).map_err(|e| format!("{}: {}", path, e))?;
+        let mut runner = Runner::new();
+        runner.cpm = true;
+        runner.cycles = cycles;
+        if let Some(out) = tracer.take() {
+            // the trace is of the first program
+            runner.tracer.set_output(out);
+        }
+        let exit = runner.run(&mut state, &mut Bare).map_err(|e| format!("cannot write the trace: {}", e))?;
+        let output = String::from_utf8_lossy(&runner.output).into_owned();
+        let passed = exit == Exit::Exited && test_passed(&output, parsed.value("--expect"));
+        let name = Path::new(path).file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or(path.clone());
+        println!(
+            "{}: {} ({} instructions, {} cycles, {})",
+            name,
+            if passed { "passed" } else { "FAILED" },
+            runner.instructions,
+            runner.tracer.cycle,
+            exit.reason()
+        );
+        if !passed || parsed.flag("--verbose") {
+            println!("{}", output.trim_end());
+        }
+        if !passed {
+            failed += 1;
         }
-        one = one << 1;
     }
-
-    return counter % 2 == 0;
+    if failed > 0 {
+        println!("{} of {} failed", failed, parsed.files.len());
+        std::process::exit(1);
+    }
+    Ok(())
 }
 
+// describes a save state, a movie, or a program
+fn info(command: &Command, parsed: &Parsed) -> Result<(), String> {
+    let path = parsed.file(command)?;
+    let bytes = read(path)?;
+    if bytes.starts_with(savestate::MAGIC) {
+        let saved = savestate::read(&bytes).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        println!("save state, version {}", u16::from_le_bytes([bytes[8], bytes[9]]));
+        println!("cycle      {}", saved.cycle);
+        println!("cpu        {}", saved.state.registers());
+        println!("devices    {} bytes", saved.devices.len());
+        return Ok(());
+    }
+    if bytes.starts_with(movie::MAGIC) {
+        let movie = Movie::read(&bytes).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        let roms: Vec<String> = movie.roms.iter().map(|crc| format!("{:08x}", crc)).collect();
+        println!("space invaders movie, version {}", u16::from_le_bytes([bytes[8], bytes[9]]));
+        println!("frames     {} ({:.1} seconds)", movie.frames.len(), movie.frames.len() as f64 / 60.0);
+        println!("ROM        {} bytes, crc32 of each 2K: {}", movie.rom_length, roms.join(" "));
+        println!("dips       {:?}", movie.dips);
+        return Ok(());
+    }
 
-// adds u8 values, and returns the sum as a u16
-fn add(a: u8, b: u8) -> u16 {
-    return (a as u16) + (b as u16);
+    let loaded = load(command, parsed)?;
+    let program = &loaded.program;
+    let end = program.origin as usize + program.bytes.len();
+    let machine = match loaded.machine {
+        MachineType::Bare => "bare",
+        MachineType::Invaders => "space invaders",
+        MachineType::Cpm => "CP/M",
+    };
+    println!("program    {} bytes, {:04x}h to {:04x}h", program.bytes.len(), program.origin, end.saturating_sub(1));
+    println!("machine    {}", machine);
+    println!("entry      {:04x}h", loaded.state.pc);
+    println!("crc32      {:08x}", movie::crc32(&program.bytes));
+    if program.bytes.len() > 0x800 {
+        let chips: Vec<String> = program.bytes.chunks(0x800).map(|chip| format!("{:08x}", movie::crc32(chip))).collect();
+        println!("each 2K    {}", chips.join(" "));
+    }
+    println!("starts with");
+    for instruction in disassembler::disassemble_range(&loaded.state.memory[..], loaded.state.pc, loaded.state.pc.saturating_add(8)) {
+        println!("  {:04x}  {}", instruction.addr, instruction);
+    }
+    Ok(())
 }
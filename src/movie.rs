@@ -0,0 +1,246 @@
+// movies: the buttons held down in each frame of a space invaders game, so the game can be played again exactly as it
+// was, for instance to see a bug someone ran into. the emulator is deterministic, so replaying the same buttons on the
+// same ROM from power on gives the same machine, frame for frame. a movie also keeps a checksum of the machine after
+// every frame, so a replay that goes differently (on another version of the emulator, say) says where it went wrong.
+// a movie starts with the magic "8080MOVI" and a format version, then holds chunks like a save state:
+//   ROM   the length of the ROM as a little-endian u32, then the crc32 of each 2K of it (each ROM chip on the board)
+//   DIPS  the dip switches, as the bits of port 2
+//   INP   port 1 and port 2 in each frame, two bytes a frame
+//   SUM   the crc32 of the save state after each frame, four bytes a frame
+// version 2 frames are 33280 cycles, from the 1.9968MHz clock. version 1 ones were 33333, so they can't be replayed.
+use crate::cpu::State8080;
+use crate::invaders;
+use crate::invaders::Dips;
+use crate::invaders::Invaders;
+use crate::savestate;
+
+pub const MAGIC: &[u8; 8] = b"8080MOVI";
+pub const VERSION: u16 = 2;
+
+// the size of a ROM chip
+const ROM_CHIP: usize = 0x800;
+
+// the crc32 table for the polynomial used by zip and png
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+// the buttons an input script can hold down, and the port and bit each one is
+const BUTTONS: [(&str, u8, u8); 10] = [
+    ("coin", 1, invaders::COIN),
+    ("p1-start", 1, invaders::P1_START),
+    ("p2-start", 1, invaders::P2_START),
+    ("p1-fire", 1, invaders::P1_FIRE),
+    ("p1-left", 1, invaders::P1_LEFT),
+    ("p1-right", 1, invaders::P1_RIGHT),
+    ("p2-fire", 2, invaders::P2_FIRE),
+    ("p2-left", 2, invaders::P2_LEFT),
+    ("p2-right", 2, invaders::P2_RIGHT),
+    ("tilt", 2, invaders::TILT),
+];
+
+// a line of an input script: the buttons held down, as the bits of port 1 and port 2, for a number of frames
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Held {
+    pub frames: u64,
+    pub port1: u8,
+    pub port2: u8,
+}
+
+// reads an input script, which says what to hold down for how long to record a movie. each line is a number of frames
+// and the buttons held down through them (none, if there are only the frames), and # starts a comment:
+//     120             wait two seconds for the game to start
+//     5 coin
+//     60
+//     5 p1-start
+//     30 p1-left p1-fire
+pub fn script(text: &str) -> Result<Vec<Held>, String> {
+    let mut lines = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(frames) = words.next() else {
+            continue;
+        };
+        let frames = frames.parse::<u64>().map_err(|_| format!("line {}: '{}' isn't a number of frames", n + 1, frames))?;
+        let mut held = Held { frames, port1: 0, port2: 0 };
+        for word in words {
+            let Some(&(_, port, bit)) = BUTTONS.iter().find(|(name, _, _)| name.eq_ignore_ascii_case(word)) else {
+                let names: Vec<&str> = BUTTONS.iter().map(|(name, _, _)| *name).collect();
+                return Err(format!("line {}: '{}' isn't a button: the buttons are {}", n + 1, word, names.join(", ")));
+            };
+            if port == 1 {
+                held.port1 |= bit;
+            } else {
+                held.port2 |= bit;
+            }
+        }
+        lines.push(held);
+    }
+    Ok(lines)
+}
+
+// a frame of a movie
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    pub port1: u8,
+    pub port2: u8,
+    pub sum: u32, // the checksum of the machine at the end of the frame
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Movie {
+    pub rom_length: usize,
+    pub roms: Vec<u32>, // the checksum of each 2K of the ROM
+    pub dips: Dips,
+    pub frames: Vec<Frame>,
+}
+
+// the checksum of the whole machine
+fn sum(state: &State8080, machine: &Invaders) -> u32 {
+    crc32(&savestate::save(state, 0, machine))
+}
+
+impl Movie {
+    // a movie with no frames yet, of a game on this ROM with these dip switches
+    pub fn new(rom: &[u8], dips: Dips) -> Movie {
+        Movie { rom_length: rom.len(), roms: rom.chunks(ROM_CHIP).map(crc32).collect(), dips, frames: Vec::new() }
+    }
+
+    // runs a frame with the buttons held in the machine now, and adds it to the movie
+    pub fn record(&mut self, state: &mut State8080, machine: &mut Invaders) {
+        let (port1, port2) = (machine.port1, machine.port2);
+        machine.frame(state);
+        self.frames.push(Frame { port1, port2, sum: sum(state, machine) });
+    }
+
+    // records the frames of an input script, holding down each line's buttons for its frames
+    pub fn record_script(&mut self, script: &[Held], state: &mut State8080, machine: &mut Invaders) {
+        for held in script {
+            machine.port1 = held.port1;
+            machine.port2 = held.port2;
+            for _ in 0..held.frames {
+                self.record(state, machine);
+            }
+        }
+    }
+
+    // checks that the ROM in memory is the one the movie was recorded on
+    pub fn check_rom(&self, memory: &[u8]) -> Result<(), String> {
+        let Some(rom) = memory.get(..self.rom_length) else {
+            return Err("the ROM is too short".to_string());
+        };
+        for (chip, (&expected, found)) in self.roms.iter().zip(rom.chunks(ROM_CHIP).map(crc32)).enumerate() {
+            if expected != found {
+                return Err(format!(
+                    "the ROM at {:04x} has the checksum {:08x}, but the movie was recorded with {:08x}",
+                    chip * ROM_CHIP,
+                    found,
+                    expected
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // plays the movie on a machine that has just been powered on with the ROM loaded. the machine's dip switches are
+    // set to the movie's. returns the first frame at which the machine isn't the same as when the movie was recorded,
+    // or None if every frame is, and an error if the ROM is a different one.
+    pub fn replay(&self, state: &mut State8080, machine: &mut Invaders) -> Result<Option<usize>, String> {
+        self.check_rom(&state.memory[..])?;
+        machine.dips = self.dips;
+        for (n, frame) in self.frames.iter().enumerate() {
+            machine.port1 = frame.port1;
+            machine.port2 = frame.port2;
+            machine.frame(state);
+            if sum(state, machine) != frame.sum {
+                return Ok(Some(n));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        let mut roms = (self.rom_length as u32).to_le_bytes().to_vec();
+        roms.extend(self.roms.iter().flat_map(|crc| crc.to_le_bytes()));
+        savestate::chunk(&mut out, b"ROM ", &roms);
+        savestate::chunk(&mut out, b"DIPS", &[self.dips.bits()]);
+        let inputs: Vec<u8> = self.frames.iter().flat_map(|frame| [frame.port1, frame.port2]).collect();
+        savestate::chunk(&mut out, b"INP ", &inputs);
+        let sums: Vec<u8> = self.frames.iter().flat_map(|frame| frame.sum.to_le_bytes()).collect();
+        savestate::chunk(&mut out, b"SUM ", &sums);
+        out
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Movie, String> {
+        if bytes.len() < 10 || &bytes[..8] != MAGIC {
+            return Err("not a movie".to_string());
+        }
+        let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+        if version > VERSION {
+            return Err(format!("the movie is version {}, which is newer than this emulator reads ({})", version, VERSION));
+        }
+        if version < VERSION {
+            return Err(format!("the movie is version {}, whose frames were a different length, so it can't be replayed", version));
+        }
+
+        let mut movie = Movie { rom_length: 0, roms: Vec::new(), dips: Dips::default(), frames: Vec::new() };
+        let (mut inputs, mut sums): (&[u8], &[u8]) = (&[], &[]);
+        for (tag, data) in savestate::chunks(&bytes[10..], "movie")? {
+            let name = String::from_utf8_lossy(tag).trim_end().to_string();
+            let wrong_length = || format!("the {} chunk is the wrong length", name);
+            match tag {
+                b"ROM " => {
+                    if data.len() < 4 || data.len() % 4 != 0 {
+                        return Err(wrong_length());
+                    }
+                    let words: Vec<u32> = data.chunks(4).map(|word| u32::from_le_bytes(word.try_into().unwrap())).collect();
+                    movie.rom_length = words[0] as usize;
+                    movie.roms = words[1..].to_vec();
+                    if movie.roms.len() != movie.rom_length.div_ceil(ROM_CHIP) {
+                        return Err(wrong_length());
+                    }
+                },
+                b"DIPS" => {
+                    let [bits] = data else {
+                        return Err(wrong_length());
+                    };
+                    movie.dips = Dips::from_bits(*bits);
+                },
+                b"INP " => inputs = data,
+                b"SUM " => sums = data,
+                // a chunk from a later version
+                _ => {},
+            }
+        }
+        if inputs.len() % 2 != 0 || sums.len() != inputs.len() * 2 {
+            return Err("the movie's inputs and checksums don't match up".to_string());
+        }
+        movie.frames = inputs
+            .chunks(2)
+            .zip(sums.chunks(4))
+            .map(|(ports, sum)| Frame { port1: ports[0], port2: ports[1], sum: u32::from_le_bytes(sum.try_into().unwrap()) })
+            .collect();
+        Ok(movie)
+    }
+}
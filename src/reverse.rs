@@ -0,0 +1,121 @@
+// what the debugger needs to run backwards: a snapshot of the machine every so many instructions, and the value
+// every IN instruction read. going back to an earlier instruction restores the last snapshot before it and runs
+// forward again, feeding each IN the value it read the first time, so the program takes exactly the same path. the
+// machine's devices are put back from the snapshot too, and the OUTs sent to them again, so they end up as they were.
+// the snapshots are whole copies of memory, so there is a limit on how many are kept. when it is reached, every
+// other one is dropped and they are taken half as often.
+use std::collections::BTreeMap;
+
+use crate::cpu::State8080;
+use crate::history::Frame;
+use crate::machine::Machine;
+
+pub const DEFAULT_INTERVAL: u64 = 10000;
+pub const MAX_SNAPSHOTS: usize = 256;
+
+#[derive(Clone)]
+pub struct Snapshot {
+    pub count: u64, // the number of instructions run before it was taken
+    pub cycle: u64,
+    pub state: State8080,
+    pub stack: Vec<Frame>, // the call stack, which can't be rebuilt from a snapshot alone
+    pub devices: Vec<u8>, // the state of the machine's devices, as saved for a save state
+}
+
+pub struct Recorder {
+    pub interval: u64,
+    pub count: u64, // the number of instructions run so far
+    pub snapshots: Vec<Snapshot>, // in order of count
+    inputs: BTreeMap<u64, u8>, // the value read by the IN that was instruction number count
+}
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder::new(DEFAULT_INTERVAL)
+    }
+}
+
+// the machine being replayed on: each IN gets the value it read the first time round, without asking the machine
+// (whose inputs may have changed since), and OUTs go to the machine so its devices follow the program again
+pub struct Replay<'a, M: ?Sized> {
+    pub input: u8,
+    pub machine: &'a mut M,
+}
+
+impl<M: Machine + ?Sized> Machine for Replay<'_, M> {
+    fn input(&mut self, _port: u8) -> u8 {
+        self.input
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        self.machine.output(port, value);
+    }
+}
+
+impl Recorder {
+    pub fn new(interval: u64) -> Recorder {
+        Recorder { interval: interval.max(1), count: 0, snapshots: Vec::new(), inputs: BTreeMap::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.count = 0;
+        self.snapshots.clear();
+        self.inputs.clear();
+    }
+
+    // called before each instruction runs for real. whatever was recorded after this point belongs to a run that
+    // has been gone back on, and is forgotten.
+    pub fn before<M: Machine + ?Sized>(&mut self, state: &State8080, machine: &M, cycle: u64, stack: &[Frame]) {
+        if self.snapshots.last().is_some_and(|s| s.count > self.count) || self.inputs.range(self.count..).next().is_some() {
+            self.forget_after(self.count);
+        }
+        if self.count.is_multiple_of(self.interval) && self.snapshots.last().is_none_or(|s| s.count < self.count) {
+            self.take(state, machine, cycle, stack);
+        }
+    }
+
+    // called after each instruction runs for real, with the opcode it was
+    pub fn after(&mut self, opcode: u8, state: &State8080) {
+        if opcode == 0xdb {
+            // IN
+            self.inputs.insert(self.count, state.a);
+        }
+        self.count += 1;
+    }
+
+    // takes a snapshot now, as when the registers or memory are changed by hand, which replaying couldn't reproduce
+    pub fn take<M: Machine + ?Sized>(&mut self, state: &State8080, machine: &M, cycle: u64, stack: &[Frame]) {
+        self.forget_after(self.count);
+        if self.snapshots.last().is_some_and(|s| s.count == self.count) {
+            self.snapshots.pop();
+        }
+        let mut state = state.clone();
+        state.accesses = None;
+        let devices = machine.save_devices();
+        self.snapshots.push(Snapshot { count: self.count, cycle, state, stack: stack.to_vec(), devices });
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            // keep the first, which is the start, and every other one after it
+            let mut i = 0;
+            self.snapshots.retain(|_| {
+                i += 1;
+                i % 2 == 1
+            });
+            self.interval *= 2;
+        }
+    }
+
+    fn forget_after(&mut self, count: u64) {
+        self.snapshots.retain(|s| s.count <= count);
+        self.inputs.retain(|c, _| *c < count);
+    }
+
+    // the last snapshot taken at or before count
+    pub fn snapshot(&self, count: u64) -> Option<&Snapshot> {
+        self.snapshots.iter().rev().find(|s| s.count <= count)
+    }
+
+    // the machine to replay instruction number count on
+    pub fn replay<'a, M: Machine + ?Sized>(&self, count: u64, machine: &'a mut M) -> Replay<'a, M> {
+        Replay { input: self.inputs.get(&count).copied().unwrap_or(0), machine }
+    }
+}
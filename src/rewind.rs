@@ -0,0 +1,144 @@
+// a rewind buffer for play: a snapshot of the machine every frame (or every few frames), going back a few seconds,
+// so that holding a key can run time backwards one frame at a time.
+// keeping 64K of memory for every frame would take 4MB a second, so each snapshot keeps only what changed in memory
+// since the one before, as runs of bytes xored with the old ones. only the newest memory is kept whole; going back
+// applies the newest delta to it, which gives the memory of the snapshot before.
+use std::collections::VecDeque;
+
+use crate::cpu::State8080;
+use crate::machine::Machine;
+use crate::savestate;
+
+// ten seconds at 60 frames a second
+pub const DEFAULT_CAPACITY: usize = 600;
+
+// a run of changed bytes costs this much on top of its bytes, so runs closer than this are joined
+const RUN_HEADER: usize = 4;
+
+struct Frame {
+    cpu: Vec<u8>, // the registers, as a save state keeps them
+    cycle: u64,
+    devices: Vec<u8>,
+    delta: Vec<u8>, // the memory xored with the frame before, as runs of (offset, length, bytes)
+}
+
+pub struct Rewind {
+    pub capacity: usize, // the most snapshots kept
+    pub interval: u64, // the frames between snapshots
+    frames: VecDeque<Frame>,
+    memory: Vec<u8>, // the memory of the newest snapshot
+    frame: u64, // the frames seen, for the interval
+}
+
+impl Default for Rewind {
+    fn default() -> Rewind {
+        Rewind::new(DEFAULT_CAPACITY, 1)
+    }
+}
+
+// the runs of bytes that differ between old and new, each one its offset and length as little-endian u16s, then the
+// bytes of old xor new. a run is at most 0xffff bytes long.
+pub fn delta(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < new.len() {
+        if old[i] == new[i] {
+            i += 1;
+            continue;
+        }
+        // carry the run on through short gaps of unchanged bytes, which are cheaper to keep than a new run
+        let start = i;
+        let mut end = i + 1;
+        let mut j = end;
+        while j < new.len() && j - start < 0xffff && j - end <= RUN_HEADER {
+            if old[j] != new[j] {
+                end = j + 1;
+            }
+            j += 1;
+        }
+        out.extend_from_slice(&(start as u16).to_le_bytes());
+        out.extend_from_slice(&((end - start) as u16).to_le_bytes());
+        out.extend((start..end).map(|k| old[k] ^ new[k]));
+        i = end;
+    }
+    out
+}
+
+// xors the runs into memory, which turns either side of the delta into the other
+pub fn apply(memory: &mut [u8], delta: &[u8]) {
+    let mut rest = delta;
+    while rest.len() >= RUN_HEADER {
+        let start = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+        let length = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        for (k, byte) in rest[RUN_HEADER..RUN_HEADER + length].iter().enumerate() {
+            memory[start + k] ^= byte;
+        }
+        rest = &rest[RUN_HEADER + length..];
+    }
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: u64) -> Rewind {
+        Rewind { capacity, interval: interval.max(1), frames: VecDeque::new(), memory: Vec::new(), frame: 0 }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.memory.clear();
+        self.frame = 0;
+    }
+
+    // the number of snapshots there are to go back through
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // roughly how much memory the snapshots take
+    pub fn bytes(&self) -> usize {
+        self.memory.len() + self.frames.iter().map(|frame| frame.delta.len() + frame.devices.len() + 64).sum::<usize>()
+    }
+
+    // called at the end of every frame, and takes a snapshot if it is time for one
+    pub fn frame<M: Machine + ?Sized>(&mut self, state: &State8080, cycle: u64, machine: &M) {
+        if self.frame.is_multiple_of(self.interval) {
+            self.push(state, cycle, machine);
+        }
+        self.frame += 1;
+    }
+
+    // takes a snapshot now
+    pub fn push<M: Machine + ?Sized>(&mut self, state: &State8080, cycle: u64, machine: &M) {
+        if self.capacity == 0 {
+            return;
+        }
+        let delta = if self.memory.is_empty() { Vec::new() } else { delta(&self.memory, &state.memory[..]) };
+        self.memory.clear();
+        self.memory.extend_from_slice(&state.memory[..]);
+        self.frames.push_back(Frame { cpu: savestate::cpu_bytes(state), cycle, devices: machine.save_devices(), delta });
+        if self.frames.len() > self.capacity {
+            // the oldest snapshot's delta is to one that is already gone, so nothing needs to change to drop it
+            self.frames.pop_front();
+        }
+    }
+
+    // goes back to the newest snapshot and forgets it, so the next call goes back one further.
+    // returns the cycle count of the snapshot, or None when there is nothing left to go back to.
+    pub fn back<M: Machine + ?Sized>(&mut self, state: &mut State8080, machine: &mut M) -> Option<u64> {
+        let frame = self.frames.pop_back()?;
+        savestate::set_cpu(state, &frame.cpu);
+        state.memory.copy_from_slice(&self.memory);
+        // a machine can always load devices it saved itself
+        machine.load_devices(&frame.devices).ok();
+        if self.frames.is_empty() {
+            self.memory.clear();
+        } else {
+            apply(&mut self.memory, &frame.delta);
+        }
+        self.frame = 0;
+        Some(frame.cycle)
+    }
+}
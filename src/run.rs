@@ -0,0 +1,285 @@
+// runs a program without anyone watching, for scripts and CI: for a number of cycles or frames, or until something
+// happens (the pc gets to an address, a byte of memory gets a value, the cpu halts, or the program prints something),
+// then sums up how it ended as JSON.
+// a run passes when it ends on one of the conditions it was waiting for. a run that wasn't waiting for anything passes
+// however it ends.
+// it runs as fast as it can unless its throttle is given a speed, which it paces to at the end of every frame.
+// with a rewind buffer it keeps a snapshot of every frame, so when the run has ended it can go back a few frames, to
+// see (or save) the machine from just before whatever ended it.
+use std::io;
+use std::io::Write;
+
+use crate::cpm;
+use crate::cpu::State8080;
+use crate::invaders;
+use crate::machine::Machine;
+use crate::rewind::Rewind;
+use crate::scheduler::Scheduler;
+use crate::throttle::Throttle;
+use crate::trace::Tracer;
+
+// something to stop at
+#[derive(Clone, Debug, PartialEq)]
+pub enum Until {
+    Pc(u16), // the pc gets to the address
+    Byte(u16, u8), // the byte at the address has the value
+    Halt, // the cpu halts
+    Output(String), // the program's output ends with the text
+}
+
+// why a run ended
+#[derive(Clone, Debug, PartialEq)]
+pub enum Exit {
+    Met(Until), // one of the conditions
+    Cycles, // it ran for as many cycles as it was allowed
+    Frames, // it ran for as many frames as it was allowed
+    Exited, // a CP/M program ended
+    Deadlocked, // the cpu halted with nothing to wake it (interrupts disabled, or none coming), so it can never go on
+}
+
+impl Exit {
+    // the reason, as the summary gives it
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Exit::Met(Until::Pc(_)) => "pc",
+            Exit::Met(Until::Byte(..)) => "memory",
+            Exit::Met(Until::Halt) => "halt",
+            Exit::Met(Until::Output(_)) => "output",
+            Exit::Cycles => "cycle limit",
+            Exit::Frames => "frame limit",
+            Exit::Exited => "exited",
+            Exit::Deadlocked => "deadlocked",
+        }
+    }
+}
+
+pub struct Runner {
+    pub cycles: Option<u64>, // the most cycles to run
+    pub frames: Option<u64>, // the most frames to run, at 60 frames a second
+    pub until: Vec<Until>,
+    pub cpm: bool, // the program is a CP/M .COM program, whose BDOS calls are done for it
+    pub console: Option<u8>, // the port a program that isn't a CP/M one writes its output to
+    pub frame_interrupts: bool, // interrupt the cpu with RST 1 and RST 2 every frame, like the space invaders video
+    pub tracer: Tracer, // counts the cycles, and traces the instructions if it's enabled
+    pub instructions: u64,
+    pub output: Vec<u8>, // what the program has written to the console
+    pub echo: Option<Box<dyn Write>>, // where to copy the output as it's written
+    pub throttle: Throttle,
+    pub speed_log: Option<Box<dyn Write>>, // where to write the speed it runs at, each second
+    pub rewind: Option<Rewind>, // snapshots of the last frames, to go back through
+}
+
+impl Default for Runner {
+    fn default() -> Runner {
+        Runner::new()
+    }
+}
+
+// a string in JSON, with its quotes
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Runner {
+    // a runner that runs until the cpu deadlocks, and stops at nothing else
+    pub fn new() -> Runner {
+        Runner {
+            cycles: None,
+            frames: None,
+            until: Vec::new(),
+            cpm: false,
+            console: None,
+            frame_interrupts: false,
+            tracer: Tracer::new(),
+            instructions: 0,
+            output: Vec::new(),
+            echo: None,
+            throttle: Throttle::new(None),
+            speed_log: None,
+            rewind: None,
+        }
+    }
+
+    // adds to the output, and says whether that met an output condition
+    fn print(&mut self, bytes: &[u8]) -> io::Result<Option<Exit>> {
+        if let Some(echo) = &mut self.echo {
+            echo.write_all(bytes)?;
+        }
+        self.output.extend_from_slice(bytes);
+        Ok(self.until.iter().find_map(|until| match until {
+            Until::Output(text) if self.output.ends_with(text.as_bytes()) => Some(Exit::Met(until.clone())),
+            _ => None,
+        }))
+    }
+
+    // the first condition the cpu meets now, apart from the output
+    fn met(&self, state: &State8080) -> Option<Exit> {
+        self.until.iter().find_map(|until| {
+            let met = match until {
+                Until::Pc(addr) => state.pc == *addr,
+                Until::Byte(addr, value) => state.memory[*addr as usize] == *value,
+                Until::Halt => state.halted,
+                Until::Output(_) => false,
+            };
+            met.then(|| Exit::Met(until.clone()))
+        })
+    }
+
+    // runs until a condition is met or a limit is reached
+    pub fn run<M: Machine + ?Sized>(&mut self, state: &mut State8080, machine: &mut M) -> io::Result<Exit> {
+        let exit = self.run_until_exit(state, machine);
+        if let Some(echo) = &mut self.echo {
+            echo.flush()?;
+        }
+        self.tracer.flush()?;
+        exit
+    }
+
+    // goes back n frames through the rewind buffer, to the start of a frame (or of the run), or as far as it goes.
+    // returns the number of frames it went back.
+    pub fn back<M: Machine + ?Sized>(&mut self, state: &mut State8080, machine: &mut M, frames: u64) -> u64 {
+        let Some(rewind) = &mut self.rewind else {
+            return 0;
+        };
+        let mut gone = 0;
+        while gone < frames {
+            let Some(cycle) = rewind.back(state, machine) else {
+                break;
+            };
+            // a snapshot of where the run already is, because it ended at the end of a frame, doesn't go back
+            if cycle < self.tracer.cycle {
+                gone += 1;
+            }
+            self.tracer.cycle = cycle;
+        }
+        gone
+    }
+
+    fn run_until_exit<M: Machine + ?Sized>(&mut self, state: &mut State8080, machine: &mut M) -> io::Result<Exit> {
+        // the limits count from here, which is after the cycles a save state was taken at if the run carries on from one,
+        // and so do the frames, the same as a frame of the game does
+        let start = self.tracer.cycle;
+        let frame_limit = self.frames.map(|frames| frames.saturating_mul(invaders::CYCLES_PER_FRAME));
+        let mut scheduler = Scheduler::new();
+        scheduler.cycle = start;
+        if self.frame_interrupts {
+            invaders::schedule_interrupts(&mut scheduler, start);
+        }
+        // the pacing starts from here
+        self.throttle.pace(self.tracer.cycle);
+        if let Some(rewind) = &mut self.rewind {
+            rewind.push(state, self.tracer.cycle, machine);
+        }
+        loop {
+            if self.cycles.is_some_and(|limit| self.tracer.cycle - start >= limit) {
+                return Ok(Exit::Cycles);
+            }
+            if frame_limit.is_some_and(|limit| self.tracer.cycle - start >= limit) {
+                return Ok(Exit::Frames);
+            }
+            if state.halted && !(state.int_enable && self.frame_interrupts) && !self.until.contains(&Until::Halt) {
+                return Ok(Exit::Deadlocked);
+            }
+
+            if self.cpm && !state.halted {
+                if state.pc == cpm::WBOOT {
+                    return Ok(Exit::Exited);
+                }
+                if state.pc == cpm::BDOS {
+                    let mut printed: Vec<u8> = Vec::new();
+                    let goes_on = cpm::bdos(state, &mut printed)?;
+                    if let Some(exit) = self.print(&printed)? {
+                        return Ok(exit);
+                    }
+                    if !goes_on {
+                        return Ok(Exit::Exited);
+                    }
+                }
+            }
+            // what an OUT to the console port writes, which the instruction itself sends to the machine
+            let out = state.memory[state.pc as usize] == 0xd3
+                && !state.halted
+                && Some(state.memory[state.pc.wrapping_add(1) as usize]) == self.console;
+            let a = state.a;
+
+            let before = self.tracer.cycle;
+            self.tracer.step(state, machine)?;
+            self.instructions += 1;
+            scheduler.catch_up(state, machine, self.tracer.cycle);
+
+            if (before - start) / invaders::CYCLES_PER_FRAME != (self.tracer.cycle - start) / invaders::CYCLES_PER_FRAME {
+                if let Some(rewind) = &mut self.rewind {
+                    rewind.frame(state, self.tracer.cycle, machine);
+                }
+                let percent = self.throttle.pace(self.tracer.cycle);
+                if let (Some(percent), Some(log)) = (percent, &mut self.speed_log) {
+                    writeln!(log, "speed {:.1}%", percent)?;
+                }
+            }
+
+            if out {
+                if let Some(exit) = self.print(&[a])? {
+                    return Ok(exit);
+                }
+            }
+            if let Some(exit) = self.met(state) {
+                return Ok(exit);
+            }
+        }
+    }
+
+    // whether a run that ended like this passed
+    pub fn passed(&self, exit: &Exit) -> bool {
+        self.until.is_empty() || matches!(exit, Exit::Met(_))
+    }
+
+    // how the run ended, as a JSON object
+    pub fn summary(&self, state: &State8080, exit: &Exit) -> String {
+        let registers = [
+            ("a", state.a as u16),
+            ("b", state.b as u16),
+            ("c", state.c as u16),
+            ("d", state.d as u16),
+            ("e", state.e as u16),
+            ("h", state.h as u16),
+            ("l", state.l as u16),
+            ("sp", state.sp),
+            ("pc", state.pc),
+            ("psw", state.get_psw() as u16),
+        ];
+        let registers: Vec<String> = registers.iter().map(|(name, value)| format!("\"{}\": {}", name, value)).collect();
+        let flags = [("s", state.cc.s), ("z", state.cc.z), ("ac", state.cc.ac), ("p", state.cc.p), ("cy", state.cc.cy)];
+        let flags: Vec<String> = flags.iter().map(|(name, set)| format!("\"{}\": {}", name, set)).collect();
+        let mut fields = vec![
+            format!("\"exit\": {}", json_string(exit.reason())),
+            format!("\"passed\": {}", self.passed(exit)),
+            format!("\"cycles\": {}", self.tracer.cycle),
+            format!("\"frames\": {}", self.tracer.cycle / invaders::CYCLES_PER_FRAME),
+            format!("\"instructions\": {}", self.instructions),
+            format!("\"registers\": {{{}}}", registers.join(", ")),
+            format!("\"flags\": {{{}}}", flags.join(", ")),
+            format!("\"interrupts_enabled\": {}", state.int_enable),
+            format!("\"halted\": {}", state.halted),
+            format!("\"output\": {}", json_string(&String::from_utf8_lossy(&self.output))),
+        ];
+        match exit {
+            Exit::Met(Until::Pc(addr)) => fields.push(format!("\"address\": {}", addr)),
+            Exit::Met(Until::Byte(addr, value)) => fields.push(format!("\"address\": {}, \"value\": {}", addr, value)),
+            _ => {},
+        }
+        format!("{{\n  {}\n}}\n", fields.join(",\n  "))
+    }
+}
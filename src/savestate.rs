@@ -0,0 +1,135 @@
+// save states: the whole machine written to bytes, so it can be put back exactly as it was.
+// a save state starts with the magic "8080SAVE" and a format version, then holds chunks, each a 4-byte tag,
+// a 4-byte little-endian length and that many bytes of data:
+//   CPU   the registers, flags and interrupt state
+//   MEM   all 64K of memory
+//   CYC   the number of cycles run, as a little-endian u64
+//   DEV   the state of the machine's devices, in whatever form the machine saves it
+// a reader skips the chunks it doesn't know, so chunks can be added without changing the version. the version only
+// goes up when a chunk it does know changes, and a save state from a newer version than this one is refused.
+use crate::cpu::State8080;
+use crate::machine::Machine;
+
+pub const MAGIC: &[u8; 8] = b"8080SAVE";
+pub const VERSION: u16 = 1;
+
+// what a save state holds
+pub struct SaveState {
+    pub state: State8080,
+    pub cycle: u64,
+    pub devices: Vec<u8>,
+}
+
+pub(crate) fn chunk(out: &mut Vec<u8>, tag: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(tag);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+}
+
+// a chunk's tag and data
+pub(crate) type Chunk<'a> = (&'a [u8], &'a [u8]);
+
+// splits the bytes after the magic and version into their chunks. what is what the bytes are, for errors.
+pub(crate) fn chunks<'a>(bytes: &'a [u8], what: &str) -> Result<Vec<Chunk<'a>>, String> {
+    let mut chunks = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        if rest.len() < 8 {
+            return Err(format!("the {} is cut short", what));
+        }
+        let length = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        let Some(data) = rest.get(8..8 + length) else {
+            return Err(format!("the {} is cut short", what));
+        };
+        chunks.push((&rest[..4], data));
+        rest = &rest[8 + length..];
+    }
+    Ok(chunks)
+}
+
+// the registers, flags and interrupt state, as the CPU chunk holds them
+pub(crate) fn cpu_bytes(state: &State8080) -> Vec<u8> {
+    let mut cpu = vec![state.a, state.b, state.c, state.d, state.e, state.h, state.l, state.get_psw()];
+    cpu.extend_from_slice(&state.sp.to_le_bytes());
+    cpu.extend_from_slice(&state.pc.to_le_bytes());
+    cpu.push(state.int_enable as u8);
+    cpu.push(state.halted as u8);
+    cpu
+}
+
+// puts back the registers from a CPU chunk. returns false if the chunk is the wrong length.
+pub(crate) fn set_cpu(state: &mut State8080, data: &[u8]) -> bool {
+    let [a, b, c, d, e, h, l, psw, sp0, sp1, pc0, pc1, int_enable, halted] = data else {
+        return false;
+    };
+    (state.a, state.b, state.c, state.d, state.e, state.h, state.l) = (*a, *b, *c, *d, *e, *h, *l);
+    state.set_psw(*psw);
+    state.sp = u16::from_le_bytes([*sp0, *sp1]);
+    state.pc = u16::from_le_bytes([*pc0, *pc1]);
+    state.int_enable = *int_enable != 0;
+    state.halted = *halted != 0;
+    true
+}
+
+// writes the cpu, its memory, the cycle count and the machine's devices
+pub fn save<M: Machine + ?Sized>(state: &State8080, cycle: u64, machine: &M) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+
+    chunk(&mut out, b"CPU ", &cpu_bytes(state));
+    chunk(&mut out, b"MEM ", &state.memory[..]);
+    chunk(&mut out, b"CYC ", &cycle.to_le_bytes());
+    chunk(&mut out, b"DEV ", &machine.save_devices());
+    out
+}
+
+// reads a save state, checking that it is complete and from a version this can read
+pub fn read(bytes: &[u8]) -> Result<SaveState, String> {
+    if bytes.len() < 10 || &bytes[..8] != MAGIC {
+        return Err("not a save state".to_string());
+    }
+    let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+    if version > VERSION {
+        return Err(format!("the save state is version {}, which is newer than this emulator reads ({})", version, VERSION));
+    }
+
+    let mut saved = SaveState { state: State8080::new(), cycle: 0, devices: Vec::new() };
+    let (mut cpu, mut memory) = (false, false);
+    for (tag, data) in chunks(&bytes[10..], "save state")? {
+        let name = String::from_utf8_lossy(tag).trim_end().to_string();
+        let wrong_length = || format!("the {} chunk is the wrong length", name);
+        match tag {
+            b"CPU " => {
+                if !set_cpu(&mut saved.state, data) {
+                    return Err(wrong_length());
+                }
+                cpu = true;
+            },
+            b"MEM " => {
+                if data.len() != saved.state.memory.len() {
+                    return Err(wrong_length());
+                }
+                saved.state.memory.copy_from_slice(data);
+                memory = true;
+            },
+            b"CYC " => saved.cycle = u64::from_le_bytes(data.try_into().map_err(|_| wrong_length())?),
+            b"DEV " => saved.devices = data.to_vec(),
+            // a chunk from a later version, which this one doesn't need
+            _ => {},
+        }
+    }
+    if !cpu || !memory {
+        return Err("the save state is missing the cpu or memory".to_string());
+    }
+    Ok(saved)
+}
+
+// reads a save state into the cpu and machine, and returns the cycle count it was saved at.
+// nothing is changed unless the whole save state can be loaded.
+pub fn load<M: Machine + ?Sized>(bytes: &[u8], state: &mut State8080, machine: &mut M) -> Result<u64, String> {
+    let saved = read(bytes)?;
+    machine.load_devices(&saved.devices)?;
+    *state = saved.state;
+    Ok(saved.cycle)
+}
@@ -0,0 +1,120 @@
+// runs the cpu by the clock, for machine drivers: for a number of cycles, or up to a cycle, calling back at the cycles
+// things were scheduled for (an interrupt from the video, a sound timer) so a driver doesn't need a main loop of its
+// own.
+// the cycle count starts at 0 and only goes up. the cpu can only be interrupted between instructions, so a callback is
+// called as soon as the count reaches its cycle after an instruction, which can be a few cycles late. callbacks due at
+// the same cycle are called in the order they were scheduled.
+use crate::cpu::State8080;
+use crate::machine;
+use crate::machine::Machine;
+
+pub type Callback<M> = Box<dyn FnMut(&mut State8080, &mut M)>;
+
+struct Event<M: ?Sized> {
+    id: u64,
+    at: u64, // the cycle it's due at
+    every: Option<u64>, // the cycles between calls, for one that repeats
+    callback: Callback<M>,
+}
+
+pub struct Scheduler<M: ?Sized> {
+    pub cycle: u64, // the cycles run so far
+    events: Vec<Event<M>>, // in the order they're due
+    due: u64, // the cycle the first of them is due at
+    next_id: u64,
+}
+
+impl<M: Machine + ?Sized> Default for Scheduler<M> {
+    fn default() -> Scheduler<M> {
+        Scheduler::new()
+    }
+}
+
+impl<M: Machine + ?Sized> Scheduler<M> {
+    pub fn new() -> Scheduler<M> {
+        Scheduler { cycle: 0, events: Vec::new(), due: u64::MAX, next_id: 0 }
+    }
+
+    fn insert(&mut self, event: Event<M>) {
+        let i = self.events.partition_point(|e| e.at <= event.at);
+        self.events.insert(i, event);
+        self.due = self.events[0].at;
+    }
+
+    fn schedule(&mut self, at: u64, every: Option<u64>, callback: Callback<M>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.insert(Event { id, at, every, callback });
+        id
+    }
+
+    // calls back once at the cycle, and returns an id to cancel it with
+    pub fn at(&mut self, cycle: u64, callback: impl FnMut(&mut State8080, &mut M) + 'static) -> u64 {
+        self.schedule(cycle, None, Box::new(callback))
+    }
+
+    // calls back at the cycle and then every period cycles after it, counted from when each call was due rather than
+    // when it was made, so the calls don't drift
+    pub fn every(&mut self, cycle: u64, period: u64, callback: impl FnMut(&mut State8080, &mut M) + 'static) -> u64 {
+        self.schedule(cycle, Some(period.max(1)), Box::new(callback))
+    }
+
+    // interrupts the cpu with RST n at the cycle, which it takes if interrupts are enabled then
+    pub fn interrupt_at(&mut self, cycle: u64, n: u8) -> u64 {
+        self.at(cycle, move |state, _| {
+            machine::interrupt(state, n);
+        })
+    }
+
+    // cancels a callback, and returns whether it was still to come
+    pub fn cancel(&mut self, id: u64) -> bool {
+        let before = self.events.len();
+        self.events.retain(|event| event.id != id);
+        self.due = self.events.first().map_or(u64::MAX, |event| event.at);
+        self.events.len() != before
+    }
+
+    // the cycle the next callback is due at
+    pub fn next_event(&self) -> Option<u64> {
+        self.events.first().map(|event| event.at)
+    }
+
+    // calls back everything that is due
+    fn fire(&mut self, state: &mut State8080, machine: &mut M) {
+        while self.due <= self.cycle {
+            let mut event = self.events.remove(0);
+            (event.callback)(state, machine);
+            if let Some(every) = event.every {
+                event.at += every;
+                self.insert(event);
+            } else {
+                self.due = self.events.first().map_or(u64::MAX, |event| event.at);
+            }
+        }
+    }
+
+    // runs instructions until the cycle count reaches the target, calling back whatever comes due on the way (and
+    // whatever is due at the target), and returns the cycles run. the last instruction can take it a few cycles past.
+    pub fn run_until_cycle(&mut self, state: &mut State8080, machine: &mut M, target: u64) -> u64 {
+        let start = self.cycle;
+        loop {
+            self.fire(state, machine);
+            if self.cycle >= target {
+                return self.cycle - start;
+            }
+            self.cycle += machine::step(state, machine) as u64;
+        }
+    }
+
+    // for a driver that runs the instructions itself (to trace them, say): moves the count on to the cycle the cpu has
+    // got to, and calls back whatever has come due
+    pub fn catch_up(&mut self, state: &mut State8080, machine: &mut M, cycle: u64) {
+        self.cycle = self.cycle.max(cycle);
+        self.fire(state, machine);
+    }
+
+    // runs instructions for at least the number of cycles, and returns the cycles they took
+    pub fn run_cycles(&mut self, state: &mut State8080, machine: &mut M, budget: u64) -> u64 {
+        self.run_until_cycle(state, machine, self.cycle.saturating_add(budget))
+    }
+}
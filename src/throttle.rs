@@ -0,0 +1,88 @@
+// keeps the emulator to the speed of the real machine, for playing rather than testing: the 8080 in the space
+// invaders board runs at 1.9968MHz, so after n cycles, n / 1.9968MHz seconds should have passed on the host. a host
+// paces once a frame, and waits for the host clock to catch up if it has run ahead.
+// the speed is a multiple of the original: 2 for double speed, 0.5 for slow motion, or none for as fast as it can
+// go, to fast-forward. it measures the speed it really ran at each second, as a percentage of the original.
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+// the clock of the space invaders board, in cycles a second
+pub const CLOCK_HZ: u64 = 1_996_800;
+
+// if the host falls further behind than this (a slow machine, or the process stopped for a while), it gives up catching
+// up and goes on at the speed from where it is, rather than running flat out until it has caught up
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+// how often the speed is measured
+const MEASURE_EVERY: Duration = Duration::from_secs(1);
+
+pub struct Throttle {
+    speed: Option<f64>,
+    start: Option<(Instant, u64)>, // the host time and cycle count the pacing is counted from
+    window: Option<(Instant, u64)>, // the start of the second the speed is being measured over
+    pub percent: Option<f64>, // the last speed measured, as a percentage of the original
+}
+
+impl Throttle {
+    // a throttle to the speed, as a multiple of the original, or none to not hold it back
+    pub fn new(speed: Option<f64>) -> Throttle {
+        Throttle { speed: speed.filter(|speed| *speed > 0.0), start: None, window: None, percent: None }
+    }
+
+    pub fn speed(&self) -> Option<f64> {
+        self.speed
+    }
+
+    // changes the speed from now on, without making up for the time run at the old one
+    pub fn set_speed(&mut self, speed: Option<f64>) {
+        self.speed = speed.filter(|speed| *speed > 0.0);
+        self.start = None;
+    }
+
+    // how long to wait at the time now for the host to catch up with the cycle count
+    pub fn delay(&mut self, now: Instant, cycle: u64) -> Duration {
+        let Some(speed) = self.speed else {
+            return Duration::ZERO;
+        };
+        let (start, start_cycle) = match self.start {
+            // the count starting again (from a save state, say) starts the pacing again too
+            Some((start, start_cycle)) if start_cycle <= cycle => (start, start_cycle),
+            _ => *self.start.insert((now, cycle)),
+        };
+        let due = start + Duration::from_secs_f64((cycle - start_cycle) as f64 / (CLOCK_HZ as f64 * speed));
+        if due > now {
+            due - now
+        } else {
+            if now - due > MAX_LAG {
+                self.start = Some((now, cycle));
+            }
+            Duration::ZERO
+        }
+    }
+
+    // adds the cycles run at the time now to the measurement, and returns the speed when a second has passed
+    pub fn measure(&mut self, now: Instant, cycle: u64) -> Option<f64> {
+        let (start, start_cycle) = match self.window {
+            Some((start, start_cycle)) if start_cycle <= cycle => (start, start_cycle),
+            _ => *self.window.insert((now, cycle)),
+        };
+        let elapsed = now - start;
+        if elapsed < MEASURE_EVERY {
+            return None;
+        }
+        let percent = (cycle - start_cycle) as f64 * 100.0 / (CLOCK_HZ as f64 * elapsed.as_secs_f64());
+        self.window = Some((now, cycle));
+        self.percent = Some(percent);
+        Some(percent)
+    }
+
+    // waits until the host has caught up with the cycle count, and returns the speed if a second has passed
+    pub fn pace(&mut self, cycle: u64) -> Option<f64> {
+        let delay = self.delay(Instant::now(), cycle);
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+        self.measure(Instant::now(), cycle)
+    }
+}
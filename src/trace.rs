@@ -0,0 +1,83 @@
+// an execution trace: one line for each instruction, written before it runs, like this:
+//   PC=0100 AF=0002 BC=0000 DE=0000 HL=0000 SP=0000 CYC=0  31 00 01  LXI SP,0200h
+// the registers come first, in fixed columns, so a trace can be diffed against another emulator's log after
+// cutting the lines down to the same fields. CYC is the number of cycles run before the instruction.
+// the tracer can be switched on and off while the program runs, and only writes the instructions inside its
+// address range and cycle window.
+use std::io;
+use std::io::Write;
+
+use crate::cpu::State8080;
+use crate::history::Entry;
+use crate::machine;
+use crate::machine::Machine;
+
+pub struct Tracer {
+    pub enabled: bool,
+    pub addresses: Option<(u16, u16)>, // only the instructions from the first address to the second, inclusive
+    pub cycles: Option<(u64, u64)>, // only the instructions that start from the first cycle, up to but not including the second
+    pub cycle: u64, // the cycles run so far, counted whether or not the tracer is enabled
+    out: Option<Box<dyn Write>>,
+}
+
+impl Default for Tracer {
+    fn default() -> Tracer {
+        Tracer::new()
+    }
+}
+
+// the trace line for the instruction at the pc
+pub fn line(state: &State8080, cycle: u64) -> String {
+    Entry::new(state, cycle).to_string()
+}
+
+impl Tracer {
+    // a tracer with nowhere to write yet, which only counts cycles
+    pub fn new() -> Tracer {
+        Tracer { enabled: false, addresses: None, cycles: None, cycle: 0, out: None }
+    }
+
+    // starts writing the trace to out
+    pub fn to(out: Box<dyn Write>) -> Tracer {
+        Tracer { enabled: true, out: Some(out), ..Tracer::new() }
+    }
+
+    // sends the trace somewhere else from now on, and switches it on
+    pub fn set_output(&mut self, out: Box<dyn Write>) {
+        self.out = Some(out);
+        self.enabled = true;
+    }
+
+    // whether the instruction at the pc is one to write
+    fn wanted(&self, state: &State8080) -> bool {
+        self.enabled
+            && self.addresses.is_none_or(|(first, last)| (first..=last).contains(&state.pc))
+            && self.cycles.is_none_or(|(start, end)| (start..end).contains(&self.cycle))
+    }
+
+    // writes the line for the instruction at the pc, if the filters let it through
+    pub fn trace(&mut self, state: &State8080) -> io::Result<()> {
+        if !self.wanted(state) || state.halted {
+            return Ok(());
+        }
+        match &mut self.out {
+            Some(out) => writeln!(out, "{}", line(state, self.cycle)),
+            None => Ok(()),
+        }
+    }
+
+    // traces the instruction at the pc, then runs it and counts its cycles
+    pub fn step<M: Machine + ?Sized>(&mut self, state: &mut State8080, machine: &mut M) -> io::Result<u8> {
+        self.trace(state)?;
+        let cycles = machine::step(state, machine);
+        self.cycle += cycles as u64;
+        Ok(cycles)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.out {
+            Some(out) => out.flush(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,333 @@
+// checks every arithmetic and logic instruction against one reference model of the 8080 ALU.
+// each instruction is run for every accumulator value, operand value and starting set of flags,
+// so an arm that was copied from its neighbour and not quite fixed up shows up here.
+use emulator_8080::cpu::emulate;
+use emulator_8080::cpu::State8080;
+
+const SIGN: u8 = 0x80;
+const ZERO: u8 = 0x40;
+const AUX_CARRY: u8 = 0x10;
+const PARITY: u8 = 0x04;
+const CARRY: u8 = 0x01;
+
+// where the instruction under test goes, and where HL points for the M operand
+const PROGRAM: u16 = 0x0100;
+const OPERAND: u16 = 0x2000;
+
+// the flags going in: all clear and all set, so flags an instruction should leave alone are checked both ways
+const FLAGS_IN: [u8; 2] = [0x02, 0xd7];
+
+const REGISTERS: [&str; 8] = ["B", "C", "D", "E", "H", "L", "M", "A"];
+
+#[derive(Clone, Copy)]
+enum Op {
+    Add,
+    Adc,
+    Sub,
+    Sbb,
+    Ana,
+    Xra,
+    Ora,
+    Cmp,
+}
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Op::Add => "ADD",
+            Op::Adc => "ADC",
+            Op::Sub => "SUB",
+            Op::Sbb => "SBB",
+            Op::Ana => "ANA",
+            Op::Xra => "XRA",
+            Op::Ora => "ORA",
+            Op::Cmp => "CMP",
+        }
+    }
+
+    fn immediate_name(self) -> &'static str {
+        match self {
+            Op::Add => "ADI",
+            Op::Adc => "ACI",
+            Op::Sub => "SUI",
+            Op::Sbb => "SBI",
+            Op::Ana => "ANI",
+            Op::Xra => "XRI",
+            Op::Ora => "ORI",
+            Op::Cmp => "CPI",
+        }
+    }
+
+    // the register forms are 0x80 + 8 * op + register, the immediate forms 0xc6 + 8 * op
+    fn index(self) -> u8 {
+        self as u8
+    }
+}
+
+fn flag(set: bool, bit: u8) -> u8 {
+    if set { bit } else { 0 }
+}
+
+// S, Z and P for a result
+fn szp(result: u8) -> u8 {
+    let mut flags = 0x02;
+    if result & 0x80 != 0 {
+        flags |= SIGN;
+    }
+    if result == 0 {
+        flags |= ZERO;
+    }
+    if result.count_ones().is_multiple_of(2) {
+        flags |= PARITY;
+    }
+    flags
+}
+
+// the result and the flags byte (as pushed by PUSH PSW) of an ALU operation.
+// subtraction is done the way the 8080 does it, adding the complement of the operand and the carry,
+// which leaves carry meaning "borrow" and aux carry meaning "no borrow out of bit 3".
+fn reference(op: Op, a: u8, b: u8, carry: bool) -> (u8, u8) {
+    let add = |b: u8, carry_in: u8| {
+        let sum = a as u16 + b as u16 + carry_in as u16;
+        let half = (a & 0xf) + (b & 0xf) + carry_in;
+        (sum as u8, sum > 0xff, half > 0xf)
+    };
+    match op {
+        Op::Add | Op::Adc => {
+            let (result, cy, ac) = add(b, (carry && matches!(op, Op::Adc)) as u8);
+            (result, szp(result) | flag(cy, CARRY) | flag(ac, AUX_CARRY))
+        },
+        Op::Sub | Op::Sbb | Op::Cmp => {
+            let borrow = carry && matches!(op, Op::Sbb);
+            let (difference, cy, ac) = add(!b, !borrow as u8);
+            // CMP sets the flags the same way as SUB, but throws the difference away
+            let result = if matches!(op, Op::Cmp) { a } else { difference };
+            (result, szp(difference) | flag(!cy, CARRY) | flag(ac, AUX_CARRY))
+        },
+        Op::Ana => {
+            let result = a & b;
+            (result, szp(result) | flag((a | b) & 0x08 != 0, AUX_CARRY))
+        },
+        Op::Xra => (a ^ b, szp(a ^ b)),
+        Op::Ora => (a | b, szp(a | b)),
+    }
+}
+
+// the registers an instruction could touch, with the flags as a PUSH PSW byte
+#[derive(PartialEq, Debug, Clone, Copy)]
+struct Registers {
+    a: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    h: u8,
+    l: u8,
+    f: u8,
+    pc: u16,
+    operand: u8, // the byte at OPERAND
+}
+
+fn registers(state: &mut State8080) -> Registers {
+    Registers {
+        a: state.a,
+        b: state.b,
+        c: state.c,
+        d: state.d,
+        e: state.e,
+        h: state.h,
+        l: state.l,
+        f: state.get_psw(),
+        pc: state.pc,
+        operand: state.get_mem(OPERAND),
+    }
+}
+
+// puts a value in register r (0 = B ... 6 = M ... 7 = A), leaving HL pointing at OPERAND unless r is H or L
+fn set_register(regs: &mut Registers, r: u8, val: u8) {
+    match r {
+        0 => regs.b = val,
+        1 => regs.c = val,
+        2 => regs.d = val,
+        3 => regs.e = val,
+        4 => regs.h = val,
+        5 => regs.l = val,
+        6 => regs.operand = val,
+        _ => regs.a = val,
+    }
+}
+
+// runs one instruction from a known starting state, and returns the registers afterwards
+fn run(state: &mut State8080, program: &[u8], start: Registers) -> Registers {
+    for (i, byte) in program.iter().enumerate() {
+        state.set_mem(PROGRAM + i as u16, *byte);
+    }
+    state.a = start.a;
+    state.b = start.b;
+    state.c = start.c;
+    state.d = start.d;
+    state.e = start.e;
+    state.h = start.h;
+    state.l = start.l;
+    state.set_psw(start.f);
+    state.pc = start.pc;
+    state.set_mem(OPERAND, start.operand);
+    emulate(state);
+    registers(state)
+}
+
+fn initial(flags: u8) -> Registers {
+    Registers {
+        a: 0,
+        b: 0x5a,
+        c: 0xa5,
+        d: 0x3c,
+        e: 0xc3,
+        h: (OPERAND >> 8) as u8,
+        l: OPERAND as u8,
+        f: flags,
+        pc: PROGRAM,
+        operand: 0,
+    }
+}
+
+// runs an ALU operation in all of its register forms and its immediate form, for every input,
+// and returns a description of the first mismatch in each form
+fn check(op: Op) -> Vec<String> {
+    let mut state = State8080::new();
+    let mut failures: Vec<String> = Vec::new();
+
+    // 0..=7 are the register operands, 8 is the immediate
+    for r in 0..=8u8 {
+        let (program, name) = if r == 8 {
+            (vec![0xc6 + 8 * op.index(), 0], format!("{} nn", op.immediate_name()))
+        } else {
+            (vec![0x80 + 8 * op.index() + r], format!("{} {}", op.name(), REGISTERS[r as usize]))
+        };
+        'form: for flags in FLAGS_IN {
+            for a in 0..=255u8 {
+                for b in 0..=255u8 {
+                    // with A as the operand, the accumulator and operand are the same value
+                    if r == 7 && a != b {
+                        continue;
+                    }
+                    let mut start = initial(flags);
+                    start.a = a;
+                    let mut program = program.clone();
+                    if r == 8 {
+                        program[1] = b;
+                    } else {
+                        set_register(&mut start, r, b);
+                    }
+
+                    let (result, f) = reference(op, a, b, flags & CARRY != 0);
+                    let mut expected = start;
+                    expected.a = result;
+                    expected.f = f;
+                    expected.pc = PROGRAM + program.len() as u16;
+
+                    let actual = run(&mut state, &program, start);
+                    if actual != expected {
+                        failures.push(format!(
+                            "{}: a={:#04x} operand={:#04x} flags={:08b}\n    got      {:x?}\n    expected {:x?}",
+                            name, a, b, flags, actual, expected
+                        ));
+                        break 'form;
+                    }
+                }
+            }
+        }
+    }
+    failures
+}
+
+// INR and DCR on each register: carry is left alone, aux carry is the carry out of bit 3
+fn check_increment(decrement: bool) -> Vec<String> {
+    let mut state = State8080::new();
+    let mut failures: Vec<String> = Vec::new();
+    let op = if decrement { "DCR" } else { "INR" };
+
+    for r in 0..=7u8 {
+        let opcode = 0x04 + 8 * r + decrement as u8;
+        'form: for flags in FLAGS_IN {
+            for val in 0..=255u8 {
+                let mut start = initial(flags);
+                set_register(&mut start, r, val);
+
+                let (result, ac) = if decrement {
+                    (val.wrapping_sub(1), val & 0xf != 0)
+                } else {
+                    (val.wrapping_add(1), val & 0xf == 0xf)
+                };
+                let mut expected = start;
+                set_register(&mut expected, r, result);
+                expected.f = szp(result) | flag(ac, AUX_CARRY) | (flags & CARRY);
+                expected.pc = PROGRAM + 1;
+
+                let actual = run(&mut state, &[opcode], start);
+                if actual != expected {
+                    failures.push(format!(
+                        "{} {}: value={:#04x} flags={:08b}\n    got      {:x?}\n    expected {:x?}",
+                        op, REGISTERS[r as usize], val, flags, actual, expected
+                    ));
+                    break 'form;
+                }
+            }
+        }
+    }
+    failures
+}
+
+fn assert_none(failures: Vec<String>) {
+    assert!(failures.is_empty(), "{} forms differ from the reference:\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn add() {
+    assert_none(check(Op::Add));
+}
+
+#[test]
+fn adc() {
+    assert_none(check(Op::Adc));
+}
+
+#[test]
+fn sub() {
+    assert_none(check(Op::Sub));
+}
+
+#[test]
+fn sbb() {
+    assert_none(check(Op::Sbb));
+}
+
+#[test]
+fn ana() {
+    assert_none(check(Op::Ana));
+}
+
+#[test]
+fn xra() {
+    assert_none(check(Op::Xra));
+}
+
+#[test]
+fn ora() {
+    assert_none(check(Op::Ora));
+}
+
+#[test]
+fn cmp() {
+    assert_none(check(Op::Cmp));
+}
+
+#[test]
+fn inr() {
+    assert_none(check_increment(false));
+}
+
+#[test]
+fn dcr() {
+    assert_none(check_increment(true));
+}
@@ -0,0 +1,105 @@
+// the assembler turns Intel syntax into the bytes the emulator runs, and reads back what the disassembler writes
+use emulator_8080::assembler;
+use emulator_8080::cpm;
+use emulator_8080::listing;
+use emulator_8080::loader;
+
+fn bytes(source: &str) -> Vec<u8> {
+    match assembler::assemble(source) {
+        Ok(assembly) => assembly.program.bytes,
+        Err(errors) => panic!("{}", errors),
+    }
+}
+
+#[test]
+fn instructions() {
+    assert_eq!(bytes(" LXI H,2400h\n MOV A,M\n JNZ 1a3fh\n RST 7\n PUSH PSW\n"), [0x21, 0x00, 0x24, 0x7e, 0xc2, 0x3f, 0x1a, 0xff, 0xf5]);
+    // mnemonics, registers and hex digits in any case
+    assert_eq!(bytes(" mvi a,0FFh\n xra a\n"), [0x3e, 0xff, 0xaf]);
+}
+
+#[test]
+fn directives_and_expressions() {
+    let source = "
+SIZE    EQU last - first + 1    ; uses labels defined further down
+        ORG 8000h
+first:  DB 'AB', 'C' + 1, -1, 10 MOD 3, 1 SHL 4, NOT 0 AND 0fh
+        DW first, $, HIGH 1234h, LOW 1234h
+        DS 2
+last:   DB SIZE, 101b, 17o, 0x20
+";
+    let assembly = assembler::assemble(source).unwrap();
+    assert_eq!(assembly.program.origin, 0x8000);
+    assert_eq!(assembly.program.bytes, [
+        b'A', b'B', b'D', 0xff, 1, 0x10, 0x0f,
+        0x00, 0x80, 0x07, 0x80, 0x12, 0x00, 0x34, 0x00,
+        0x00, 0x00,
+        0x12, 5, 15, 0x20,
+    ]);
+    assert_eq!(assembly.symbols["SIZE"], 0x12);
+    assert!(assembly.symbol_file().contains("8011 LAST\n"));
+}
+
+#[test]
+fn local_labels() {
+    let source = "
+one:    MVI B,2
+.loop:  DCR B
+        JNZ .loop
+two:    MVI B,2
+.loop:  DCR B
+        JNZ .loop
+";
+    assert_eq!(bytes(source), [0x06, 0x02, 0x05, 0xc2, 0x02, 0x00, 0x06, 0x02, 0x05, 0xc2, 0x08, 0x00]);
+}
+
+#[test]
+fn errors_name_their_lines() {
+    let errors = assembler::assemble(" JMP nowhere\nx: NOP\nx: NOP\n MOV A,Q\n FOO\n MVI A,300\n").err().unwrap();
+    let errors: Vec<&str> = errors.lines().collect();
+    assert_eq!(errors, [
+        "line 1: 'NOWHERE' isn't defined",
+        "line 3: 'X' is defined more than once",
+        "line 4: MOV can't take 'A,Q'",
+        "line 5: unknown instruction 'FOO'",
+        "line 6: 300 doesn't fit in a byte",
+    ]);
+}
+
+#[test]
+fn runs_as_a_cpm_program() {
+    let source = "
+        ORG 100h
+        MVI C,9
+        LXI D,msg
+        CALL 5
+        RET
+msg:    DB 'assembled$'
+";
+    let mut out: Vec<u8> = Vec::new();
+    cpm::run_com(&bytes(source), &mut out).unwrap();
+    assert_eq!(out, b"assembled");
+}
+
+#[test]
+fn reassembles_a_disassembly() {
+    // code with data in the middle, an undocumented opcode, and a jump back to the start
+    let original = [
+        0xc3, 0x07, 0x01, b'H', b'i', b'$', 0x00, 0x11,
+        0x03, 0x01, 0xcd, 0x05, 0x00, 0xca, 0x11, 0x01,
+        0xcf, 0x76, 0x08, 0xc3, 0x00, 0x01,
+    ];
+    let program = loader::binary(&original, 0x0100).unwrap();
+    let source = listing::listing(&program, &[0x0100]);
+    let assembly = assembler::assemble(&source).unwrap();
+    assert_eq!(assembly.program, program, "\n{}", source);
+}
+
+#[test]
+fn writes_intel_hex() {
+    let assembly = assembler::assemble(" ORG 0f0h\n DS 8\n DB 1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20\n").unwrap();
+    let hex = loader::write_intel_hex(&assembly.program);
+    assert!(hex.starts_with(":1000F800"));
+    assert!(hex.ends_with(":00000001FF\n"));
+    assert_eq!(loader::intel_hex(&hex).unwrap(), assembly.program);
+}
@@ -0,0 +1,123 @@
+// breakpoints, watchpoints and port breakpoints, set and hit through the debugger's commands
+use emulator_8080::assembler;
+use emulator_8080::breakpoints::Condition;
+use emulator_8080::cpu::State8080;
+use emulator_8080::debugger::Debugger;
+use emulator_8080::machine::Bare;
+
+// fills 2000h-2003h with 4, 3, 2, 1, then reads 2002h back and writes it to port 3
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,100h
+        LXI H,2000h
+        MVI B,4
+loop:   MOV M,B
+        INX H
+        DCR B
+        JNZ loop
+        LDA 2002h
+        OUT 3
+        HLT
+";
+
+fn debugger() -> Debugger<Bare> {
+    let assembly = assembler::assemble(PROGRAM).unwrap();
+    Debugger::new(&assembly.program, Bare)
+}
+
+fn run(debugger: &mut Debugger<Bare>, commands: &[&str]) -> String {
+    let mut out: Vec<u8> = Vec::new();
+    for command in commands {
+        assert!(debugger.command(command, &mut out).unwrap());
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn breakpoints_stop_and_count_hits() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["b 8", "c"]);
+    assert!(out.contains("1: break 0008"), "{}", out);
+    assert!(out.contains("breakpoint 1: at 0008, hit once"), "{}", out);
+    assert_eq!(debugger.state.b, 4);
+
+    // continuing carries on from the breakpoint, and stops at it the next time round the loop
+    let out = run(&mut debugger, &["c"]);
+    assert!(out.contains("breakpoint 1: at 0008, hit 2 times"), "{}", out);
+    assert_eq!(debugger.state.b, 3);
+
+    // ignore lets hits pass, and disable turns it off altogether
+    let out = run(&mut debugger, &["ignore 1 1", "c", "bl"]);
+    assert!(out.contains("hit 4 times"), "{}", out);
+    assert!(out.contains("1: break 0008  (hit 4)"), "{}", out);
+    let out = run(&mut debugger, &["disable 1", "c"]);
+    assert!(out.starts_with("halted"), "{}", out);
+
+    let out = run(&mut debugger, &["delete 1", "bl", "delete 1"]);
+    assert!(out.contains("no breakpoints"), "{}", out);
+    assert!(out.contains("there is no breakpoint 1"), "{}", out);
+}
+
+#[test]
+fn conditions_decide_whether_a_breakpoint_hits() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["b 8 if b == 2 && !z", "c", "bl"]);
+    assert!(out.contains("breakpoint 1: at 0008, hit once"), "{}", out);
+    assert!(out.contains("1: break 0008 if b == 2 && !z  (hit 1)"), "{}", out);
+    assert_eq!(debugger.state.b, 2);
+
+    // m is the byte at hl, and [addr] any byte of memory
+    let out = run(&mut debugger, &["reset", "cond 1 [2000h] == 4 && hl == 2003", "c"]);
+    assert!(out.contains("hit 2 times"), "{}", out);
+    assert_eq!(debugger.state.b, 1);
+
+    let out = run(&mut debugger, &["b 0 if q == 1", "b 0 if a ==", "b 0 if (a"]);
+    assert!(out.contains("'q' isn't a register, flag or number"), "{}", out);
+    assert!(out.contains("the condition ends too soon"), "{}", out);
+    assert!(out.contains("the condition is missing a ')'"), "{}", out);
+}
+
+#[test]
+fn watchpoints_stop_after_memory_is_read_or_written() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["watch 2002", "c"]);
+    assert!(out.contains("breakpoint 1: 2002 written with 02, hit once"), "{}", out);
+    assert_eq!(debugger.state.pc, 0x0009);
+
+    let out = run(&mut debugger, &["delete 1", "watch 2000-20ff read", "c"]);
+    assert!(out.contains("breakpoint 2: 2002 read as 02, hit once"), "{}", out);
+    assert_eq!(debugger.state.a, 2);
+
+    // fetching instructions doesn't count as reading memory
+    let out = run(&mut debugger, &["reset", "delete 2", "watch 0-ff access if a != 0", "c"]);
+    assert!(out.contains("\nhalted\n"), "{}", out);
+}
+
+#[test]
+fn port_breakpoints_stop_before_in_and_out() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["port out 3 if a == 2", "c"]);
+    assert!(out.contains("breakpoint 1: OUT 02 to port 03, hit once"), "{}", out);
+    assert_eq!(debugger.state.pc, 0x0011);
+
+    let out = run(&mut debugger, &["port in 3", "port up 3", "watch 10 sideways"]);
+    assert!(out.contains("2: port in 03"), "{}", out);
+    assert!(out.contains("port needs in or out, and a port"), "{}", out);
+    assert!(out.contains("'sideways' isn't read, write or access"), "{}", out);
+}
+
+#[test]
+fn conditions_read_registers_flags_and_memory() {
+    let mut state = State8080::new();
+    state.a = 0x10;
+    state.cc.cy = true;
+    (state.h, state.l) = (0x12, 0x34);
+    state.memory[0x1234] = 0x99;
+    let holds = |text: &str| Condition::parse(text).unwrap().holds(&state);
+    assert!(holds("a == 0x10 && cy"));
+    assert!(holds("a == 10h || z"));
+    assert!(!holds("a == 10 && z"));
+    assert!(holds("m == 99 && [hl] == 99 && [1233 + 1] == 99"));
+    assert!(holds("hl - 34 == 1200 && (psw & 1) == 1"));
+    assert!(holds("a > 0f && a >= 10 && a < 11 && a <= 10 && a != 0"));
+}
@@ -0,0 +1,140 @@
+// the command line: its subcommands, their options, and the errors for ones it doesn't understand
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+
+fn emulator(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_emulator-8080")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+// writes a file for a test into the temp directory, named so that tests running at once don't share it
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cli-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn help_and_errors() {
+    let output = emulator(&[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).contains("commands:"));
+
+    let output = emulator(&["help", "run"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("--until-mem <address>=<byte>"), "{}", stdout(&output));
+
+    let output = emulator(&["run", "--cycle", "5", "x.asm"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("run doesn't have an option --cycle"), "{}", stderr(&output));
+    assert!(stderr(&output).contains("help run"));
+
+    let output = emulator(&["debug", "--origin"]);
+    assert!(stderr(&output).contains("--origin needs a value"), "{}", stderr(&output));
+    let output = emulator(&["frob", "x"]);
+    assert!(stderr(&output).contains("there is no command 'frob'"), "{}", stderr(&output));
+}
+
+#[test]
+fn run_with_an_entry_point_and_a_machine() {
+    let source = temp_file("entry.asm", "ORG 0\nMVI A,1\nHLT\nMVI A,2\nHLT\n");
+    let source = source.to_str().unwrap();
+    let output = emulator(&["run", "--entry", "3", "--until-halt", source]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("\"a\": 2"), "{}", stdout(&output));
+
+    // waiting for something that never happens fails the run
+    let output = emulator(&["run", "--machine", "invaders", "--until-pc", "1234", "--cycles", "100", source]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("\"exit\": \"deadlocked\""), "{}", stdout(&output));
+
+    // going back from the end of the run
+    let looping = temp_file("loop.asm", "ORG 0\nEI\nloop: JMP loop\n");
+    let output = emulator(&["run", "--machine", "invaders", "--frames", "10", "--rewind", "3", looping.to_str().unwrap()]);
+    assert!(stdout(&output).contains("\"frames\": 7"), "{}", stdout(&output));
+    std::fs::remove_file(looping).unwrap();
+
+    let output = emulator(&["run", "--machine", "z80", source]);
+    assert!(stderr(&output).contains("'z80' isn't a machine"), "{}", stderr(&output));
+    let output = emulator(&["run", "--speed", "fast", source]);
+    assert!(stderr(&output).contains("'fast' isn't a speed"), "{}", stderr(&output));
+    std::fs::remove_file(source).unwrap();
+}
+
+#[test]
+fn test_and_info() {
+    let source = temp_file("diag.asm", "ORG 100h\nMVI C,9\nLXI D,text\nCALL 5\nRET\ntext: DB 'CPU IS OPERATIONAL$'\n");
+    let com = source.with_extension("com");
+    let output = emulator(&["asm", source.to_str().unwrap(), "-o", com.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let output = emulator(&["test", com.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert!(stdout(&output).contains("diag.com: passed"), "{}", stdout(&output));
+    let output = emulator(&["test", "--expect", "ALL GOOD", com.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("FAILED"));
+
+    let output = emulator(&["info", com.to_str().unwrap()]);
+    assert!(stdout(&output).contains("0100h to"), "{}", stdout(&output));
+    assert!(stdout(&output).contains("CP/M"));
+
+    for extension in ["asm", "com", "lst", "sym"] {
+        std::fs::remove_file(source.with_extension(extension)).unwrap();
+    }
+}
+
+#[test]
+fn a_com_file_too_big_for_the_tpa() {
+    let com = temp_file("big.com", &"\0".repeat(0xfe80));
+    let com = com.to_str().unwrap();
+    for args in [&["test", com][..], &["--cpm", com], &["run", com]] {
+        let output = emulator(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&output).contains("has to fit in the 64768 bytes of the TPA"), "{}", stderr(&output));
+    }
+    std::fs::remove_file(com).unwrap();
+}
+
+#[test]
+fn a_hexdump_that_never_halts_still_ends() {
+    // JMP 0
+    let dump = temp_file("loop.hexdump", "0000000 c300 0000\n");
+    let output = emulator(&[dump.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(stdout(&output).contains("\"exit\": \"cycle limit\""), "{}", stdout(&output));
+    std::fs::remove_file(dump).unwrap();
+}
+
+#[test]
+fn record_and_replay_a_movie() {
+    // a game that adds up its inputs every frame
+    let source = temp_file("game.asm", "ORG 0\nEI\nloop: IN 1\nLXI H,2000h\nADD M\nMOV M,A\nJMP loop\nORG 10h\nEI\nRET\n");
+    let rom = source.with_extension("bin");
+    let output = emulator(&["asm", source.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let script = temp_file("game.script", "30\n5 coin\n10 p1-start p1-fire\n");
+
+    let output = emulator(&["record", rom.to_str().unwrap(), script.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Recorded 45 frames"), "{}", stdout(&output));
+    let movie = script.with_extension("movie");
+    let output = emulator(&["replay", rom.to_str().unwrap(), movie.to_str().unwrap()]);
+    assert!(stdout(&output).contains("matches the recording for all 45 frames"), "{}", stdout(&output));
+
+    std::fs::write(&script, "5 jump\n").unwrap();
+    let output = emulator(&["record", rom.to_str().unwrap(), script.to_str().unwrap()]);
+    assert!(stderr(&output).contains("line 1: 'jump' isn't a button"), "{}", stderr(&output));
+
+    for extension in ["asm", "bin", "lst", "sym", "script", "movie"] {
+        std::fs::remove_file(source.with_extension(extension)).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+// runs the classic 8080 diagnostics through the emulator as CP/M programs, and checks that every one passes.
+// the programs themselves aren't part of the repository, so a test fails if its program is missing: fetch them with
+// tests/roms/fetch.sh (see tests/roms/README.md).
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use emulator_8080::cpm;
+
+// the CRC that 8080EXM computes for each group of instructions on a real 8080
+const EXERCISER_CRCS: [(&str, &str); 25] = [
+    ("dad <b,d,h,sp>", "14474ba6"),
+    ("aluop nn", "9e922f9e"),
+    ("aluop <b,c,d,e,h,l,m,a>", "cf762c86"),
+    ("<daa,cma,stc,cmc>", "bb3f030c"),
+    ("<inr,dcr> a", "adb6460e"),
+    ("<inr,dcr> b", "83ed1345"),
+    ("<inx,dcx> b", "f79287cd"),
+    ("<inr,dcr> c", "e5f6721b"),
+    ("<inr,dcr> d", "15b5579a"),
+    ("<inx,dcx> d", "7f4e2501"),
+    ("<inr,dcr> e", "cf2ab396"),
+    ("<inr,dcr> h", "12b2952c"),
+    ("<inx,dcx> h", "9f2b23c0"),
+    ("<inr,dcr> l", "ff57d356"),
+    ("<inr,dcr> m", "92e963bd"),
+    ("<inx,dcx> sp", "d5702fab"),
+    ("lhld nnnn", "a9c3d5cb"),
+    ("shld nnnn", "e8864f26"),
+    ("lxi <b,d,h,sp>,nnnn", "fcf46e12"),
+    ("ldax <b,d>", "2b821d5f"),
+    ("mvi <b,c,d,e,h,l,m,a>,nn", "eaa72044"),
+    ("mov <bcdehla>,<bcdehla>", "10b58cee"),
+    ("sta nnnn / lda nnnn", "ed57af72"),
+    ("<rlc,rrc,ral,rar>", "e0d89235"),
+    ("stax <b,d>", "2b0471e9"),
+];
+
+// runs the program from the rom directory and returns its console output
+fn run(name: &str) -> String {
+    let dir = match env::var("EMULATOR_8080_ROMS") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("roms"),
+    };
+    let path = dir.join(name);
+    let program = fs::read(&path).unwrap_or_else(|err| {
+        panic!("can't read {}: {} (run tests/roms/fetch.sh to download the test programs)", path.display(), err)
+    });
+
+    let mut out: Vec<u8> = Vec::new();
+    cpm::run_com(&program, &mut out).unwrap();
+    let out = String::from_utf8_lossy(&out).into_owned();
+    println!("{}", out);
+    out
+}
+
+#[test]
+fn bdos_console_output() {
+    // MVI C,9; LXI D,msg; CALL 5; MVI C,2; MVI E,'!'; CALL 5; RET; msg: "8080$"
+    let program = [
+        0x0e, 0x09, 0x11, 0x10, 0x01, 0xcd, 0x05, 0x00,
+        0x0e, 0x02, 0x1e, b'!', 0xcd, 0x05, 0x00, 0xc9,
+        b'8', b'0', b'8', b'0', b'$',
+    ];
+    let mut out: Vec<u8> = Vec::new();
+    cpm::run_com(&program, &mut out).unwrap();
+    assert_eq!(out, b"8080!");
+}
+
+#[test]
+fn tst8080() {
+    let out = run("TST8080.COM");
+    assert!(out.contains("CPU IS OPERATIONAL"), "TST8080 failed:\n{}", out);
+}
+
+#[test]
+fn cputest() {
+    let out = run("CPUTEST.COM");
+    assert!(out.contains("CPU TESTS OK"), "CPUTEST failed:\n{}", out);
+}
+
+#[test]
+fn preliminary_exerciser() {
+    let out = run("8080PRE.COM");
+    assert!(out.contains("Preliminary tests complete"), "8080PRE failed:\n{}", out);
+}
+
+#[test]
+#[ignore = "runs billions of instructions; use cargo test --release -- --ignored"]
+fn instruction_exerciser() {
+    let out = run("8080EXM.COM");
+
+    // each group prints "<name>....  PASS! crc is:<crc>", or "<name>....  ERROR **** crc expected:<crc> found:<crc>"
+    let mut failures: Vec<String> = Vec::new();
+    for (name, crc) in EXERCISER_CRCS.iter() {
+        let line = out.lines().find(|line| line.trim_start().starts_with(&format!("{}.", name)));
+        match line {
+            Some(line) if line.contains(&format!("PASS! crc is:{}", crc)) => {},
+            Some(line) => failures.push(line.trim().to_string()),
+            None => failures.push(format!("{}: no result", name)),
+        }
+    }
+    assert!(failures.is_empty(), "8080EXM failed:\n{}", failures.join("\n"));
+    assert!(out.contains("Tests complete"), "8080EXM didn't finish:\n{}", out);
+}
@@ -0,0 +1,106 @@
+// the BIOS of the CP/M machine, booted from disk images in the temp directory, or from a system image of test code
+// that runs in place of the CCP
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use emulator_8080::assembler;
+use emulator_8080::cpm::disk::Disk;
+use emulator_8080::cpm::disk::DiskParams;
+use emulator_8080::cpm::disk::SECTOR_SIZE;
+use emulator_8080::cpm::system::CpmSystem;
+use emulator_8080::cpm::system::Drive;
+
+const CCP: usize = 0xe400;
+
+// selects drive A and translates logical sectors 2 and 25 with its table, into 80h and 82h
+const SECTRAN: &str = "
+        ORG 0e400h
+        MVI C,0
+        CALL 0fa1bh     ; SELDSK
+        MOV E,M
+        INX H
+        MOV D,M         ; the translation table, from the first word of the DPH
+        LXI B,2
+        CALL 0fa30h     ; SECTRAN
+        SHLD 80h
+        LXI B,25
+        CALL 0fa30h
+        SHLD 82h
+        HLT
+";
+
+// writes an image for a test, named so that tests running at once don't share it
+fn image(name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cpm-system-{}-{}.img", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn drive(path: &Path, params: DiskParams) -> Drive {
+    Drive::Image(Disk::open(path.to_str().unwrap(), params).unwrap())
+}
+
+// boots and runs until the test code halts
+fn run(cpm: &mut CpmSystem) {
+    cpm.boot();
+    for _ in 0..10000 {
+        if cpm.state.halted {
+            return;
+        }
+        assert!(cpm.step().unwrap());
+    }
+    panic!("still running at {:04x}", cpm.state.pc);
+}
+
+#[test]
+fn sectran_follows_the_skew() {
+    let system = assembler::assemble(SECTRAN).unwrap().program.bytes;
+    let path = image("skew", &[]);
+    for (params, expected) in [(DiskParams::ibm_3740(), [13, 22]), (DiskParams::hd_4mb(), [2, 25])] {
+        let mut cpm = CpmSystem::new(vec![drive(&path, params)], Some(system.clone())).unwrap();
+        run(&mut cpm);
+        assert_eq!([cpm.state.memory[0x80], cpm.state.memory[0x82]], expected);
+        assert_eq!([cpm.state.memory[0x81], cpm.state.memory[0x83]], [0, 0]);
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn the_system_tracks_are_loaded_from_drive_a() {
+    // two tracks of 26 sectors, each filled with its number. the first is the cold boot loader, and the CCP and
+    // BDOS are the 44 after it.
+    let mut contents = Vec::new();
+    for sector in 0..52u8 {
+        contents.extend_from_slice(&[sector; SECTOR_SIZE]);
+    }
+    let path = image("system", &contents);
+    let mut cpm = CpmSystem::new(vec![drive(&path, DiskParams::ibm_3740())], None).unwrap();
+    cpm.boot();
+    assert_eq!(cpm.state.pc, CCP as u16);
+    assert!(cpm.state.memory[CCP..CCP + 44 * SECTOR_SIZE] == contents[SECTOR_SIZE..45 * SECTOR_SIZE]);
+
+    // a disk without system tracks can't be booted from
+    let Err(error) = CpmSystem::new(vec![drive(&path, DiskParams::hd_4mb())], None) else {
+        panic!("booted from a disk without system tracks");
+    };
+    assert!(error.to_string().contains("no room for CP/M"), "{}", error);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn the_bios_has_to_fit_in_memory() {
+    let path = image("overflow", &[]);
+    // the disk parameter headers and allocation vectors of two hard disks fit above the BIOS, but not of sixteen
+    let drives = |n: usize| (0..n).map(|_| drive(&path, DiskParams::hd_4mb())).collect::<Vec<Drive>>();
+    assert!(CpmSystem::new(drives(2), Some(Vec::new())).is_ok());
+    let Err(error) = CpmSystem::new(drives(16), Some(Vec::new())) else {
+        panic!("sixteen hard disks fitted");
+    };
+    assert!(error.to_string().contains("don't fit in memory"), "{}", error);
+
+    // nor does a check vector as big as this one
+    let params = DiskParams::parse("spt=26,bsh=3,blm=7,exm=0,dsm=242,drm=63,al0=0xc0,al1=0,cks=4096,off=2").unwrap();
+    assert!(CpmSystem::new(vec![drive(&path, params)], Some(Vec::new())).is_err());
+    fs::remove_file(path).unwrap();
+}
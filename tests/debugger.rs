@@ -0,0 +1,105 @@
+// the debugger's commands, run the way the REPL runs them, one line at a time
+use emulator_8080::assembler;
+use emulator_8080::debugger::Debugger;
+use emulator_8080::machine::Machine;
+
+// remembers what the program wrote to its ports, and reads back 0x42
+#[derive(Default)]
+struct Ports {
+    written: Vec<(u8, u8)>,
+}
+
+impl Machine for Ports {
+    fn input(&mut self, _port: u8) -> u8 {
+        0x42
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        self.written.push((port, value));
+    }
+}
+
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,100h
+        MVI B,3
+loop:   DCR B
+        MOV A,B
+        OUT 1
+        JNZ loop
+        IN 2
+        HLT
+";
+
+fn debugger() -> Debugger<Ports> {
+    let assembly = assembler::assemble(PROGRAM).unwrap();
+    Debugger::new(&assembly.program, Ports::default())
+}
+
+// runs the commands, and returns what they printed
+fn run(debugger: &mut Debugger<Ports>, commands: &[&str]) -> String {
+    let mut out: Vec<u8> = Vec::new();
+    for command in commands {
+        assert!(debugger.command(command, &mut out).unwrap());
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn step_and_show_registers() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["s", "s", "r"]);
+    assert!(out.contains("> 0003  06 03     MVI B,03h"), "{}", out);
+    assert!(out.contains("A=00 BC=0300 DE=0000 HL=0000 SP=0100 PC=0005  flags - - - - -  DI"), "{}", out);
+
+    // an empty line repeats the last step
+    // (DCR B, MOV A,B, then OUT 1, JNZ loop)
+    run(&mut debugger, &["s 2", ""]);
+    assert_eq!((debugger.state.b, debugger.state.pc), (0x02, 0x0005));
+}
+
+#[test]
+fn continue_until_halted() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["c"]);
+    assert!(out.starts_with("halted\n"), "{}", out);
+    assert!(debugger.state.halted);
+    assert_eq!(debugger.state.a, 0x42);
+    assert_eq!(debugger.machine.written, [(1, 2), (1, 1), (1, 0)]);
+
+    // reset goes back to the loaded program, and continue can be limited
+    let out = run(&mut debugger, &["reset", "c 3"]);
+    assert!(out.contains("stopped after 3 instructions"), "{}", out);
+    assert_eq!(debugger.state.pc, 0x0006);
+}
+
+#[test]
+fn examine_and_change_memory_and_registers() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["w 200 48 69", "m 200 4"]);
+    assert!(out.contains("0200  48 69 00 00"), "{}", out);
+    assert!(out.contains("Hi.."), "{}", out);
+
+    run(&mut debugger, &["set hl 1234", "set a ff", "set cy 1", "set pc 5"]);
+    assert_eq!((debugger.state.h, debugger.state.l, debugger.state.a), (0x12, 0x34, 0xff));
+    assert!(debugger.state.cc.cy);
+    assert_eq!(debugger.state.pc, 0x0005);
+
+    let out = run(&mut debugger, &["set a 100", "set q 1", "frobnicate"]);
+    assert!(out.contains("100 doesn't fit in a"), "{}", out);
+    assert!(out.contains("unknown register 'q'"), "{}", out);
+    assert!(out.contains("unknown command 'frobnicate'"), "{}", out);
+}
+
+#[test]
+fn disassemble_around_the_pc() {
+    let mut debugger = debugger();
+    run(&mut debugger, &["s 3"]);
+    let out = run(&mut debugger, &["d"]);
+    let lines: Vec<&str> = out.lines().collect();
+    // up to 3 instructions before the pc, then the pc and onwards
+    assert_eq!(lines[0], "  0000  31 00 01  LXI SP,0100h");
+    assert_eq!(lines[2], "  0005  05        DCR B");
+    assert_eq!(lines[3], "> 0006  78        MOV A,B");
+    assert_eq!(lines.len(), 10);
+}
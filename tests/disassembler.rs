@@ -0,0 +1,61 @@
+// the disassembler names instructions the Intel way, and agrees with the cpu on how long each one is
+use emulator_8080::cpu::emulate;
+use emulator_8080::cpu::State8080;
+use emulator_8080::disassembler;
+
+fn text(bytes: &[u8]) -> String {
+    disassembler::disassemble(bytes, 0).to_string()
+}
+
+#[test]
+fn mnemonics() {
+    assert_eq!(text(&[0x21, 0x00, 0x24]), "LXI H,2400h");
+    assert_eq!(text(&[0xc2, 0x3f, 0x1a]), "JNZ 1a3fh");
+    assert_eq!(text(&[0xc3, 0x00, 0xe0]), "JMP 0e000h");
+    assert_eq!(text(&[0x3e, 0xff]), "MVI A,0ffh");
+    assert_eq!(text(&[0xd3, 0x03]), "OUT 03h");
+    assert_eq!(text(&[0x7e]), "MOV A,M");
+    assert_eq!(text(&[0xf5]), "PUSH PSW");
+    assert_eq!(text(&[0xef]), "RST 5");
+    assert_eq!(text(&[0xcb, 0x34, 0x12]), "*JMP 1234h");
+}
+
+#[test]
+fn operands_wrap_around_memory() {
+    let mut memory = vec![0u8; 0x10000];
+    memory[0xffff] = 0x01; // LXI B
+    memory[0x0000] = 0xcd;
+    memory[0x0001] = 0xab;
+    let instruction = disassembler::disassemble(&memory, 0xffff);
+    assert_eq!(instruction.to_string(), "LXI B,0abcdh");
+    assert_eq!(instruction.length(), 3);
+}
+
+#[test]
+fn lengths_match_the_cpu() {
+    // JMP, CALL, RET, PCHL and HLT (and their undocumented twins) never just move on to the next instruction
+    let always_transfer = [0xc3, 0xcb, 0xcd, 0xdd, 0xed, 0xfd, 0xc9, 0xd9, 0xe9, 0x76];
+
+    let mut state = State8080::new();
+    for opcode in 0..=255u8 {
+        let is_rst = opcode & 0xc7 == 0xc7;
+        if always_transfer.contains(&opcode) || is_rst {
+            continue;
+        }
+        let instruction = disassembler::disassemble(&[opcode, 0x00, 0x00], 0);
+
+        // a conditional jump, call or return falls through with one of the sets of flags
+        let mut lengths: Vec<u16> = Vec::new();
+        for psw in [0x02, 0xd7] {
+            state.memory[0x0100] = opcode;
+            state.memory[0x0101] = 0;
+            state.memory[0x0102] = 0;
+            state.pc = 0x0100;
+            state.sp = 0x2000;
+            state.set_psw(psw);
+            emulate(&mut state);
+            lengths.push(state.pc.wrapping_sub(0x0100));
+        }
+        assert!(lengths.contains(&instruction.length()), "{:02x} {}: the cpu moved on by {:?}", opcode, instruction, lengths);
+    }
+}
@@ -0,0 +1,110 @@
+// CP/M disk formats, and disk images read and written in the temp directory
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use emulator_8080::cpm::disk::Disk;
+use emulator_8080::cpm::disk::DiskParams;
+use emulator_8080::cpm::disk::SECTOR_SIZE;
+
+const FIELDS: &str = "spt=26,bsh=3,blm=7,exm=0,dsm=242,drm=63,al0=0xc0,al1=0,cks=16,off=2";
+
+// writes an image for a test, named so that tests running at once don't share it
+fn image(name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("disk-{}-{}.img", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn the_named_formats() {
+    let ibm = DiskParams::parse("ibm3740").unwrap();
+    assert_eq!((ibm.spt, ibm.bsh, ibm.blm, ibm.dsm, ibm.drm, ibm.cks, ibm.off), (26, 3, 7, 242, 63, 16, 2));
+    assert_eq!(ibm.skew.as_ref().map(Vec::len), Some(26));
+    assert_eq!(ibm.first_sector(), 1);
+
+    let hd = DiskParams::parse("hd4mb").unwrap();
+    assert_eq!((hd.spt, hd.bsh, hd.blm, hd.dsm, hd.drm, hd.cks, hd.off), (32, 4, 15, 2039, 1023, 0, 0));
+    assert!(hd.skew.is_none());
+    assert_eq!(hd.first_sector(), 0);
+}
+
+#[test]
+fn a_format_given_by_its_fields() {
+    // in any order, in decimal or hex, with spaces around them
+    let params = DiskParams::parse("off=0, cks=0, al1=0xff, al0=0xff, drm=1023, dsm=0x7f7, exm=0, blm=15, bsh=4, spt=32").unwrap();
+    assert_eq!(params.dpb(), DiskParams::hd_4mb().dpb());
+    assert!(params.skew.is_none());
+    assert_eq!(DiskParams::parse(FIELDS).unwrap().dpb(), [26, 0, 3, 7, 0, 242, 0, 63, 0, 0xc0, 0, 16, 0, 2, 0]);
+
+    for (text, error) in [
+        ("ibm", "expected name=value, found \"ibm\""),
+        ("spt=x", "invalid value for spt: \"x\""),
+        ("spt=26,bsh=3", "missing DPB field \"blm\""),
+        (&format!("{},sides=2", FIELDS), "unknown DPB field \"sides\""),
+        (&FIELDS.replace("al0=0xc0", "al0=0x100"), "al0 must fit in a byte"),
+    ] {
+        assert_eq!(DiskParams::parse(text).err().as_deref(), Some(error), "{}", text);
+    }
+}
+
+#[test]
+fn bad_block_sizes_are_rejected() {
+    // blocks smaller than 1K or bigger than 16K, and masks that don't go with the shift
+    for (bsh, blm) in [(2, 3), (8, 255), (4, 7), (3, 15)] {
+        let text = FIELDS.replace("bsh=3,blm=7", &format!("bsh={},blm={}", bsh, blm));
+        let error = DiskParams::parse(&text).err().unwrap();
+        assert!(error.contains("bsh and blm"), "{}: {}", text, error);
+    }
+    assert!(DiskParams::parse(&FIELDS.replace("spt=26", "spt=0")).is_err());
+}
+
+#[test]
+fn past_the_end_of_the_image_reads_as_formatted() {
+    // the image ends 100 bytes into the second sector
+    let path = image("short", &[[1; SECTOR_SIZE].as_slice(), &[2; 100]].concat());
+    let mut disk = Disk::open(path.to_str().unwrap(), DiskParams::ibm_3740()).unwrap();
+    let mut buf = [0; SECTOR_SIZE];
+    disk.read_sector(0, 1, &mut buf).unwrap();
+    assert_eq!(buf, [1; SECTOR_SIZE]);
+    disk.read_sector(0, 2, &mut buf).unwrap();
+    assert_eq!((buf[..100].to_vec(), buf[100..].to_vec()), (vec![2; 100], vec![0xe5; SECTOR_SIZE - 100]));
+    disk.read_sector(76, 26, &mut buf).unwrap();
+    assert_eq!(buf, [0xe5; SECTOR_SIZE]);
+
+    // sectors number from 1 on a disk with a skew, and there are only spt of them
+    for sector in [0, 27] {
+        assert_eq!(disk.read_sector(0, sector, &mut buf).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn writing_grows_the_image() {
+    let path = image("write", &[]);
+    let mut disk = Disk::open(path.to_str().unwrap(), DiskParams::hd_4mb()).unwrap();
+    disk.write_sector(1, 2, &[7; SECTOR_SIZE]).unwrap();
+    // track 1, sector 2 of 32 sectors numbered from 0
+    let offset = (32 + 2) * SECTOR_SIZE;
+    assert_eq!(fs::metadata(&path).unwrap().len(), (offset + SECTOR_SIZE) as u64);
+    let mut buf = [0; SECTOR_SIZE];
+    disk.read_sector(1, 2, &mut buf).unwrap();
+    assert_eq!(buf, [7; SECTOR_SIZE]);
+    // the gap before it is zeros, as the host fills it
+    disk.read_sector(0, 0, &mut buf).unwrap();
+    assert_eq!(buf, [0; SECTOR_SIZE]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn a_read_only_disk_cant_be_written() {
+    let path = image("read-only", &[0x42; 4 * SECTOR_SIZE]);
+    let mut disk = Disk::open_read_only(path.to_str().unwrap(), DiskParams::ibm_3740()).unwrap();
+    let error = disk.write_sector(0, 1, &[0; SECTOR_SIZE]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    let mut buf = [0; SECTOR_SIZE];
+    disk.read_sector(0, 1, &mut buf).unwrap();
+    assert_eq!(buf, [0x42; SECTOR_SIZE]);
+    assert_eq!(fs::read(&path).unwrap(), [0x42; 4 * SECTOR_SIZE]);
+    fs::remove_file(path).unwrap();
+}
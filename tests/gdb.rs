@@ -0,0 +1,123 @@
+// the gdb stub, given packets directly and over a tcp connection like gdb would send them
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+
+use emulator_8080::assembler;
+use emulator_8080::debugger::Debugger;
+use emulator_8080::gdb;
+use emulator_8080::gdb::Stub;
+use emulator_8080::machine::Bare;
+
+// fills 2000h-2003h with 4, 3, 2, 1, then reads 2002h back
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,100h
+        LXI H,2000h
+        MVI B,4
+loop:   MOV M,B
+        INX H
+        DCR B
+        JNZ loop
+        LDA 2002h
+        HLT
+";
+
+fn debugger() -> Debugger<Bare> {
+    let assembly = assembler::assemble(PROGRAM).unwrap();
+    Debugger::new(&assembly.program, Bare)
+}
+
+// sends each packet, and returns the replies
+fn send(debugger: &mut Debugger<Bare>, packets: &[&str]) -> Vec<String> {
+    let mut stub = Stub { debugger };
+    packets.iter().map(|packet| stub.handle(packet, &mut || false).unwrap_or_default()).collect()
+}
+
+#[test]
+fn registers_are_read_and_written_in_the_layout() {
+    let mut debugger = debugger();
+    let replies = send(&mut debugger, &["s", "s", "g", "p5", "P3=3412", "p3"]);
+    assert_eq!(replies[0], "S05");
+    // psw, bc, de, hl, sp, pc, each little-endian
+    assert_eq!(replies[2], "020000000000002000010600");
+    assert_eq!(replies[3], "0600");
+    assert_eq!(replies[4], "OK");
+    assert_eq!((debugger.state.h, debugger.state.l), (0x12, 0x34));
+
+    let replies = send(&mut debugger, &["Gd70f0302040506070001ffff", "p0", "p6", "Gzz"]);
+    assert_eq!(replies[0], "OK");
+    assert_eq!((debugger.state.a, debugger.state.b, debugger.state.c), (0x0f, 0x02, 0x03));
+    assert!(debugger.state.cc.s && debugger.state.cc.z && debugger.state.cc.cy);
+    assert_eq!((debugger.state.sp, debugger.state.pc), (0x0100, 0xffff));
+    assert_eq!(replies[1], "d70f");
+    assert_eq!(&replies[2..], ["E01", "E01"]);
+}
+
+#[test]
+fn memory_is_read_and_written() {
+    let mut debugger = debugger();
+    let replies = send(&mut debugger, &["m0,3", "M2000,2:abcd", "m1fff,4", "m10000,1", "M2000,2:ab"]);
+    assert_eq!(replies[0], "310001");
+    assert_eq!(replies[1], "OK");
+    assert_eq!(replies[2], "00abcd00");
+    assert_eq!(&replies[3..], ["E01", "E01"]);
+}
+
+#[test]
+fn breakpoints_and_watchpoints_stop_a_continue() {
+    let mut debugger = debugger();
+    let replies = send(&mut debugger, &["Z0,b,1", "c", "c", "z0,b,1"]);
+    assert_eq!(replies, ["OK", "S05", "S05", "OK"]);
+    assert_eq!((debugger.state.pc, debugger.state.b), (0x000b, 0x02));
+
+    let replies = send(&mut debugger, &["Z2,2002,1", "c"]);
+    assert_eq!(replies[1], "T05watch:2002;");
+    assert_eq!(debugger.state.pc, 0x0009);
+
+    let replies = send(&mut debugger, &["z2,2002,1", "Z3,2000,4", "c"]);
+    assert_eq!(replies[2], "T05rwatch:2002;");
+    assert_eq!(debugger.state.a, 2);
+
+    // then it runs on to the HLT
+    let replies = send(&mut debugger, &["c", "Z9,0,1"]);
+    assert_eq!(replies, ["S05", ""]);
+    assert!(debugger.state.halted);
+}
+
+#[test]
+fn a_session_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let mut debugger = debugger();
+        let (stream, _) = listener.accept().unwrap();
+        gdb::serve(&mut debugger, stream).unwrap();
+        debugger.state.pc
+    });
+
+    let mut client = TcpStream::connect(address).unwrap();
+    let mut exchange = |packet: &str| -> String {
+        let sum = packet.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(client, "${}#{:02x}", packet, sum).unwrap();
+        // the ack, then the reply
+        let mut reply: Vec<u8> = Vec::new();
+        let mut byte = [0u8];
+        while !reply.ends_with(b"#") {
+            client.read_exact(&mut byte).unwrap();
+            reply.push(byte[0]);
+        }
+        let mut checksum = [0u8; 2];
+        client.read_exact(&mut checksum).unwrap();
+        assert_eq!(reply[0], b'+');
+        String::from_utf8(reply[2..reply.len() - 1].to_vec()).unwrap()
+    };
+    assert!(exchange("qSupported:multiprocess+").contains("qXfer:features:read+"));
+    assert!(exchange("qXfer:features:read:target.xml:0,1000").contains(r#"<reg name="pc" bitsize="16" type="code_ptr"/>"#));
+    assert_eq!(exchange("?"), "S05");
+    assert_eq!(exchange("Z0,e,1"), "OK");
+    assert_eq!(exchange("c"), "S05");
+    assert_eq!(exchange("D"), "OK");
+    assert_eq!(server.join().unwrap(), 0x000e);
+}
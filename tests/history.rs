@@ -0,0 +1,126 @@
+// the history of the last instructions, and the call stack rebuilt from the calls and returns
+use emulator_8080::assembler;
+use emulator_8080::cpu::State8080;
+use emulator_8080::debugger::Debugger;
+use emulator_8080::history::History;
+use emulator_8080::history::Via;
+use emulator_8080::machine;
+use emulator_8080::machine::Bare;
+
+// main calls outer, which restarts into inner at 8; inner halts.
+// a conditional call that isn't taken, and a routine that returns, shouldn't leave frames behind.
+const PROGRAM: &str = "
+        ORG 0
+        JMP main
+        ORG 8
+inner:  HLT
+        ORG 20h
+main:   LXI SP,100h
+        XRA A
+        CNZ outer
+        CALL leaf
+        CALL outer
+leaf:   RET
+outer:  NOP
+        RST 1
+";
+
+fn debugger() -> Debugger<Bare> {
+    let assembly = assembler::assemble(PROGRAM).unwrap();
+    Debugger::new(&assembly.program, Bare)
+}
+
+fn run(debugger: &mut Debugger<Bare>, commands: &[&str]) -> String {
+    let mut out: Vec<u8> = Vec::new();
+    for command in commands {
+        assert!(debugger.command(command, &mut out).unwrap());
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn the_call_stack_follows_calls_restarts_and_returns() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["c", "bt"]);
+    let stack = &debugger.history.stack;
+    assert_eq!(stack.len(), 2);
+    assert_eq!((stack[0].via, stack[0].from, stack[0].target, stack[0].ret), (Via::Call, 0x002a, 0x002e, 0x002d));
+    assert_eq!((stack[1].via, stack[1].from, stack[1].target, stack[1].ret), (Via::Restart, 0x002f, 0x0008, 0x0030));
+    assert!(out.contains("#0  0009\n#1  0008 from 002f by RST, returns to 0030 (sp 00fc)\n#2  002e from 002a by CALL, returns to 002d (sp 00fe)\n"), "{}", out);
+}
+
+#[test]
+fn the_ring_buffer_keeps_the_last_instructions() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["history size 3", "c", "history"]);
+    let lines: Vec<&str> = out.lines().filter(|line| line.starts_with("PC=")).collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("PC=002e "), "{}", out);
+    assert!(lines[1].starts_with("PC=002f "), "{}", out);
+    assert!(lines[2].ends_with("HLT"), "{}", out);
+
+    // reset forgets it all
+    run(&mut debugger, &["reset"]);
+    assert!(debugger.history.entries.is_empty() && debugger.history.stack.is_empty());
+}
+
+#[test]
+fn shown_when_the_program_stops() {
+    let mut debugger = debugger();
+    let out = run(&mut debugger, &["history auto on", "b 2d", "c"]);
+    assert!(out.contains("breakpoint 1: at 002d, hit once\n#0  002d\n#1  002d from 0027 by CALL"), "{}", out);
+    assert!(out.contains("PC=0027 AF=0046 BC=0000 DE=0000 HL=0000 SP=0100 CYC=35  cd 2d 00  CALL 002dh"), "{}", out);
+
+    // but not when a continue just runs out of instructions
+    let out = run(&mut debugger, &["c 1"]);
+    assert!(!out.contains("#0"), "{}", out);
+}
+
+#[test]
+fn interrupts_are_frames_too() {
+    let mut state = State8080::new();
+    let mut history = History::new(10);
+    (state.sp, state.pc, state.int_enable) = (0x0100, 0x1234, true);
+    assert!(machine::interrupt(&mut state, 2));
+    history.interrupted(&state, 0x1234);
+    assert_eq!(history.backtrace(state.pc), "#0  0010\n#1  0010 from 1234 by interrupt, returns to 1234 (sp 00fe)\n");
+
+    // RET from the handler pops it
+    state.memory[0x0010] = 0xc9;
+    history.before(&state, 0);
+    machine::step(&mut state, &mut Bare);
+    history.after(&state);
+    assert!(history.stack.is_empty());
+}
+
+// main loops with interrupts enabled until the frame interrupt halfway through the frame restarts it at 8, where the
+// handler calls a routine that halts
+const LOOPING: &str = "
+        ORG 0
+        JMP main
+        ORG 8
+isr:    CALL sub
+        ORG 20h
+main:   LXI SP,100h
+        EI
+loop:   JMP loop
+sub:    HLT
+";
+
+#[test]
+fn a_backtrace_in_an_interrupt_handler_shows_the_interrupt() {
+    let assembly = assembler::assemble(LOOPING).unwrap();
+    let mut debugger = Debugger::new(&assembly.program, Bare);
+    debugger.frame_interrupts = true;
+    let expected = "#0  0028\n#1  0027 from 0008 by CALL, returns to 000b (sp 00fc)\n#2  0008 from 0024 by interrupt, returns to 0024 (sp 00fe)\n";
+    let out = run(&mut debugger, &["c", "bt"]);
+    assert!(out.contains("halted\n"), "{}", out);
+    assert!(out.contains(expected), "{}", out);
+
+    // going back over the interrupt takes its frame away, and running forward again interrupts in the same place
+    let out = run(&mut debugger, &["rs 3", "bt"]);
+    assert!(out.contains("#0  0024\n"), "{}", out);
+    assert!(debugger.history.stack.is_empty());
+    let out = run(&mut debugger, &["c", "bt"]);
+    assert!(out.contains(expected), "{}", out);
+}
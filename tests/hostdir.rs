@@ -0,0 +1,131 @@
+// the BDOS file functions for a host directory mounted as a drive, run against a directory in the temp directory
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use emulator_8080::cpu::State8080;
+use emulator_8080::cpm::hostdir::HostDrive;
+
+const FCB: u16 = 0x5c;
+const DMA: u16 = 0x80;
+
+// makes an empty directory for a test, named so that tests running at once don't share it
+fn temp_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("hostdir-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    path
+}
+
+// fills in the FCB at addr with the name, as 8 + 3 bytes padded with spaces, and clears the rest of it
+fn set_fcb(state: &mut State8080, addr: u16, name: &[u8; 11]) {
+    for i in 0..36u16 {
+        state.set_mem(addr.wrapping_add(i), 0);
+    }
+    for (i, c) in name.iter().enumerate() {
+        state.set_mem(addr.wrapping_add(1 + i as u16), *c);
+    }
+}
+
+fn drive(dir: &Path) -> HostDrive {
+    HostDrive::new(dir.to_str().unwrap()).unwrap()
+}
+
+#[test]
+fn writes_and_reads_back_a_file() {
+    let dir = temp_dir("write");
+    let mut host = drive(&dir);
+    let mut state = State8080::new();
+    set_fcb(&mut state, FCB, b"HELLO   TXT");
+    assert_eq!(host.bdos(22, &mut state, FCB, DMA), 0);
+    for record in 0..3u8 {
+        state.memory[DMA as usize..DMA as usize + 128].fill(b'a' + record);
+        assert_eq!(host.bdos(21, &mut state, FCB, DMA), 0);
+    }
+    assert_eq!(host.bdos(16, &mut state, FCB, DMA), 0);
+    assert_eq!(fs::metadata(dir.join("HELLO.TXT")).unwrap().len(), 3 * 128);
+
+    set_fcb(&mut state, FCB, b"HELLO   TXT");
+    assert_eq!(host.bdos(15, &mut state, FCB, DMA), 0);
+    for record in 0..3u8 {
+        assert_eq!(host.bdos(20, &mut state, FCB, DMA), 0);
+        assert_eq!(state.memory[DMA as usize], b'a' + record);
+    }
+    assert_eq!(host.bdos(20, &mut state, FCB, DMA), 1, "reading past the end");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lists_every_extent_of_a_big_file() {
+    let dir = temp_dir("extents");
+    // 40K: two full 16K extents and 64 records in a third
+    fs::write(dir.join("BIG.DAT"), vec![0; 40 * 1024]).unwrap();
+    let mut host = drive(&dir);
+    let mut state = State8080::new();
+    set_fcb(&mut state, FCB, b"???????????");
+    let mut extents = Vec::new();
+    let mut result = host.bdos(17, &mut state, FCB, DMA);
+    while result != 0xff {
+        let entry = &state.memory[DMA as usize + 32 * result as usize..][..32];
+        assert_eq!(&entry[1..12], b"BIG     DAT");
+        extents.push((entry[12], entry[15]));
+        result = host.bdos(18, &mut state, FCB, DMA);
+    }
+    assert_eq!(extents, [(0, 128), (1, 128), (2, 64)]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn a_missing_file_is_an_error_for_the_program() {
+    let dir = temp_dir("missing");
+    let mut host = drive(&dir);
+    let mut state = State8080::new();
+    set_fcb(&mut state, FCB, b"NOTHERE TXT");
+    assert_eq!(host.bdos(15, &mut state, FCB, DMA), 0xff);
+    assert_eq!(host.bdos(20, &mut state, FCB, DMA), 0xff);
+    assert_eq!(host.bdos(19, &mut state, FCB, DMA), 0xff);
+    // the file going after it was found
+    fs::write(dir.join("GONE.TXT"), b"x").unwrap();
+    set_fcb(&mut state, FCB, b"GONE    TXT");
+    assert_eq!(host.bdos(15, &mut state, FCB, DMA), 0);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(host.bdos(20, &mut state, FCB, DMA), 0xff);
+}
+
+#[test]
+fn names_cant_leave_the_directory() {
+    let dir = temp_dir("traversal");
+    let inside = dir.join("inside");
+    fs::create_dir_all(&inside).unwrap();
+    let mut host = drive(&inside);
+    let mut state = State8080::new();
+    for name in [b"../../AB   ", b"..\\AB      ", b"../OUT  TXT", b"A/B     TXT"] {
+        set_fcb(&mut state, FCB, name);
+        assert_eq!(host.bdos(22, &mut state, FCB, DMA), 0xff, "making {}", String::from_utf8_lossy(name));
+    }
+    // renaming to one
+    fs::write(inside.join("OK.TXT"), b"x").unwrap();
+    set_fcb(&mut state, FCB, b"OK      TXT");
+    for (i, c) in b"../OUT  TXT".iter().enumerate() {
+        state.set_mem(FCB + 17 + i as u16, *c);
+    }
+    assert_eq!(host.bdos(23, &mut state, FCB, DMA), 0xff);
+    assert!(inside.join("OK.TXT").exists());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "only the directory itself");
+    assert_eq!(fs::read_dir(&inside).unwrap().count(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn an_fcb_at_the_top_of_memory_wraps() {
+    let dir = temp_dir("wrap");
+    let mut host = drive(&dir);
+    let mut state = State8080::new();
+    let fcb = 0xfff0;
+    set_fcb(&mut state, fcb, b"TOP     TXT");
+    assert_eq!(host.bdos(22, &mut state, fcb, DMA), 0);
+    assert_eq!(host.bdos(21, &mut state, fcb, DMA), 0);
+    assert_eq!(host.bdos(33, &mut state, fcb, DMA), 0);
+    assert!(dir.join("TOP.TXT").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,237 @@
+// the JIT against the interpreter: the same programs from the same state, run for the same cycles, must end in the
+// same state. only built with the jit feature:
+//
+//     cargo test --features jit --test jit
+#![cfg(all(feature = "jit", target_arch = "x86_64"))]
+
+use emulator_8080::assembler;
+use emulator_8080::cpu::State8080;
+use emulator_8080::jit::Jit;
+use emulator_8080::machine;
+use emulator_8080::machine::Machine;
+
+// a machine that logs what is written to its ports, and reads a count that goes up with every IN
+#[derive(Default)]
+struct Ports {
+    outputs: Vec<(u8, u8)>,
+    count: u8,
+}
+
+impl Machine for Ports {
+    fn input(&mut self, port: u8) -> u8 {
+        self.count = self.count.wrapping_add(port);
+        self.count
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        self.outputs.push((port, value));
+    }
+}
+
+// xorshift, so the random programs are the same every run
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn byte(&mut self) -> u8 {
+        self.next() as u8
+    }
+}
+
+// runs the way the JIT does, one instruction at a time until at least the cycles have passed
+fn interpret(state: &mut State8080, machine: &mut Ports, cycles: u64) -> u64 {
+    let mut run = 0;
+    while run < cycles {
+        run += machine::step(state, machine) as u64;
+    }
+    run
+}
+
+fn assert_same(interpreted: &State8080, translated: &State8080, what: &str) {
+    assert_eq!(interpreted.registers(), translated.registers(), "{}", what);
+    assert_eq!(interpreted.get_psw(), translated.get_psw(), "{}", what);
+    assert_eq!((interpreted.int_enable, interpreted.halted), (translated.int_enable, translated.halted), "{}", what);
+    if interpreted.memory != translated.memory {
+        let addr = (0..=0xffff).find(|&addr| interpreted.memory[addr] != translated.memory[addr]).unwrap();
+        panic!("{}: memory differs first at {:04x}", what, addr);
+    }
+}
+
+// runs both from the state for the slices of cycles, with an interrupt between slices when there's one given
+fn compare(state: State8080, slices: &[(u64, Option<u8>)]) -> (Jit, Ports) {
+    let mut interpreted = state.clone();
+    let mut translated = state;
+    let mut interpreter_ports = Ports::default();
+    let mut jit_ports = Ports::default();
+    let mut jit = Jit::new().unwrap();
+    for (i, &(cycles, interrupt)) in slices.iter().enumerate() {
+        let expected = interpret(&mut interpreted, &mut interpreter_ports, cycles);
+        let run = jit.run(&mut translated, &mut jit_ports, cycles);
+        let what = format!("slice {}", i);
+        assert_eq!(expected, run, "{}", what);
+        assert_same(&interpreted, &translated, &what);
+        assert_eq!(interpreter_ports.outputs, jit_ports.outputs, "{}", what);
+        if let Some(n) = interrupt {
+            assert_eq!(machine::interrupt(&mut interpreted, n), jit.interrupt(&mut translated, n));
+        }
+    }
+    (jit, jit_ports)
+}
+
+#[test]
+fn random_programs() {
+    let mut random = Random(0x8080_2024_cafe_f00d);
+    let mut translated = 0;
+    for program in 0..200 {
+        let mut state = State8080::new();
+        random.next();
+        for byte in state.memory.iter_mut() {
+            *byte = random.byte();
+        }
+        // mostly instructions that can be translated, so there are long blocks, with the others mixed in
+        for addr in 0..0x400 {
+            let mut opcode = random.byte();
+            while opcode >= 0xc0 && opcode & 7 != 6 && !random.next().is_multiple_of(4) {
+                opcode = random.byte();
+            }
+            state.memory[addr] = opcode;
+        }
+        state.set_psw(random.byte());
+        (state.a, state.b, state.c, state.d, state.e) = (random.byte(), random.byte(), 0, random.byte(), 0);
+        // hl and bc point into the program, so stores through them modify it
+        (state.h, state.l, state.b, state.c) = (random.byte() & 3, random.byte(), random.byte() & 3, random.byte());
+        state.sp = random.next() as u16;
+        state.int_enable = random.next().is_multiple_of(2);
+
+        let slices: Vec<(u64, Option<u8>)> =
+            (0..20).map(|_| (random.next() % 500, Some(random.byte() & 7).filter(|_| random.next().is_multiple_of(3)))).collect();
+        let (jit, _) = std::panic::catch_unwind(|| compare(state, &slices))
+            .unwrap_or_else(|_| panic!("program {} ran differently", program));
+        translated += jit.translated;
+    }
+    assert!(translated > 10_000, "only {} instructions were run as native code", translated);
+}
+
+// every ALU instruction on every pair of operands, with carry both clear and set
+#[test]
+fn every_alu_result() {
+    let mut state = State8080::new();
+    let mut program = Vec::new();
+    for opcode in (0x80..=0xbf).filter(|opcode| opcode & 7 == 0).chain([0xc6, 0xce, 0xd6, 0xde, 0xe6, 0xee, 0xf6, 0xfe]) {
+        program.push(opcode);
+        if opcode >= 0xc0 {
+            program.push(0x3c);
+        }
+        // and INR A, DCR B, CMC and the rotates, on whatever it left
+        program.extend_from_slice(&[0x3c, 0x05, 0x3f, 0x07, 0x17, 0x0f, 0x1f]);
+    }
+    program.push(0x76);
+    state.memory[..program.len()].copy_from_slice(&program);
+    let mut jit = Jit::new().unwrap();
+    for a in 0..=255u8 {
+        for b in (0..=255u8).step_by(3) {
+            for carry in [0, 1] {
+                let mut state = state.clone();
+                state.pc = 0;
+                (state.a, state.b) = (a, b);
+                state.set_psw(carry);
+                let mut interpreted = state.clone();
+                let mut translated = state;
+                let expected = interpret(&mut interpreted, &mut Ports::default(), 2_000);
+                assert_eq!(expected, jit.run(&mut translated, &mut Ports::default(), 2_000));
+                assert_same(&interpreted, &translated, &format!("a {:02x} b {:02x} carry {}", a, b, carry));
+                assert!(translated.halted);
+            }
+        }
+    }
+}
+
+// a loop that rewrites its own MVI each time round, so the JIT has to throw the code away and translate it again
+const SELF_MODIFYING: &str = "
+        ORG 0
+        LXI SP,0f000h
+        LXI H,0
+loop:   MVI A,0
+        INR A
+        STA loop+1
+        MOV E,A
+        MVI D,0
+        DAD D
+        CPI 200
+        JNZ loop
+        SHLD 2000h
+        HLT
+";
+
+#[test]
+fn self_modifying_code() {
+    let mut state = State8080::new();
+    assembler::assemble(SELF_MODIFYING).unwrap().program.load(&mut state);
+    let (jit, _) = compare(state.clone(), &[(100_000, None)]);
+    assert!(jit.translated > 0);
+
+    let mut jit = Jit::new().unwrap();
+    jit.run(&mut state, &mut Ports::default(), 100_000);
+    // 1 + 2 + ... + 200
+    assert_eq!((state.memory[0x2000], state.memory[0x2001]), (0x84, 0x4e));
+    assert!(state.halted);
+}
+
+// ports and interrupts go through the interpreter, with the blocks around them translated
+const PORTS: &str = "
+        ORG 0
+        JMP start
+        ORG 8
+        PUSH PSW
+        IN 3
+        OUT 2
+        POP PSW
+        EI
+        RET
+start:  LXI SP,0f000h
+        EI
+        MVI B,0
+loop:   IN 1
+        ADD B
+        MOV B,A
+        RLC
+        OUT 1
+        INX H
+        MOV M,B
+        DCR C
+        JNZ loop
+        HLT
+        JMP loop
+";
+
+#[test]
+fn ports_and_interrupts() {
+    let mut state = State8080::new();
+    assembler::assemble(PORTS).unwrap().program.load(&mut state);
+    let slices: Vec<(u64, Option<u8>)> = (0..200).map(|i| (97 + i * 13 % 101, Some(1))).collect();
+    let (jit, ports) = compare(state, &slices);
+    assert!(jit.translated > 0);
+    assert!(ports.outputs.iter().any(|&(port, _)| port == 2));
+}
+
+// memory written from outside has to be invalidated by whoever wrote it
+#[test]
+fn invalidate() {
+    let mut state = State8080::new();
+    // MVI A,1; JMP 0
+    state.memory[..5].copy_from_slice(&[0x3e, 0x01, 0xc3, 0x00, 0x00]);
+    let mut jit = Jit::new().unwrap();
+    jit.run(&mut state, &mut Ports::default(), 100);
+    assert_eq!(state.a, 1);
+
+    state.memory[1] = 2;
+    jit.invalidate(1);
+    jit.run(&mut state, &mut Ports::default(), 100);
+    assert_eq!(state.a, 2);
+}
@@ -0,0 +1,62 @@
+// the tracing disassembler separates code from the data around it, and labels the places the code branches to
+use emulator_8080::listing;
+use emulator_8080::listing::Byte;
+use emulator_8080::loader;
+
+// JMP start; msg: "Hi$",0; start: LXI D,msg; CALL 5; JZ done; RST 1; done: HLT; (undocumented NOP); JMP 0100h
+const PROGRAM: [u8; 22] = [
+    0xc3, 0x07, 0x01, b'H', b'i', b'$', 0x00, 0x11,
+    0x03, 0x01, 0xcd, 0x05, 0x00, 0xca, 0x11, 0x01,
+    0xcf, 0x76, 0x08, 0xc3, 0x00, 0x01,
+];
+
+#[test]
+fn data_between_code_is_not_disassembled() {
+    let program = loader::binary(&PROGRAM, 0x0100).unwrap();
+    let disassembly = listing::disassemble(&program, &[0x0100]);
+    assert_eq!(disassembly.bytes[3..7], [Byte::Data; 4]);
+    assert_eq!(disassembly.bytes[7..10], [Byte::Opcode, Byte::Operand, Byte::Operand]);
+    // the branch targets inside the program get labels; CALL 5 and RST 1 go outside it
+    assert_eq!(disassembly.labels.iter().copied().collect::<Vec<u16>>(), [0x0100, 0x0107, 0x0111]);
+}
+
+#[test]
+fn listing_uses_labels_and_data() {
+    let program = loader::binary(&PROGRAM, 0x0100).unwrap();
+    let text = listing::listing(&program, &[0x0100]);
+    let lines: Vec<&str> = text.lines().map(|line| line.split(';').next().unwrap().trim_end()).collect();
+    for expected in [
+        "        ORG 0100h",
+        "L0100:  JMP L0107",
+        "        DB 48h,69h,24h,00h",
+        "L0107:  LXI D,0103h",
+        "        CALL 0005h",
+        "        JZ L0111",
+        "L0111:  HLT",
+        "        DB 08h",
+        "        JMP L0100",
+        "        END",
+    ] {
+        assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, text);
+    }
+}
+
+#[test]
+fn unreachable_bytes_stay_data() {
+    // RET, then bytes that look like a JMP but are never reached
+    let program = loader::binary(&[0xc9, 0xc3, 0x00, 0x00], 0).unwrap();
+    let disassembly = listing::disassemble(&program, &[0]);
+    assert_eq!(disassembly.bytes, [Byte::Opcode, Byte::Data, Byte::Data, Byte::Data]);
+}
+
+#[test]
+fn a_conditional_call_reaches_its_subroutine() {
+    // CNZ sub; JMP 0003h; NOP; sub: INR A; RET
+    let program = loader::binary(&[0xc4, 0x07, 0x00, 0xc3, 0x03, 0x00, 0x00, 0x3c, 0xc9], 0).unwrap();
+    let disassembly = listing::disassemble(&program, &[0]);
+    assert_eq!(disassembly.bytes[6..], [Byte::Data, Byte::Opcode, Byte::Opcode]);
+    assert!(disassembly.labels.contains(&0x0007));
+    let text = listing::listing(&program, &[0]);
+    assert!(text.contains("CNZ L0007"), "{}", text);
+    assert!(text.contains("L0007:  INR A"), "{}", text);
+}
@@ -0,0 +1,44 @@
+// the program loaders accept well-formed files and reject everything else with an error
+use emulator_8080::cpu::State8080;
+use emulator_8080::loader;
+
+#[test]
+fn hexdump() {
+    let program = loader::hexdump("0000000 3100 2400 c3d4 0018\n0000008\n").unwrap();
+    assert_eq!(program.origin, 0);
+    assert_eq!(program.bytes, [0x31, 0x00, 0x24, 0x00, 0xc3, 0xd4, 0x00, 0x18]);
+
+    assert!(loader::hexdump("0000000 31zz\n").is_err());
+    assert!(loader::hexdump("0000000 123456\n").is_err());
+    // 64K and one more word is too much
+    let line = "0000000 0000 0000 0000 0000 0000 0000 0000 0000\n";
+    assert!(loader::hexdump(&line.repeat(4096)).is_ok());
+    assert!(loader::hexdump(&(line.repeat(4096) + "0010000 0000\n")).is_err());
+}
+
+#[test]
+fn intel_hex() {
+    let text = ":0301000021FF0FCD\n:010108007680\n:00000001FF\n";
+    let program = loader::intel_hex(text).unwrap();
+    assert_eq!(program.origin, 0x0100);
+    assert_eq!(program.bytes, [0x21, 0xff, 0x0f, 0, 0, 0, 0, 0, 0x76]);
+
+    let mut state = State8080::new();
+    program.load(&mut state);
+    assert_eq!(state.memory[0x0100..0x0103], [0x21, 0xff, 0x0f]);
+    assert_eq!(state.memory[0x0108], 0x76);
+
+    // a bad checksum, a short record, a missing colon and data past 0xffff
+    assert!(loader::intel_hex(":0301000021FF0FCE\n").is_err());
+    assert!(loader::intel_hex(":03010000\n").is_err());
+    assert!(loader::intel_hex("0301000021FF0FCD\n").is_err());
+    assert!(loader::intel_hex(":02FFFF000102FD\n").is_err());
+}
+
+#[test]
+fn binary() {
+    let program = loader::binary(&[0xc3, 0x00, 0x01], 0x0100).unwrap();
+    assert_eq!(program.origin, 0x0100);
+    assert!(loader::binary(&[0; 0x10000], 0).is_ok());
+    assert!(loader::binary(&[0; 0x10000], 1).is_err());
+}
@@ -0,0 +1,122 @@
+// movies of space invaders games, replayed to the same machine state frame by frame
+use emulator_8080::assembler;
+use emulator_8080::cpu::State8080;
+use emulator_8080::invaders;
+use emulator_8080::invaders::Dips;
+use emulator_8080::invaders::Invaders;
+use emulator_8080::movie;
+use emulator_8080::movie::Movie;
+
+// a little game: the interrupts count frames, and the main loop adds up port 1 and the shift register into memory
+const GAME: &str = "
+        ORG 0
+        LXI SP,2400h
+        EI
+        JMP main
+        ORG 8
+        PUSH H
+        LXI H,2010h
+        INR M
+        POP H
+        EI
+        RET
+        ORG 10h
+        EI
+        RET
+main:   IN 1
+        OUT 4
+        MOV B,A
+        LDA 2000h
+        ADD B
+        STA 2000h
+        IN 3
+        STA 2001h
+        IN 2
+        STA 2002h
+        JMP main
+";
+
+fn power_on() -> (State8080, Invaders, Vec<u8>) {
+    let assembly = assembler::assemble(GAME).unwrap();
+    let mut state = State8080::new();
+    assembly.program.load(&mut state);
+    (state, Invaders::default(), assembly.program.bytes)
+}
+
+// records a few frames of pressing buttons
+fn record() -> (Movie, State8080) {
+    let (mut state, mut machine, rom) = power_on();
+    machine.dips = Dips { lives: 5, bonus_at_1000: true, coin_info: true };
+    let mut movie = Movie::new(&rom, machine.dips);
+    for frame in 0..20u8 {
+        machine.port1 = if frame % 3 == 0 { invaders::P1_FIRE } else { invaders::P1_LEFT | invaders::COIN };
+        machine.port2 = frame & invaders::P2_RIGHT;
+        movie.record(&mut state, &mut machine);
+    }
+    (movie, state)
+}
+
+#[test]
+fn crc32_is_the_usual_one() {
+    assert_eq!(movie::crc32(b"123456789"), 0xcbf43926);
+    assert_eq!(movie::crc32(b""), 0);
+}
+
+#[test]
+fn a_replay_ends_in_the_same_state() {
+    let (recorded, end) = record();
+    assert_eq!(recorded.frames.len(), 20);
+    assert_eq!(end.memory[0x2010], 20);
+
+    let movie = Movie::read(&recorded.to_bytes()).unwrap();
+    assert_eq!(movie, recorded);
+    let (mut state, mut machine, _) = power_on();
+    assert_eq!(movie.replay(&mut state, &mut machine), Ok(None));
+    assert_eq!(state.registers(), end.registers());
+    assert!(state.memory == end.memory);
+    assert_eq!(machine.dips.lives, 5);
+}
+
+#[test]
+fn the_first_frame_that_goes_differently_is_found() {
+    let (mut movie, _) = record();
+    // a different button in frame 7 changes everything after it
+    movie.frames[7].port1 = 0;
+    let (mut state, mut machine, _) = power_on();
+    assert_eq!(movie.replay(&mut state, &mut machine), Ok(Some(7)));
+}
+
+#[test]
+fn a_movie_needs_the_same_rom() {
+    let (movie, _) = record();
+    let (mut state, mut machine, _) = power_on();
+    state.memory[0x0012] ^= 1;
+    let error = movie.replay(&mut state, &mut machine).err().unwrap();
+    assert!(error.contains("the ROM at 0000"), "{}", error);
+
+    let bytes = movie.to_bytes();
+    assert_eq!(Movie::read(b"8080SAVE\x01\x00").err().unwrap(), "not a movie");
+    assert!(Movie::read(&bytes[..bytes.len() - 3]).err().unwrap().contains("cut short"));
+    let mut old = bytes.clone();
+    old[8..10].copy_from_slice(&1u16.to_le_bytes());
+    assert!(Movie::read(&old).err().unwrap().contains("version 1, whose frames were a different length"));
+}
+
+#[test]
+fn recording_an_input_script() {
+    let script = movie::script("# insert a coin and start\n3\n2 coin\n\n4 p1-start  # one player\n5 P1-LEFT p1-fire tilt\n").unwrap();
+    assert_eq!(script.len(), 4);
+    assert_eq!(script[3], movie::Held { frames: 5, port1: invaders::P1_LEFT | invaders::P1_FIRE, port2: invaders::TILT });
+
+    let (mut state, mut machine, rom) = power_on();
+    let mut movie = Movie::new(&rom, machine.dips);
+    movie.record_script(&script, &mut state, &mut machine);
+    assert_eq!(movie.frames.len(), 14);
+    assert_eq!((movie.frames[0].port1, movie.frames[3].port1, movie.frames[5].port1), (0, invaders::COIN, invaders::P1_START));
+    let (mut state, mut machine, _) = power_on();
+    assert_eq!(movie.replay(&mut state, &mut machine), Ok(None));
+
+    let error = movie::script("10 coin\n5 jump\n").err().unwrap();
+    assert!(error.starts_with("line 2: 'jump' isn't a button"), "{}", error);
+    assert_eq!(movie::script("soon coin").err().unwrap(), "line 1: 'soon' isn't a number of frames");
+}
@@ -0,0 +1,188 @@
+// going backwards in the debugger: stepping back, and going back to the last breakpoint or write
+use emulator_8080::assembler;
+use emulator_8080::debugger::Debugger;
+use emulator_8080::gdb::Stub;
+use emulator_8080::invaders::Invaders;
+use emulator_8080::machine::Machine;
+use emulator_8080::reverse::Recorder;
+
+// reads a different value every time, so going back only works if the values read the first time are replayed
+#[derive(Default)]
+struct Counter {
+    reads: u8,
+    written: Vec<u8>,
+}
+
+impl Machine for Counter {
+    fn input(&mut self, _port: u8) -> u8 {
+        self.reads += 1;
+        self.reads * 10
+    }
+
+    fn output(&mut self, _port: u8, value: u8) {
+        self.written.push(value);
+    }
+}
+
+// stores four values read from port 1 at 2000h-2003h, then overwrites 2002h
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,100h
+        LXI H,2000h
+        MVI B,4
+loop:   IN 1
+        MOV M,A
+        INX H
+        DCR B
+        JNZ loop
+        MVI A,0ffh
+        STA 2002h
+        OUT 2
+        HLT
+";
+
+// records a snapshot every 3 instructions, so that going back has to cross several of them
+fn debugger() -> Debugger<Counter> {
+    let assembly = assembler::assemble(PROGRAM).unwrap();
+    let mut debugger = Debugger::new(&assembly.program, Counter::default());
+    debugger.recorder = Recorder::new(3);
+    debugger
+}
+
+fn run<M: Machine>(debugger: &mut Debugger<M>, commands: &[&str]) -> String {
+    let mut out: Vec<u8> = Vec::new();
+    for command in commands {
+        assert!(debugger.command(command, &mut out).unwrap());
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn stepping_back_returns_to_the_same_state() {
+    let mut debugger = debugger();
+    run(&mut debugger, &["s 10"]);
+    let registers = debugger.state.registers();
+    let memory = debugger.state.memory.clone();
+    run(&mut debugger, &["s 7", "rs 7"]);
+    assert_eq!(debugger.state.registers(), registers);
+    assert!(debugger.state.memory == memory);
+    assert_eq!(debugger.recorder.count, 10);
+
+    // going forward again runs on the machine, which reads its next values
+    run(&mut debugger, &["c"]);
+    assert_eq!(debugger.state.memory[0x2000..0x2004], [10, 20, 0xff, 50]);
+    assert_eq!(debugger.machine.reads, 5);
+    // going back didn't cross the OUT, so the machine has only been sent it once
+    assert_eq!(debugger.machine.written, [0xff]);
+
+    let out = run(&mut debugger, &["rs 1000"]);
+    assert!(out.contains("back at the start"), "{}", out);
+    assert_eq!((debugger.state.pc, debugger.recorder.count), (0x0000, 0));
+}
+
+#[test]
+fn reverse_continue_finds_the_last_write() {
+    let mut debugger = debugger();
+    run(&mut debugger, &["c"]);
+    assert_eq!(debugger.state.memory[0x2002], 0xff);
+
+    let out = run(&mut debugger, &["rc write 2002"]);
+    assert!(out.contains("went back 3 instructions: 0012 wrote ff to 2002"), "{}", out);
+    assert_eq!(debugger.state.pc, 0x0012);
+    assert_eq!(debugger.state.memory[0x2002], 30);
+
+    // and the write before that one
+    let out = run(&mut debugger, &["rc write 2000-2003"]);
+    assert!(out.contains("000a wrote 28 to 2003"), "{}", out);
+    let out = run(&mut debugger, &["rc write 2002"]);
+    assert!(out.contains("000a wrote 1e to 2002"), "{}", out);
+    assert_eq!(debugger.state.memory[0x2002], 0);
+
+    let out = run(&mut debugger, &["rc write 2002"]);
+    assert!(out.contains("nothing to go back to"), "{}", out);
+    assert_eq!(debugger.state.memory[0x2002], 0);
+}
+
+#[test]
+fn reverse_continue_stops_at_breakpoints() {
+    let mut debugger = debugger();
+    run(&mut debugger, &["b 8", "c", "c", "delete 1", "c", "b 8"]);
+    assert!(debugger.state.halted);
+
+    // back to the last time round the loop, when b was 1, then the time before
+    let out = run(&mut debugger, &["rc"]);
+    assert!(out.contains("at 0008"), "{}", out);
+    assert_eq!((debugger.state.pc, debugger.state.b), (0x0008, 1));
+    run(&mut debugger, &["rc"]);
+    assert_eq!((debugger.state.pc, debugger.state.b), (0x0008, 2));
+
+    // going back doesn't count as hitting the breakpoint
+    assert_eq!(debugger.breakpoints.list[0].hits, 0);
+}
+
+#[test]
+fn changes_by_hand_are_kept_when_going_back() {
+    let mut debugger = debugger();
+    run(&mut debugger, &["s 4", "set b 1", "s 5", "rs 3"]);
+    assert_eq!(debugger.state.b, 1);
+    assert_eq!(debugger.state.pc, 0x000c);
+}
+
+#[test]
+fn gdb_can_go_backwards() {
+    let mut debugger = debugger();
+    let mut stub = Stub { debugger: &mut debugger };
+    let mut send = |packet: &str| stub.handle(packet, &mut || false).unwrap();
+    assert!(send("qSupported").contains("ReverseStep+;ReverseContinue+"));
+    assert_eq!(send("Z2,2002,1"), "OK");
+    assert_eq!(send("c"), "T05watch:2002;");
+    assert_eq!(send("c"), "T05watch:2002;");
+    assert_eq!(send("bs"), "S05");
+    assert_eq!(send("p5"), "1200");
+    assert_eq!(send("bc"), "S05");
+    assert_eq!(send("p5"), "0a00");
+    assert_eq!(send("bc"), "T05replaylog:begin;");
+    assert_eq!(send("p5"), "0000");
+}
+
+// shifts two pairs of bytes through the space invaders shift register, reading each result back with IN 3
+const SHIFTER: &str = "
+        ORG 0
+        MVI A,12h
+        OUT 4
+        MVI A,34h
+        OUT 4
+        MVI A,3
+        OUT 2
+        IN 3
+        STA 2000h
+        MVI A,56h
+        OUT 4
+        MVI A,6
+        OUT 2
+        IN 3
+        STA 2001h
+        HLT
+";
+
+#[test]
+fn going_back_puts_the_devices_back() {
+    let assembly = assembler::assemble(SHIFTER).unwrap();
+    let mut debugger = Debugger::new(&assembly.program, Invaders::default());
+    debugger.recorder = Recorder::new(4);
+    run(&mut debugger, &["s 8"]);
+    let devices = debugger.machine.save_devices();
+    assert_eq!((debugger.machine.shift, debugger.machine.shift_offset), (0x3412, 3));
+    run(&mut debugger, &["c"]);
+    let results = [debugger.state.memory[0x2000], debugger.state.memory[0x2001]];
+    assert_eq!(results, [0xa0, 0x8d]);
+    assert_eq!((debugger.machine.shift, debugger.machine.shift_offset), (0x5634, 6));
+
+    // back over the second pair of OUTs, to between two snapshots
+    run(&mut debugger, &["rs 7"]);
+    assert_eq!(debugger.machine.save_devices(), devices);
+    // and running forward again on the machine shifts the same bytes through
+    run(&mut debugger, &["c"]);
+    assert_eq!([debugger.state.memory[0x2000], debugger.state.memory[0x2001]], results);
+    assert_eq!((debugger.machine.shift, debugger.machine.shift_offset), (0x5634, 6));
+}
@@ -0,0 +1,82 @@
+// the rewind buffer: snapshots every frame, kept as changes to memory, that can be gone back through
+use emulator_8080::cpu::State8080;
+use emulator_8080::invaders::Invaders;
+use emulator_8080::machine::Machine;
+use emulator_8080::rewind;
+use emulator_8080::rewind::Rewind;
+
+// a frame that changes a few bytes of memory, a register and a device
+fn play(state: &mut State8080, machine: &mut Invaders, frame: u8) {
+    state.memory[0x2400 + frame as usize * 3] = frame;
+    state.memory[0x2000] = frame;
+    state.a = frame;
+    state.pc = 0x100 + frame as u16;
+    machine.output(4, frame);
+}
+
+#[test]
+fn deltas_turn_either_side_into_the_other() {
+    let old = vec![0u8; 0x10000];
+    let mut new = old.clone();
+    new[0] = 1;
+    new[3] = 2; // close enough to join the run before
+    new[0x8000] = 3;
+    new[0xffff] = 4;
+    let delta = rewind::delta(&old, &new);
+    // two runs of header and bytes, and a third of a single byte at each end
+    assert_eq!(delta.len(), (4 + 4) + (4 + 1) + (4 + 1));
+
+    let mut memory = old.clone();
+    rewind::apply(&mut memory, &delta);
+    assert!(memory == new);
+    rewind::apply(&mut memory, &delta);
+    assert!(memory == old);
+    assert!(rewind::delta(&old, &old).is_empty());
+}
+
+#[test]
+fn going_back_frame_by_frame() {
+    let mut state = State8080::new();
+    let mut machine = Invaders::default();
+    let mut buffer = Rewind::default();
+    let mut saved = Vec::new();
+    for frame in 0..10u8 {
+        play(&mut state, &mut machine, frame);
+        buffer.frame(&state, frame as u64 * 33333, &machine);
+        saved.push((state.registers(), state.memory.clone(), machine.clone()));
+    }
+    assert_eq!(buffer.len(), 10);
+    // one whole copy of memory, and small changes after it
+    assert!(buffer.bytes() < 0x10000 + 10 * 100, "{}", buffer.bytes());
+
+    play(&mut state, &mut machine, 99);
+    for frame in (0..10).rev() {
+        assert_eq!(buffer.back(&mut state, &mut machine), Some(frame as u64 * 33333));
+        let (registers, memory, devices) = &saved[frame];
+        assert_eq!(&state.registers(), registers);
+        assert!(&state.memory == memory);
+        assert_eq!(&machine, devices);
+    }
+    assert_eq!(buffer.back(&mut state, &mut machine), None);
+    assert_eq!(state.a, 0);
+}
+
+#[test]
+fn only_the_last_few_seconds_are_kept() {
+    let mut state = State8080::new();
+    let mut machine = Invaders::default();
+    // a snapshot every other frame, and at most three of them
+    let mut buffer = Rewind::new(3, 2);
+    for frame in 0..12u8 {
+        play(&mut state, &mut machine, frame);
+        buffer.frame(&state, frame as u64, &machine);
+    }
+    assert_eq!(buffer.len(), 3);
+    let mut cycles = Vec::new();
+    while let Some(cycle) = buffer.back(&mut state, &mut machine) {
+        cycles.push(cycle);
+        assert_eq!(state.a as u64, cycle);
+        assert_eq!(state.memory[0x2000] as u64, cycle);
+    }
+    assert_eq!(cycles, [10, 8, 6]);
+}
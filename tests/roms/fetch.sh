@@ -0,0 +1,8 @@
+#!/bin/sh
+# downloads the 8080 test programs the conformance tests run into this directory (or the one given)
+set -e
+dir=${1:-$(dirname "$0")}
+url=https://altairclone.com/downloads/cpu_tests
+for program in TST8080.COM CPUTEST.COM 8080PRE.COM 8080EXM.COM; do
+    curl -fsSL -o "$dir/$program" "$url/$program"
+done
@@ -0,0 +1,174 @@
+// running programs headless until a condition or a limit, and the JSON summaries of how they ended
+use emulator_8080::assembler;
+use emulator_8080::cpm;
+use emulator_8080::cpu::State8080;
+use emulator_8080::invaders;
+use emulator_8080::invaders::Invaders;
+use emulator_8080::machine::Bare;
+use emulator_8080::rewind::Rewind;
+use emulator_8080::run::Exit;
+use emulator_8080::run::Runner;
+use emulator_8080::run::Until;
+use emulator_8080::savestate;
+
+// counts b down from 3 into 2000h, printing a digit to port 1 each time round, then halts
+const COUNTDOWN: &str = "
+        ORG 0
+        MVI B,3
+loop:   MOV A,B
+        STA 2000h
+        ADI '0'
+        OUT 1
+        DCR B
+        JNZ loop
+        HLT
+";
+
+fn countdown() -> State8080 {
+    let mut state = State8080::new();
+    assembler::assemble(COUNTDOWN).unwrap().program.load(&mut state);
+    state
+}
+
+#[test]
+fn each_kind_of_condition() {
+    let conditions = [
+        (Until::Pc(0x000e), 0x000e),
+        (Until::Byte(0x2000, 2), 0x0006),
+        (Until::Output("32".to_string()), 0x000a),
+        (Until::Halt, 0x000f),
+    ];
+    for (until, pc) in conditions {
+        let mut state = countdown();
+        let mut runner = Runner::new();
+        runner.console = Some(1);
+        runner.until.push(until.clone());
+        let exit = runner.run(&mut state, &mut Bare).unwrap();
+        assert_eq!(exit, Exit::Met(until));
+        assert_eq!(state.pc, pc);
+        assert!(runner.passed(&exit));
+    }
+}
+
+#[test]
+fn limits_fail_a_run_that_was_waiting() {
+    let mut state = countdown();
+    let mut runner = Runner::new();
+    runner.cycles = Some(20);
+    runner.until.push(Until::Pc(0x1234));
+    let exit = runner.run(&mut state, &mut Bare).unwrap();
+    assert_eq!(exit, Exit::Cycles);
+    // the limit is checked before each instruction, so the STA that crosses it still runs
+    assert_eq!((runner.tracer.cycle, runner.instructions), (7 + 5 + 13, 3));
+    assert!(!runner.passed(&exit));
+
+    // with nothing to wait for, running until the cpu can't go on is a pass
+    let mut state = countdown();
+    let mut runner = Runner::new();
+    let exit = runner.run(&mut state, &mut Bare).unwrap();
+    assert_eq!(exit, Exit::Deadlocked);
+    assert!(runner.passed(&exit));
+
+    // halting with interrupts enabled but no frame interrupts to wake it is just as stuck
+    let mut state = State8080::new();
+    assembler::assemble("ORG 0\nEI\nHLT\n").unwrap().program.load(&mut state);
+    let mut runner = Runner::new();
+    runner.until.push(Until::Pc(0x1234));
+    assert_eq!(runner.run(&mut state, &mut Bare).unwrap(), Exit::Deadlocked);
+}
+
+#[test]
+fn the_summary_is_json() {
+    let mut state = countdown();
+    let mut runner = Runner::new();
+    runner.console = Some(1);
+    runner.until.push(Until::Halt);
+    let exit = runner.run(&mut state, &mut Bare).unwrap();
+    let summary: serde_json::Value = serde_json::from_str(&runner.summary(&state, &exit)).unwrap();
+    assert_eq!(summary.get("exit").and_then(|v| v.as_str()), Some("halt"));
+    assert_eq!(summary.get("output").and_then(|v| v.as_str()), Some("321"));
+    assert_eq!(summary.get("cycles").and_then(|v| v.as_u64()), Some(runner.tracer.cycle));
+    let registers = summary.get("registers").unwrap();
+    assert_eq!(registers.get("pc").and_then(|v| v.as_u64()), Some(0x000f));
+    assert_eq!(registers.get("b").and_then(|v| v.as_u64()), Some(0));
+    assert!(matches!(summary.get("flags").and_then(|flags| flags.get("z")), Some(serde_json::Value::Bool(true))));
+}
+
+#[test]
+fn cpm_programs_and_frames() {
+    let program = assembler::assemble("ORG 100h\nMVI C,9\nLXI D,text\nCALL 5\nRET\ntext: DB 'hello$'\n").unwrap();
+    let mut state = State8080::new();
+    cpm::load_com(&mut state, &program.program.bytes).unwrap();
+    let mut runner = Runner::new();
+    runner.cpm = true;
+    let exit = runner.run(&mut state, &mut Bare).unwrap();
+    assert_eq!((exit, runner.output.as_slice()), (Exit::Exited, &b"hello"[..]));
+
+    // the frame interrupts reach a program that waits for them
+    let program = assembler::assemble("ORG 0\nLXI SP,100h\nEI\nloop: HLT\nJMP loop\nORG 8\nINR B\nEI\nRET\nORG 10h\nINR C\nEI\nRET\n").unwrap();
+    let mut state = State8080::new();
+    program.program.load(&mut state);
+    let mut runner = Runner::new();
+    runner.frames = Some(3);
+    runner.frame_interrupts = true;
+    let exit = runner.run(&mut state, &mut Invaders::default()).unwrap();
+    assert_eq!(exit, Exit::Frames);
+    // the RST 2 at the end of the last frame has been taken, but its handler hasn't run yet
+    assert_eq!((state.b, state.c, state.pc), (3, 2, 0x10));
+    assert!(runner.tracer.cycle >= 3 * invaders::CYCLES_PER_FRAME);
+}
+
+#[test]
+fn going_back_frames_when_the_run_ends() {
+    // counts up through memory from 2000h as fast as it can
+    let counter = assembler::assemble("ORG 0\nLXI H,2000h\nloop: INR M\nJNZ loop\nINX H\nJMP loop\n").unwrap();
+    let run = |frames: u64, rewind: Option<Rewind>| {
+        let mut state = State8080::new();
+        counter.program.load(&mut state);
+        let mut runner = Runner::new();
+        runner.frames = Some(frames);
+        runner.rewind = rewind;
+        runner.run(&mut state, &mut Bare).unwrap();
+        (runner, state)
+    };
+
+    let (mut runner, mut state) = run(10, Some(Rewind::new(5, 1)));
+    assert_eq!(runner.back(&mut state, &mut Bare, 3), 3);
+    let (seven, seven_state) = run(7, None);
+    assert_eq!(runner.tracer.cycle, seven.tracer.cycle);
+    assert_eq!(state.registers(), seven_state.registers());
+    assert!(state.memory == seven_state.memory);
+
+    // only as far as the buffer goes, which holds the ends of frames 6 to 10
+    assert_eq!(runner.back(&mut state, &mut Bare, 10), 1);
+    assert_eq!(runner.tracer.cycle, run(6, None).0.tracer.cycle);
+    assert_eq!(runner.back(&mut state, &mut Bare, 1), 0);
+    // and without one, nowhere
+    let (mut runner, mut state) = run(2, None);
+    assert_eq!(runner.back(&mut state, &mut Bare, 1), 0);
+}
+
+#[test]
+fn limits_count_from_a_save_state() {
+    // counts up through memory from 2000h as fast as it can
+    let counter = assembler::assemble("ORG 0\nLXI H,2000h\nloop: INR M\nJNZ loop\nINX H\nJMP loop\n").unwrap();
+    let mut state = State8080::new();
+    counter.program.load(&mut state);
+    let mut runner = Runner::new();
+    runner.cycles = Some(1000);
+    assert_eq!(runner.run(&mut state, &mut Bare).unwrap(), Exit::Cycles);
+    let saved = savestate::save(&state, runner.tracer.cycle, &Bare);
+
+    // as run --state does
+    for (cycles, frames, exit, length) in [(Some(1000), None, Exit::Cycles, 1000), (None, Some(2), Exit::Frames, 2 * invaders::CYCLES_PER_FRAME)] {
+        let mut state = State8080::new();
+        let mut runner = Runner::new();
+        runner.tracer.cycle = savestate::load(&saved, &mut state, &mut Bare).unwrap();
+        let start = runner.tracer.cycle;
+        assert!(start >= 1000);
+        (runner.cycles, runner.frames) = (cycles, frames);
+        assert_eq!(runner.run(&mut state, &mut Bare).unwrap(), exit);
+        assert!(runner.instructions > 0);
+        assert!((length..length + 20).contains(&(runner.tracer.cycle - start)), "ran {} cycles", runner.tracer.cycle - start);
+    }
+}
@@ -0,0 +1,108 @@
+// save states, and the space invaders devices they save along with the cpu
+use emulator_8080::assembler;
+use emulator_8080::cpu::State8080;
+use emulator_8080::debugger::Debugger;
+use emulator_8080::invaders;
+use emulator_8080::invaders::Dips;
+use emulator_8080::invaders::Invaders;
+use emulator_8080::machine::Bare;
+use emulator_8080::machine::Machine;
+use emulator_8080::savestate;
+
+fn busy_state() -> State8080 {
+    let mut state = State8080::new();
+    (state.a, state.b, state.c, state.d, state.e, state.h, state.l) = (1, 2, 3, 4, 5, 6, 7);
+    (state.sp, state.pc) = (0x2400, 0x1a5c);
+    state.cc.z = true;
+    state.cc.cy = true;
+    state.int_enable = true;
+    state.memory[0x2000] = 0x99;
+    state.memory[0xffff] = 0x42;
+    state
+}
+
+#[test]
+fn everything_comes_back() {
+    let state = busy_state();
+    let mut machine = Invaders::new(Dips { lives: 5, bonus_at_1000: true, coin_info: false });
+    machine.port1 = invaders::P1_FIRE;
+    machine.output(4, 0xab);
+    machine.output(4, 0xcd);
+    machine.output(2, 3);
+    let bytes = savestate::save(&state, 123456, &machine);
+    assert_eq!(&bytes[..10], b"8080SAVE\x01\x00");
+
+    let mut loaded = State8080::new();
+    let mut loaded_machine = Invaders::default();
+    let cycle = savestate::load(&bytes, &mut loaded, &mut loaded_machine).unwrap();
+    assert_eq!(cycle, 123456);
+    assert_eq!(loaded.registers(), state.registers());
+    assert!(loaded.memory == state.memory);
+    assert!(loaded.cc.z && loaded.cc.cy && !loaded.cc.s);
+    assert_eq!(loaded_machine, machine);
+    assert_eq!(loaded_machine.input(3), machine.input(3));
+}
+
+#[test]
+fn bad_save_states_are_refused() {
+    let bytes = savestate::save(&busy_state(), 0, &Bare);
+    let mut state = State8080::new();
+
+    assert_eq!(savestate::load(b"hello", &mut state, &mut Bare).err().unwrap(), "not a save state");
+    assert!(savestate::load(&bytes[..bytes.len() - 1], &mut state, &mut Bare).err().unwrap().contains("cut short"));
+
+    let mut newer = bytes.clone();
+    newer[8] = 2;
+    assert!(savestate::load(&newer, &mut state, &mut Bare).err().unwrap().contains("newer"));
+
+    // a machine won't take another machine's devices, and nothing is loaded when that happens
+    let invaders = savestate::save(&busy_state(), 0, &Invaders::default());
+    assert!(savestate::load(&invaders, &mut state, &mut Bare).is_err());
+    assert_eq!(state.pc, 0);
+}
+
+#[test]
+fn chunks_from_later_versions_are_skipped() {
+    let mut bytes = savestate::save(&busy_state(), 7, &Bare);
+    bytes.extend_from_slice(b"NEW \x03\x00\x00\x00abc");
+    let mut state = State8080::new();
+    assert_eq!(savestate::load(&bytes, &mut state, &mut Bare), Ok(7));
+    assert_eq!(state.pc, 0x1a5c);
+}
+
+#[test]
+fn the_shift_register_and_input_ports() {
+    let mut machine = Invaders::default();
+    machine.output(4, 0x12);
+    machine.output(4, 0x34);
+    // 3412 shifted left by the offset, taking the top byte
+    assert_eq!(machine.input(3), 0x34);
+    machine.output(2, 4);
+    assert_eq!(machine.input(3), 0x41);
+    machine.output(2, 0x0f);
+    assert_eq!(machine.input(3), 0x09);
+
+    machine.port1 = invaders::COIN | invaders::P1_LEFT;
+    assert_eq!(machine.input(1), 0x29);
+    machine.port2 = invaders::P2_FIRE | invaders::TILT;
+    machine.dips = Dips { lives: 6, bonus_at_1000: false, coin_info: true };
+    assert_eq!(machine.input(2), 0x17);
+    machine.dips.coin_info = false;
+    assert_eq!(Dips::from_bits(machine.dips.bits()), machine.dips);
+}
+
+#[test]
+fn saved_and_loaded_in_the_debugger() {
+    let assembly = assembler::assemble("ORG 0\nMVI A,5\nOUT 4\nINR A\nJMP 2\n").unwrap();
+    let mut debugger = Debugger::new(&assembly.program, Invaders::default());
+    let path = std::env::temp_dir().join(format!("savestate-{}.sav", std::process::id()));
+    let mut out: Vec<u8> = Vec::new();
+    for command in ["s 3", &format!("save {}", path.display()), "s 6", &format!("load {}", path.display())] {
+        debugger.command(command, &mut out).unwrap();
+    }
+    std::fs::remove_file(&path).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("saved at cycle 22"), "{}", out);
+    assert_eq!((debugger.state.a, debugger.state.pc, debugger.tracer.cycle), (6, 0x0005, 22));
+    assert_eq!(debugger.machine.shift, 0x0500);
+}
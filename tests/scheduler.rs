@@ -0,0 +1,136 @@
+// running the cpu by the clock, with callbacks at the cycles they were scheduled for
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use emulator_8080::assembler;
+use emulator_8080::cpu::State8080;
+use emulator_8080::machine;
+use emulator_8080::machine::Bare;
+use emulator_8080::scheduler::Scheduler;
+
+// waits for interrupts, counting the RST 1s at 2000h and the RST 2s at 2001h
+const COUNTER: &str = "
+        ORG 0
+        JMP start
+        ORG 8
+        PUSH H
+        LXI H,2000h
+        INR M
+        POP H
+        EI
+        RET
+        ORG 10h
+        PUSH H
+        LXI H,2001h
+        INR M
+        POP H
+        EI
+        RET
+start:  LXI SP,0f000h
+        EI
+wait:   HLT
+        JMP wait
+";
+
+fn counter() -> State8080 {
+    let mut state = State8080::new();
+    assembler::assemble(COUNTER).unwrap().program.load(&mut state);
+    state
+}
+
+#[test]
+fn runs_to_the_same_instruction_as_stepping() {
+    let mut stepped = counter();
+    let mut cycles = 0;
+    while cycles < 1000 {
+        cycles += machine::step(&mut stepped, &mut Bare) as u64;
+    }
+
+    let mut state = counter();
+    let mut scheduler = Scheduler::new();
+    let mut run = 0;
+    for budget in [1, 10, 100, 889] {
+        run += scheduler.run_cycles(&mut state, &mut Bare, budget);
+        assert_eq!(run, scheduler.cycle);
+    }
+    assert!(run >= 1000);
+    let mut rest = stepped.clone();
+    while cycles < run {
+        cycles += machine::step(&mut rest, &mut Bare) as u64;
+    }
+    assert_eq!(cycles, run);
+    assert_eq!(rest.registers(), state.registers());
+}
+
+#[test]
+fn callbacks_come_at_their_cycles() {
+    let mut state = counter();
+    let mut scheduler = Scheduler::new();
+    let calls: Rc<RefCell<Vec<(&str, u64)>>> = Rc::default();
+    for (name, at) in [("late", 500), ("early", 100), ("also late", 500), ("cancelled", 300), ("start", 0)] {
+        let calls = calls.clone();
+        let id = scheduler.at(at, move |_, _| calls.borrow_mut().push((name, at)));
+        if name == "cancelled" {
+            assert!(scheduler.cancel(id));
+            assert!(!scheduler.cancel(id));
+        }
+    }
+    assert_eq!(scheduler.next_event(), Some(0));
+    scheduler.run_until_cycle(&mut state, &mut Bare, 499);
+    assert_eq!(*calls.borrow(), [("start", 0), ("early", 100)]);
+    // what's due at the target is called before the run ends
+    scheduler.run_until_cycle(&mut state, &mut Bare, 500);
+    assert_eq!(calls.borrow().len(), 4);
+    assert_eq!(calls.borrow()[2..], [("late", 500), ("also late", 500)]);
+    assert_eq!(scheduler.next_event(), None);
+}
+
+#[test]
+fn interrupts_every_frame() {
+    let mut state = counter();
+    let mut scheduler = Scheduler::new();
+    // two interrupts a frame, like the space invaders video, for 10 frames
+    scheduler.every(1000, 2000, |state, _| {
+        machine::interrupt(state, 1);
+    });
+    scheduler.every(2000, 2000, |state, _| {
+        machine::interrupt(state, 2);
+    });
+    scheduler.run_until_cycle(&mut state, &mut Bare, 20_000);
+    // the last interrupt has been taken, but its handler hasn't run yet
+    assert_eq!((state.memory[0x2000], state.memory[0x2001]), (10, 9));
+    assert_eq!(state.pc, 0x10);
+    scheduler.run_until_cycle(&mut state, &mut Bare, 20_500);
+    assert_eq!((state.memory[0x2000], state.memory[0x2001]), (10, 10));
+    assert_eq!(scheduler.next_event(), Some(21_000));
+
+    // one at a set cycle
+    scheduler.interrupt_at(21_500, 2);
+    scheduler.run_cycles(&mut state, &mut Bare, 2000);
+    assert_eq!((state.memory[0x2000], state.memory[0x2001]), (11, 12));
+}
+
+#[test]
+fn catches_up_with_a_driver_that_steps_itself() {
+    let mut stepped = counter();
+    let mut cycles = 0;
+    let mut scheduler = Scheduler::new();
+    scheduler.every(1000, 2000, |state, _| {
+        machine::interrupt(state, 1);
+    });
+    while cycles < 10_000 {
+        cycles += machine::step(&mut stepped, &mut Bare) as u64;
+        scheduler.catch_up(&mut stepped, &mut Bare, cycles);
+    }
+
+    // the same as the scheduler running the cpu
+    let mut state = counter();
+    let mut run = Scheduler::new();
+    run.every(1000, 2000, |state, _| {
+        machine::interrupt(state, 1);
+    });
+    run.run_until_cycle(&mut state, &mut Bare, cycles);
+    assert_eq!(scheduler.cycle, run.cycle);
+    assert_eq!(stepped.registers(), state.registers());
+    assert_eq!(stepped.memory[0x2000], 5);
+}
@@ -0,0 +1,157 @@
+// runs the community "single step" test vectors: for each opcode, a file of cases that each give a starting state,
+// the state after one instruction and the bus cycles it took. the vectors aren't part of the repository, so the test
+// fails without them: fetch them with tests/single_step/fetch.sh (see tests/single_step/README.md).
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use emulator_8080::cpu::emulate;
+use emulator_8080::cpu::State8080;
+use serde_json::Value as Json;
+
+// failures reported for each file before the rest are only counted
+const REPORTED_FAILURES: usize = 5;
+
+fn field(state: &Json, name: &str) -> Result<u64, String> {
+    state.get(name).and_then(Json::as_u64).ok_or(format!("missing {}", name))
+}
+
+fn ram(state: &Json) -> Result<Vec<(u16, u8)>, String> {
+    let mut bytes = Vec::new();
+    for pair in state.get("ram").and_then(Json::as_array).ok_or("missing ram")? {
+        match pair.as_array().map(Vec::as_slice) {
+            Some([addr, val]) => match (addr.as_u64(), val.as_u64()) {
+                (Some(addr), Some(val)) => bytes.push((addr as u16, val as u8)),
+                _ => return Err("bad ram entry".to_string()),
+            },
+            _ => return Err("bad ram entry".to_string()),
+        }
+    }
+    Ok(bytes)
+}
+
+fn load(state: &mut State8080, initial: &Json) -> Result<(), String> {
+    state.pc = field(initial, "pc")? as u16;
+    state.sp = field(initial, "sp")? as u16;
+    state.a = field(initial, "a")? as u8;
+    state.b = field(initial, "b")? as u8;
+    state.c = field(initial, "c")? as u8;
+    state.d = field(initial, "d")? as u8;
+    state.e = field(initial, "e")? as u8;
+    state.h = field(initial, "h")? as u8;
+    state.l = field(initial, "l")? as u8;
+    state.set_psw(field(initial, "f")? as u8);
+    state.int_enable = false;
+    state.halted = false;
+    for (addr, val) in ram(initial)? {
+        state.set_mem(addr, val);
+    }
+    Ok(())
+}
+
+// returns a description of the first way the state differs from the expected one
+fn compare(state: &mut State8080, expected: &Json, cycles: u8, expected_cycles: usize) -> Result<Option<String>, String> {
+    let registers: [(&str, u64); 10] = [
+        ("pc", state.pc as u64),
+        ("sp", state.sp as u64),
+        ("a", state.a as u64),
+        ("b", state.b as u64),
+        ("c", state.c as u64),
+        ("d", state.d as u64),
+        ("e", state.e as u64),
+        ("h", state.h as u64),
+        ("l", state.l as u64),
+        ("f", state.get_psw() as u64),
+    ];
+    for (name, actual) in registers.iter() {
+        let wanted = field(expected, name)?;
+        if *actual != wanted {
+            if *name == "f" {
+                return Ok(Some(format!("f is {:08b}, expected {:08b} (sz0a0p1c)", actual, wanted)));
+            }
+            return Ok(Some(format!("{} is {:#x}, expected {:#x}", name, actual, wanted)));
+        }
+    }
+    for (addr, val) in ram(expected)? {
+        let actual = state.get_mem(addr);
+        if actual != val {
+            return Ok(Some(format!("memory at {:#06x} is {:#04x}, expected {:#04x}", addr, actual, val)));
+        }
+    }
+    if cycles as usize != expected_cycles {
+        return Ok(Some(format!("took {} cycles, expected {}", cycles, expected_cycles)));
+    }
+    Ok(None)
+}
+
+// runs every case in one file, and returns how many failed along with the first few failures
+fn run_file(state: &mut State8080, cases: &[Json]) -> Result<(usize, Vec<String>), String> {
+    let mut failed = 0;
+    let mut reported: Vec<String> = Vec::new();
+    for case in cases {
+        let name = case.get("name").and_then(Json::as_str).unwrap_or("?");
+        let initial = case.get("initial").ok_or("missing initial")?;
+        let expected = case.get("final").ok_or("missing final")?;
+        let expected_cycles = case.get("cycles").and_then(Json::as_array).ok_or("missing cycles")?.len();
+
+        load(state, initial)?;
+        // IN reads from the machine's ports, which the cpu alone doesn't have
+        if state.get_mem(state.pc) == 0xdb {
+            continue;
+        }
+        let cycles = emulate(state);
+        if let Some(difference) = compare(state, expected, cycles, expected_cycles)? {
+            failed += 1;
+            if reported.len() < REPORTED_FAILURES {
+                reported.push(format!("{}: {}", name, difference));
+            }
+        }
+
+        // clear the bytes this case touched so they can't leak into the next one
+        for (addr, _) in ram(initial)?.into_iter().chain(ram(expected)?) {
+            state.set_mem(addr, 0);
+        }
+    }
+    Ok((failed, reported))
+}
+
+#[test]
+fn single_step() {
+    let dir = match env::var("SINGLE_STEP_TESTS") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("single_step"),
+    };
+    let mut paths: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    assert!(
+        !paths.is_empty(),
+        "no .json files in {} (run tests/single_step/fetch.sh to download the test vectors)",
+        dir.display()
+    );
+    paths.sort();
+
+    let mut state = State8080::new();
+    let mut failures: Vec<String> = Vec::new();
+    for path in paths.iter() {
+        let file = path.file_name().unwrap().to_string_lossy();
+        let text = fs::read_to_string(path).expect("Failed to read test vectors");
+        let result = serde_json::from_str::<Json>(&text).map_err(|err| err.to_string()).and_then(|cases| {
+            let cases = cases.as_array().ok_or("expected an array of cases")?;
+            run_file(&mut state, cases)
+        });
+        match result {
+            Ok((0, _)) => {},
+            Ok((failed, reported)) => {
+                failures.push(format!("{}: {} cases failed", file, failed));
+                failures.extend(reported.into_iter().map(|failure| format!("    {}", failure)));
+            },
+            Err(error) => failures.push(format!("{}: {}", file, error)),
+        }
+    }
+    assert!(failures.is_empty(), "single step tests failed:\n{}", failures.join("\n"));
+}
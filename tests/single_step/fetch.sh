@@ -0,0 +1,9 @@
+#!/bin/sh
+# downloads the 8080 single step test vectors, one file per opcode, into this directory (or the one given)
+set -e
+dir=${1:-$(dirname "$0")}
+url=https://raw.githubusercontent.com/SingleStepTests/8080/main/v1
+for opcode in $(seq 0 255); do
+    file=$(printf "%02x.json" "$opcode")
+    curl -fsSL -o "$dir/$file" "$url/$file"
+done
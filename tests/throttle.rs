@@ -0,0 +1,80 @@
+// pacing a run to the speed of the real machine, and measuring the speed it ran at
+use std::time::Duration;
+use std::time::Instant;
+
+use emulator_8080::assembler;
+use emulator_8080::cpu::State8080;
+use emulator_8080::invaders;
+use emulator_8080::machine::Bare;
+use emulator_8080::run::Runner;
+use emulator_8080::throttle::Throttle;
+use emulator_8080::throttle::CLOCK_HZ;
+
+const SECOND: Duration = Duration::from_secs(1);
+
+fn close(a: Duration, b: Duration) -> bool {
+    a.abs_diff(b) < Duration::from_micros(10)
+}
+
+#[test]
+fn waits_for_the_host_to_catch_up() {
+    let start = Instant::now();
+    for (speed, frame) in [(1.0, SECOND / 60), (0.5, SECOND / 30), (4.0, SECOND / 240)] {
+        let mut throttle = Throttle::new(Some(speed));
+        assert_eq!(throttle.delay(start, 1000), Duration::ZERO);
+        // a 60th of a second of cycles, run in no time at all
+        let delay = throttle.delay(start, 1000 + CLOCK_HZ / 60);
+        assert!(close(delay, frame), "{:?} at {}x", delay, speed);
+        // run in just the right time
+        let delay = throttle.delay(start + frame, 1000 + CLOCK_HZ / 60);
+        assert!(close(delay, Duration::ZERO), "{:?} at {}x", delay, speed);
+    }
+
+    let mut throttle = Throttle::new(None);
+    throttle.delay(start, 0);
+    assert_eq!(throttle.delay(start, CLOCK_HZ), Duration::ZERO);
+}
+
+#[test]
+fn falling_behind_starts_again() {
+    let start = Instant::now();
+    let mut throttle = Throttle::new(Some(1.0));
+    throttle.delay(start, 0);
+    // half a second late, for a frame's worth of cycles
+    let late = start + SECOND / 2;
+    assert_eq!(throttle.delay(late, CLOCK_HZ / 60), Duration::ZERO);
+    // so it doesn't rush the next ones to make it up
+    let delay = throttle.delay(late, 2 * CLOCK_HZ / 60);
+    assert!(close(delay, SECOND / 60), "{:?}", delay);
+
+    // changing the speed starts from now too
+    throttle.set_speed(Some(2.0));
+    throttle.delay(late, 2 * CLOCK_HZ / 60);
+    assert!(close(throttle.delay(late, 3 * CLOCK_HZ / 60), SECOND / 120));
+}
+
+#[test]
+fn measures_the_speed_each_second() {
+    let start = Instant::now();
+    let mut throttle = Throttle::new(None);
+    assert_eq!(throttle.measure(start, 0), None);
+    assert_eq!(throttle.measure(start + SECOND / 2, CLOCK_HZ), None);
+    let percent = throttle.measure(start + SECOND, 3 * CLOCK_HZ / 2).unwrap();
+    assert!((percent - 150.0).abs() < 0.01, "{}", percent);
+    let percent = throttle.measure(start + 3 * SECOND, 5 * CLOCK_HZ / 2).unwrap();
+    assert!((percent - 50.0).abs() < 0.01, "{}", percent);
+    assert_eq!(throttle.percent, Some(percent));
+}
+
+#[test]
+fn a_run_at_the_real_speed_takes_real_time() {
+    let mut state = State8080::new();
+    assembler::assemble("ORG 0\nloop: JMP loop\n").unwrap().program.load(&mut state);
+    let mut runner = Runner::new();
+    runner.frames = Some(3);
+    runner.throttle.set_speed(Some(1.0));
+    let start = Instant::now();
+    runner.run(&mut state, &mut Bare).unwrap();
+    let frames = (3 * invaders::CYCLES_PER_FRAME) as f64 / CLOCK_HZ as f64;
+    assert!(start.elapsed() >= Duration::from_secs_f64(frames * 0.95), "{:?}", start.elapsed());
+}
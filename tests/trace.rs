@@ -0,0 +1,110 @@
+// the execution trace, and the filters that decide which instructions it writes
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use emulator_8080::assembler;
+use emulator_8080::cpu::State8080;
+use emulator_8080::debugger::Debugger;
+use emulator_8080::machine::Bare;
+use emulator_8080::trace::Tracer;
+
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,100h
+        MVI B,3
+loop:   DCR B
+        JNZ loop
+        HLT
+";
+
+// a writer the test can read back after the tracer is done with it
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Shared {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap().lines().map(str::to_string).collect()
+    }
+}
+
+// runs the program to its HLT with the tracer, and returns what it wrote
+fn run(tracer: &mut Tracer, out: &Shared) -> Vec<String> {
+    let mut state = State8080::new();
+    assembler::assemble(PROGRAM).unwrap().program.load(&mut state);
+    while !state.halted {
+        tracer.step(&mut state, &mut Bare).unwrap();
+    }
+    out.lines()
+}
+
+#[test]
+fn one_line_for_each_instruction() {
+    let out = Shared::default();
+    let mut tracer = Tracer::to(Box::new(out.clone()));
+    let lines = run(&mut tracer, &out);
+    assert_eq!(lines.len(), 9);
+    assert_eq!(lines[0], "PC=0000 AF=0002 BC=0000 DE=0000 HL=0000 SP=0000 CYC=0  31 00 01  LXI SP,0100h");
+    assert_eq!(lines[1], "PC=0003 AF=0002 BC=0000 DE=0000 HL=0000 SP=0100 CYC=10  06 03     MVI B,03h");
+    assert_eq!(lines[2], "PC=0005 AF=0002 BC=0300 DE=0000 HL=0000 SP=0100 CYC=17  05        DCR B");
+    assert_eq!(lines[8], "PC=0009 AF=0056 BC=0000 DE=0000 HL=0000 SP=0100 CYC=62  76        HLT");
+    assert_eq!(tracer.cycle, 69);
+}
+
+#[test]
+fn filtered_by_address_and_cycle() {
+    let out = Shared::default();
+    let mut tracer = Tracer::to(Box::new(out.clone()));
+    tracer.addresses = Some((0x0005, 0x0005));
+    let lines = run(&mut tracer, &out);
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| line.starts_with("PC=0005")));
+
+    let out = Shared::default();
+    let mut tracer = Tracer::to(Box::new(out.clone()));
+    tracer.cycles = Some((17, 38));
+    let lines = run(&mut tracer, &out);
+    let cycles: Vec<&str> = lines.iter().map(|line| line.split_whitespace().nth(6).unwrap()).collect();
+    assert_eq!(cycles, ["CYC=17", "CYC=22", "CYC=32", "CYC=37"]);
+}
+
+#[test]
+fn switched_off_it_only_counts_cycles() {
+    let out = Shared::default();
+    let mut tracer = Tracer::to(Box::new(out.clone()));
+    tracer.enabled = false;
+    assert!(run(&mut tracer, &out).is_empty());
+    assert_eq!(tracer.cycle, 69);
+}
+
+#[test]
+fn switched_on_and_off_in_the_debugger() {
+    let assembly = assembler::assemble(PROGRAM).unwrap();
+    let mut debugger = Debugger::new(&assembly.program, Bare);
+    let path = std::env::temp_dir().join(format!("trace-{}.log", std::process::id()));
+    let mut out: Vec<u8> = Vec::new();
+    for command in [&format!("trace {}", path.display()), "trace range 5-7", "s 4", "trace off", "c"] {
+        debugger.command(command, &mut out).unwrap();
+    }
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("tracing is on, from 0005 to 0007, at any cycle; 0 cycles so far"), "{}", out);
+    assert!(out.contains("tracing is off, from 0005 to 0007, at any cycle; 32 cycles so far"), "{}", out);
+
+    let trace = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("PC=0005 AF=0002 BC=0300"));
+    assert!(lines[1].starts_with("PC=0006 AF=0012 BC=0200"), "{}", trace);
+}
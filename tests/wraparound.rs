@@ -0,0 +1,80 @@
+// instructions at the top of memory, and stacks at the bottom, wrap around the 64K address space instead of panicking
+use emulator_8080::cpu::emulate;
+use emulator_8080::cpu::State8080;
+
+// runs one instruction, placed so that its last byte is at 0xffff
+fn run_at_top(program: &[u8]) -> State8080 {
+    let mut state = State8080::new();
+    let start = 0x10000 - program.len();
+    state.memory[start..].copy_from_slice(program);
+    state.pc = start as u16;
+    emulate(&mut state);
+    state
+}
+
+#[test]
+fn pc_wraps_past_the_top_of_memory() {
+    // NOP and LXI B at the end of memory go on to address 0
+    assert_eq!(run_at_top(&[0x00]).pc, 0x0000);
+    let state = run_at_top(&[0x01, 0x34, 0x12]);
+    assert_eq!((state.b, state.c, state.pc), (0x12, 0x34, 0x0000));
+
+    // a 3-byte instruction starting at 0xffff takes its operand from 0x0000 and 0x0001
+    let mut state = State8080::new();
+    state.memory[0xffff] = 0x21; // LXI H
+    state.memory[0x0000] = 0x78;
+    state.memory[0x0001] = 0x56;
+    state.pc = 0xffff;
+    emulate(&mut state);
+    assert_eq!((state.h, state.l, state.pc), (0x56, 0x78, 0x0002));
+}
+
+#[test]
+fn call_at_the_top_of_memory_returns_to_address_0() {
+    let mut state = run_at_top(&[0xcd, 0x00, 0x20]); // CALL 2000h
+    assert_eq!(state.pc, 0x2000);
+    let sp = state.sp;
+    assert_eq!((state.get_mem(sp), state.get_mem(sp.wrapping_add(1))), (0x00, 0x00));
+}
+
+#[test]
+fn stack_wraps_below_address_0() {
+    // PUSH B with sp at 0 writes to 0xffff and 0xfffe
+    let mut state = State8080::new();
+    state.memory[0x0100] = 0xc5;
+    state.pc = 0x0100;
+    state.sp = 0x0000;
+    state.b = 0xab;
+    state.c = 0xcd;
+    emulate(&mut state);
+    assert_eq!(state.sp, 0xfffe);
+    assert_eq!((state.memory[0xffff], state.memory[0xfffe]), (0xab, 0xcd));
+
+    // and RST 7 with sp at 1 pushes across the wrap
+    state.memory[0x0101] = 0xff;
+    state.sp = 0x0001;
+    emulate(&mut state);
+    assert_eq!((state.sp, state.pc), (0xffff, 0x0038));
+    assert_eq!((state.memory[0x0000], state.memory[0xffff]), (0x01, 0x02));
+}
+
+#[test]
+fn stack_wraps_past_the_top_of_memory() {
+    // POP D and XTHL with sp at 0xffff read the high byte from address 0
+    let mut state = State8080::new();
+    state.memory[0x0100] = 0xd1; // POP D
+    state.memory[0x0101] = 0xe3; // XTHL
+    state.memory[0xffff] = 0x11;
+    state.memory[0x0000] = 0x22;
+    state.pc = 0x0100;
+    state.sp = 0xffff;
+    emulate(&mut state);
+    assert_eq!((state.d, state.e, state.sp), (0x22, 0x11, 0x0001));
+
+    state.sp = 0xffff;
+    state.h = 0x33;
+    state.l = 0x44;
+    emulate(&mut state);
+    assert_eq!((state.h, state.l), (0x22, 0x11));
+    assert_eq!((state.memory[0x0000], state.memory[0xffff]), (0x33, 0x44));
+}